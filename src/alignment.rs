@@ -0,0 +1,271 @@
+use egui::Widget;
+
+use crate::{
+    error::{Error, Result},
+    i18n, monitor,
+    window_modifier::WindowInfo,
+};
+
+/// Where a window is placed inside the reference rect, row by row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    pub const ALL: [Self; 9] = [
+        Self::TopLeft,
+        Self::Top,
+        Self::TopRight,
+        Self::Left,
+        Self::Center,
+        Self::Right,
+        Self::BottomLeft,
+        Self::Bottom,
+        Self::BottomRight,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::TopLeft => i18n::tr("alignment.top_left"),
+            Self::Top => i18n::tr("alignment.top"),
+            Self::TopRight => i18n::tr("alignment.top_right"),
+            Self::Left => i18n::tr("alignment.left"),
+            Self::Center => i18n::tr("alignment.center"),
+            Self::Right => i18n::tr("alignment.right"),
+            Self::BottomLeft => i18n::tr("alignment.bottom_left"),
+            Self::Bottom => i18n::tr("alignment.bottom"),
+            Self::BottomRight => i18n::tr("alignment.bottom_right"),
+        }
+    }
+
+    /// the label of its button in the 3x3 grid
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::TopLeft => "↖",
+            Self::Top => "↑",
+            Self::TopRight => "↗",
+            Self::Left => "←",
+            Self::Center => "●",
+            Self::Right => "→",
+            Self::BottomLeft => "↙",
+            Self::Bottom => "↓",
+            Self::BottomRight => "↘",
+        }
+    }
+
+    /// `[horizontal, vertical]`, -1 for the left or top edge, 0 for the center and 1 for the
+    /// right or bottom edge
+    fn sides(self) -> [i32; 2] {
+        let index = Self::ALL.iter().position(|&anchor| anchor == self).unwrap() as i32;
+        [index % 3 - 1, index / 3 - 1]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reference {
+    /// the whole monitor of the window
+    Monitor,
+    /// the monitor of the window without the taskbar
+    WorkArea,
+    /// the visible frame of the chosen window
+    Window,
+}
+
+impl Reference {
+    pub const ALL: [Self; 3] = [Self::Monitor, Self::WorkArea, Self::Window];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Monitor => i18n::tr("alignment.monitor"),
+            Self::WorkArea => i18n::tr("alignment.work_area"),
+            Self::Window => i18n::tr("alignment.window"),
+        }
+    }
+}
+
+/// `[x, y]` of a frame of `size: [width, height]` aligned to `anchor` inside
+/// `reference: [x, y, width, height]`, `margin: [horizontal, vertical]` away from the anchored
+/// edges. The margin is ignored on a centered axis.
+pub fn aligned_position(
+    anchor: Anchor,
+    size: [i32; 2],
+    reference: [i32; 4],
+    margin: [i32; 2],
+) -> [i32; 2] {
+    let [x, y, width, height] = reference;
+    let align = |side: i32, start: i32, length: i32, size: i32, margin: i32| match side {
+        -1 => start + margin,
+        0 => start + (length - size) / 2,
+        _ => start + length - size - margin,
+    };
+    let [horizontal, vertical] = anchor.sides();
+    [
+        align(horizontal, x, width, size[0], margin[0]),
+        align(vertical, y, height, size[1], margin[1]),
+    ]
+}
+
+/// The options of the alignment row, kept between frames.
+#[derive(Debug, Clone)]
+pub struct AlignmentPanel {
+    reference: Reference,
+    /// the window aligned to with `Reference::Window`
+    reference_window: Option<WindowInfo>,
+    /// `[horizontal, vertical]` in points, scaled to pixels of the monitor of the window
+    margin: [i32; 2],
+}
+
+impl AlignmentPanel {
+    pub fn new() -> Self {
+        Self {
+            reference: Reference::WorkArea,
+            reference_window: None,
+            margin: [0, 0],
+        }
+    }
+
+    /// Moves `window_info` so its visible frame is aligned to `anchor` of the reference.
+    pub fn align(&self, window_info: &WindowInfo, anchor: Anchor) -> Result<()> {
+        let frame = window_info.get_frame_rect()?;
+        let monitor_info =
+            monitor::monitor_of_window(window_info.hwnd).ok_or(Error::InvalidMonitor)?;
+        let reference = match self.reference {
+            Reference::Monitor => monitor_info.rect,
+            Reference::WorkArea => monitor_info.work_area,
+            Reference::Window => self
+                .reference_window
+                .as_ref()
+                .filter(|reference_window| reference_window.is_valid())
+                .ok_or(Error::WindowGone(None))?
+                .get_frame_rect()?,
+        };
+        let margin = self
+            .margin
+            .map(|margin| (margin as f32 * monitor_info.scale_factor()).round() as i32);
+        let [x, y] = aligned_position(anchor, [frame[2], frame[3]], reference, margin);
+        // the window rect is larger than the frame by the invisible borders, and child windows
+        // are positioned relative to their parent, so the window is moved by the offset
+        let [left, top] = window_info.get_position()?;
+        window_info.move_to([left + x - frame[0], top + y - frame[1]])
+    }
+
+    /// The options and a grid of the anchors, returns the anchor clicked.
+    pub fn show_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        window_info_list: &[WindowInfo],
+    ) -> Option<Anchor> {
+        const FONT_SIZE: f32 = crate::app::App::FONT_SIZE;
+        let text = |text: &str| egui::RichText::new(text).size(FONT_SIZE);
+        let mut clicked = None;
+        egui::Grid::new("alignment_anchors")
+            .spacing([2.0; 2])
+            .show(ui, |ui| {
+                Anchor::ALL.chunks(3).for_each(|row| {
+                    row.iter().for_each(|&anchor| {
+                        egui::Button::new(text(anchor.symbol()))
+                            .small()
+                            .ui(ui)
+                            .on_hover_text(anchor.name())
+                            .clicked()
+                            .then(|| clicked = Some(anchor));
+                    });
+                    ui.end_row();
+                });
+            });
+        egui::ComboBox::from_id_salt("alignment_reference")
+            .selected_text(text(self.reference.name()))
+            .show_ui(ui, |ui| {
+                Reference::ALL.into_iter().for_each(|reference| {
+                    ui.selectable_value(&mut self.reference, reference, text(reference.name()));
+                });
+            });
+        (self.reference == Reference::Window).then(|| {
+            egui::ComboBox::from_id_salt("alignment_reference_window")
+                .selected_text(text(
+                    self.reference_window
+                        .as_ref()
+                        .map(|window_info| window_info.title.as_str())
+                        .unwrap_or(i18n::tr("alignment.choose_window")),
+                ))
+                .width(200.0)
+                .show_ui(ui, |ui| {
+                    window_info_list.iter().for_each(|window_info| {
+                        let selected =
+                            self.reference_window
+                                .as_ref()
+                                .is_some_and(|reference_window| {
+                                    reference_window.key() == window_info.key()
+                                });
+                        ui.selectable_label(selected, text(&window_info.title))
+                            .clicked()
+                            .then(|| self.reference_window = Some(window_info.clone()));
+                    });
+                });
+        });
+        egui::Label::new(text(i18n::tr("alignment.margin"))).ui(ui);
+        self.margin.iter_mut().for_each(|margin| {
+            egui::DragValue::new(margin).range(0..=1000).ui(ui);
+        });
+        clicked
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_aligned_position() {
+        let reference = [0, 0, 1920, 1040];
+        let size = [800, 600];
+        assert_eq!(
+            aligned_position(Anchor::Center, size, reference, [20, 10]),
+            [560, 220]
+        );
+        assert_eq!(
+            aligned_position(Anchor::TopLeft, size, reference, [20, 10]),
+            [20, 10]
+        );
+        assert_eq!(
+            aligned_position(Anchor::BottomRight, size, reference, [20, 10]),
+            [1100, 430]
+        );
+        assert_eq!(
+            aligned_position(Anchor::Top, size, reference, [20, 10]),
+            [560, 10]
+        );
+        assert_eq!(
+            aligned_position(Anchor::Left, size, reference, [20, 10]),
+            [20, 220]
+        );
+    }
+
+    #[test]
+    fn test_aligned_position_on_secondary_monitor() {
+        // a monitor left of the primary one
+        let reference = [-1280, 200, 1280, 1024];
+        assert_eq!(
+            aligned_position(Anchor::BottomLeft, [640, 480], reference, [0, 0]),
+            [-1280, 744]
+        );
+        assert_eq!(
+            aligned_position(Anchor::Right, [640, 480], reference, [8, 8]),
+            [-648, 472]
+        );
+        // larger than the reference, centered so both sides stick out
+        assert_eq!(
+            aligned_position(Anchor::Center, [1480, 1224], reference, [0, 0]),
+            [-1380, 100]
+        );
+    }
+}
@@ -1,11 +1,31 @@
-use std::sync::Arc;
+use std::{collections::BTreeSet, time::Instant};
 
 use eframe::Renderer;
-use egui::{ThemePreference, ViewportBuilder, Widget};
+use egui::{ViewportBuilder, Widget};
+use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+use windows::Win32::Foundation::HWND;
 
 use crate::{
-    message_dialog, utils,
-    window_modifier::{WindowInfo, WindowModifier},
+    alignment::AlignmentPanel,
+    arrangement::ArrangementPanel,
+    batch::BatchReport,
+    crash_report, error,
+    expression::{self, Expression, Variables},
+    font,
+    hotkey::HotkeyManager,
+    i18n,
+    ipc::{self, IpcServer, WindowHandler},
+    lock,
+    logging::LogViewer,
+    monitor, notification,
+    restore_on_exit::{self, ExitReason},
+    script::ScriptPanel,
+    settings::{self, Settings, WindowGeometry},
+    tray, utils,
+    window_list::WindowListView,
+    window_modifier::{ModificationKind, WindowInfo, WindowModifier},
+    window_picker::{PickerEvent, WindowPicker},
+    window_tree::WindowTree,
 };
 
 pub struct MainApp {
@@ -18,10 +38,20 @@ impl MainApp {
     }
 
     pub fn run(self) {
+        let settings = settings::settings();
+        let mut viewport = ViewportBuilder::default()
+            .with_min_inner_size(Settings::MIN_WINDOW_SIZE)
+            .with_inner_size(Settings::DEFAULT_WINDOW_SIZE);
+        if let Some(geometry) = settings.window_geometry
+            && settings.remember_window_geometry
+        {
+            viewport = viewport
+                .with_position(geometry.position)
+                .with_inner_size(geometry.inner_size)
+                .with_maximized(geometry.maximized);
+        }
         let native_options = eframe::NativeOptions {
-            viewport: ViewportBuilder::default()
-                .with_min_inner_size([600.0, 600.0])
-                .with_inner_size([1280.0, 720.0]),
+            viewport,
             renderer: Renderer::Wgpu,
             ..Default::default()
         };
@@ -42,39 +72,133 @@ struct WindowModificationCache {
     inner_height: i32,
     x: i32,
     y: i32,
+    /// `[width, height]` expressions overriding the sliders when they're not empty
+    size_expressions: [String; 2],
+    inner_size_expressions: [String; 2],
+    /// `[x, y]`
+    position_expressions: [String; 2],
 }
 
 pub(crate) struct App {
     window_modifier: WindowModifier,
     current_window_info_index: Option<usize>,
     window_modification_cache: WindowModificationCache,
+    window_list_view: WindowListView,
+    arrangement_panel: ArrangementPanel,
+    alignment_panel: AlignmentPanel,
+    /// the kinds of modification the lock button keeps
+    lock_kinds: BTreeSet<ModificationKind>,
+    /// `Some` while the hierarchy browser is open
+    window_tree: Option<WindowTree>,
+    window_tree_include_invisible: bool,
+    /// `Some` while picking a window by clicking on it
+    window_picker: Option<WindowPicker>,
+    hotkey_manager: HotkeyManager,
+    show_hotkey_window: bool,
+    script_panel: ScriptPanel,
+    show_script_window: bool,
+    /// `false` if the tray icon could not be created
+    tray_installed: bool,
+    /// quit was chosen from the tray, the close is not turned into minimizing to the tray
+    quitting: bool,
+    /// `Some` while asking whether to restore the modified windows before quitting
+    exit_question: Option<String>,
+    /// whether to restore the kinds set to `Ask`, taken by the close that follows the answer
+    exit_answer: Option<bool>,
+    /// edited by the settings panel, saved on change
+    settings: Settings,
+    show_settings_window: bool,
+    log_viewer: LogViewer,
+    show_log_window: bool,
+    show_notification_window: bool,
+    /// when the main window was last moved or resized, its geometry is saved once it settles
+    window_geometry_changed_at: Option<Instant>,
+    last_refreshed_at: Instant,
+    /// `Some` while other processes may control windows through the local JSON-RPC endpoint
+    ipc_server: Option<IpcServer>,
 }
 
 impl App {
     fn new(cc: &eframe::CreationContext) -> Self {
-        cc.egui_ctx.set_theme(ThemePreference::Dark);
-        let mut font_definitions = egui::FontDefinitions::default();
-        let proportional = font_definitions
-            .families
-            .get_mut(&egui::FontFamily::Proportional)
-            .unwrap();
-        let font_data = include_bytes!("simhei.ttf");
-        font_definitions.font_data.insert(
-            "simhei".to_string(),
-            Arc::new(egui::FontData::from_static(font_data)),
-        );
-        proportional.insert(0, "simhei".to_string());
-        cc.egui_ctx.set_fonts(font_definitions);
+        let settings = settings::settings();
+        settings.apply(&cc.egui_ctx);
+        cc.egui_ctx
+            .set_fonts(font::font_definitions(settings.ui_font.as_ref()));
+        i18n::set_language(settings.language());
+        crash_report::check_pending_reports();
+        let tray_installed = match cc.window_handle().map(|handle| handle.as_raw()) {
+            Ok(RawWindowHandle::Win32(handle)) => {
+                tray::install(HWND(handle.hwnd.get() as _), cc.egui_ctx.clone())
+                    .map_err(|err| {
+                        tracing::warn!(error = %err, "tray icon could not be installed");
+                        notification::warning(i18n::tr_format(
+                            "app.tray_install_failed",
+                            &[("error", &err)],
+                        ))
+                    })
+                    .is_ok()
+            }
+            _ => false,
+        };
+        let ipc_server = settings
+            .ipc_server_enabled
+            .then(Self::start_ipc_server)
+            .flatten();
         Self {
-            window_modifier: WindowModifier::new(),
+            window_modifier: WindowModifier::new(cc.egui_ctx.clone()),
             current_window_info_index: None,
             window_modification_cache: Default::default(),
+            window_list_view: WindowListView::new(),
+            arrangement_panel: ArrangementPanel::new(),
+            alignment_panel: AlignmentPanel::new(),
+            lock_kinds: BTreeSet::from([ModificationKind::Geometry]),
+            window_tree: None,
+            window_tree_include_invisible: false,
+            window_picker: None,
+            hotkey_manager: HotkeyManager::new(cc.egui_ctx.clone()),
+            show_hotkey_window: false,
+            script_panel: ScriptPanel::new(cc.egui_ctx.clone()),
+            show_script_window: false,
+            tray_installed,
+            quitting: false,
+            exit_question: None,
+            exit_answer: None,
+            settings,
+            show_settings_window: false,
+            log_viewer: LogViewer::new(),
+            show_log_window: false,
+            show_notification_window: false,
+            window_geometry_changed_at: None,
+            last_refreshed_at: Instant::now(),
+            ipc_server,
         }
     }
 }
 
 impl eframe::App for App {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if tray::take_quit_request() {
+            self.quitting = true;
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+        if ctx.input(|input| input.viewport().close_requested()) {
+            if !self.quitting && self.tray_installed && self.settings.minimize_to_tray_on_close {
+                ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+                tray::hide_main_window();
+            } else if let Some(restore_asked) = self.exit_answer.take() {
+                restore_on_exit::restore_on_exit(ExitReason::Quit, restore_asked);
+            } else if let Some(question) = restore_on_exit::question() {
+                ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+                self.quitting = true;
+                self.exit_question = Some(question);
+            } else {
+                restore_on_exit::restore_on_exit(ExitReason::Quit, false);
+            }
+        }
+        self.remember_window_geometry(ctx);
+        self.auto_refresh(ctx);
+        self.update_window_list(WindowModifier::poll_window_info_list);
+
         egui::SidePanel::left("left_panel")
             .min_width(300.0)
             .default_width(480.0)
@@ -82,23 +206,65 @@ impl eframe::App for App {
             .show(ctx, |ui| self.window_select_panel(ui));
 
         egui::CentralPanel::default().show(ctx, |ui| self.modification_panel(ui));
+
+        self.window_tree_window(ctx);
+        self.window_picker_window(ctx);
+        self.hotkey_window(ctx);
+        self.script_window(ctx);
+        self.settings_window(ctx);
+        self.log_window(ctx);
+        self.notification_window(ctx);
+        self.exit_question_modal(ctx);
+        notification::show_toasts(ctx);
     }
 }
 
 impl App {
     fn current_window_info(&self) -> Option<&WindowInfo> {
         self.current_window_info_index
-            .as_ref()
-            .map(|&index| self.window_modifier.window_info_list().get(index).unwrap())
+            .and_then(|index| self.window_modifier.window_info_list().get(index))
+    }
+
+    /// The windows the modifications apply to, the checked ones or else the current one.
+    fn target_windows(&self) -> Vec<WindowInfo> {
+        let selected_windows = self
+            .window_list_view
+            .selected_windows(self.window_modifier.window_info_list());
+        match selected_windows.is_empty() {
+            true => self.current_window_info().cloned().into_iter().collect(),
+            false => selected_windows,
+        }
+    }
+
+    /// Applies `modify` to the target windows, a summary of every window is shown if there are
+    /// several.
+    fn apply(&mut self, operation: &str, modify: impl Fn(&WindowInfo) -> error::Result<()>) {
+        self.update_current_window_info();
+        match self.target_windows().as_slice() {
+            [] => (),
+            [window_info] => modify(window_info).unwrap_or_else(|err| notification::error(&err)),
+            target_windows => BatchReport::run(operation, target_windows.to_vec(), modify).notify(),
+        }
     }
 
-    fn current_window_info_mut(&mut self) -> Option<&mut WindowInfo> {
-        self.current_window_info_index.as_ref().map(|&index| {
-            self.window_modifier
-                .window_info_list_mut()
-                .get_mut(index)
-                .unwrap()
-        })
+    /// Like `apply` with `values`, or the expressions of the pair evaluated for each window where
+    /// they're not empty.
+    fn apply_expressions(
+        &mut self,
+        operation_key: &'static str,
+        sources: [String; 2],
+        values: [i32; 2],
+        modify: impl Fn(&WindowInfo, [i32; 2]) -> error::Result<()>,
+    ) {
+        match expression::parse_pair(&sources) {
+            Ok(expressions) => self.apply(i18n::tr(operation_key), |window_info| {
+                modify(
+                    window_info,
+                    expression::resolve_pair(&expressions, values, window_info)?,
+                )
+            }),
+            Err(err) => notification::warning(err.to_string()),
+        }
     }
 
     fn update_current_window_info(&mut self) {
@@ -106,23 +272,167 @@ impl App {
             .map(|window_info| !window_info.is_valid())
             .map(|is_invalid| {
                 is_invalid.then(|| {
+                    tracing::info!("the current window no longer exists");
                     self.current_window_info_index = None;
-                    message_dialog::info("当前窗口句柄已失效。").show();
+                    notification::info(i18n::tr("app.current_window_invalid"));
                 })
             });
     }
 
+    fn select_window(&mut self, index: usize) {
+        self.current_window_info_index = Some(index);
+        self.current_window_info().map(|window_info| {
+            tracing::info!(
+                hwnd = format!("{:#x}", window_info.hwnd.0 as usize),
+                pid = window_info.pid,
+                title = window_info.title,
+                program_path = window_info.program_path,
+                "window selected"
+            )
+        });
+    }
+
+    /// Re-enumerates the windows in the background, the closed ones are removed right away.
+    fn refresh_window_list(&mut self) {
+        self.update_window_list(|window_modifier| {
+            window_modifier.request_window_info_list();
+            true
+        });
+        self.last_refreshed_at = Instant::now();
+    }
+
+    /// Runs `update` on the window list, if it returns `true` the list is sorted again and the
+    /// current window stays selected if it still exists.
+    fn update_window_list(&mut self, update: impl FnOnce(&mut WindowModifier) -> bool) {
+        let key = self.current_window_info().map(WindowInfo::key);
+        if !update(&mut self.window_modifier) {
+            return;
+        }
+        self.window_list_view
+            .update(self.window_modifier.window_info_list());
+        key.map(|key| {
+            self.current_window_info_index = self
+                .window_modifier
+                .window_info_list()
+                .iter()
+                .position(|window_info| window_info.key() == key);
+        });
+    }
+
+    /// Moves the windows that are not visible enough on any monitor into the nearest work area.
+    fn rescue_offscreen_windows(&mut self) {
+        self.update_window_list(|window_modifier| {
+            window_modifier.update_window_info_list();
+            true
+        });
+        let monitors = monitor::enumerate_monitors();
+        let targets: Vec<_> = self
+            .window_modifier
+            .window_info_list()
+            .iter()
+            // minimized windows are at -32000, -32000
+            .filter(|window_info| !window_info.is_child() && !window_info.is_minimized())
+            .filter_map(|window_info| {
+                let [x, y] = window_info.get_position().ok()?;
+                let [width, height] = window_info.get_size().ok()?;
+                monitor::rescue_position([x, y, width, height], &monitors)
+                    .map(|position| (window_info.clone(), position))
+            })
+            .collect();
+        if targets.is_empty() {
+            notification::info(i18n::tr("app.no_offscreen_windows"));
+            return;
+        }
+        BatchReport::run_with(
+            i18n::tr("app.rescue_offscreen_windows"),
+            targets,
+            |window_info, position| window_info.move_to(position),
+        )
+        .notify();
+    }
+
+    fn auto_refresh(&mut self, ctx: &egui::Context) {
+        if self.settings.auto_refresh_interval == 0 {
+            return;
+        }
+        let interval = std::time::Duration::from_secs(self.settings.auto_refresh_interval as _);
+        let elapsed = self.last_refreshed_at.elapsed();
+        if elapsed >= interval {
+            self.refresh_window_list();
+            ctx.request_repaint_after(interval);
+        } else {
+            ctx.request_repaint_after(interval - elapsed);
+        }
+    }
+
+    /// Keeps the geometry of the main window in the settings, saved a second after it stops
+    /// changing so dragging the window doesn't write the file on every frame.
+    fn remember_window_geometry(&mut self, ctx: &egui::Context) {
+        const SETTLE_TIME: std::time::Duration = std::time::Duration::from_secs(1);
+        if !self.settings.remember_window_geometry {
+            return;
+        }
+        let geometry = ctx.input(|input| {
+            let viewport = input.viewport();
+            let maximized = viewport.maximized.unwrap_or(false);
+            let minimized = viewport.minimized.unwrap_or(false);
+            match (viewport.outer_rect, viewport.inner_rect) {
+                // a maximized window keeps the geometry it is restored to
+                _ if maximized => self
+                    .settings
+                    .window_geometry
+                    .map(|geometry| WindowGeometry {
+                        maximized: true,
+                        ..geometry
+                    }),
+                (Some(outer_rect), Some(inner_rect)) if !minimized => Some(WindowGeometry {
+                    position: outer_rect.min.into(),
+                    inner_size: inner_rect.size().into(),
+                    maximized: false,
+                }),
+                _ => None,
+            }
+        });
+        if geometry.is_some() && geometry != self.settings.window_geometry {
+            self.settings.window_geometry = geometry;
+            self.window_geometry_changed_at = Some(Instant::now());
+        }
+        self.window_geometry_changed_at.map(|changed_at| {
+            if changed_at.elapsed() >= SETTLE_TIME {
+                self.window_geometry_changed_at = None;
+                settings::set_window_geometry(self.settings.window_geometry);
+            } else {
+                ctx.request_repaint_after(SETTLE_TIME - changed_at.elapsed());
+            }
+        });
+    }
+
+    fn start_ipc_server() -> Option<IpcServer> {
+        IpcServer::start(&ipc::transport::default_endpoint(), WindowHandler)
+            .inspect(|ipc_server| {
+                tracing::info!(endpoint = ipc_server.endpoint(), "ipc server started")
+            })
+            .map_err(|err| {
+                tracing::warn!(error = %err, "ipc server failed to start");
+                notification::warning(i18n::tr_format(
+                    "app.ipc_server_start_failed",
+                    &[("error", &err)],
+                ))
+            })
+            .ok()
+    }
+
     fn window_select_panel(&mut self, ui: &mut egui::Ui) {
         ui.vertical(|ui| {
             egui::ScrollArea::horizontal().show(ui, |ui| {
                 ui.horizontal(|ui| {
-                    egui::Label::new(Self::egui_text("当前窗口: "))
+                    egui::Label::new(Self::egui_text(i18n::tr("app.current_window")))
                         .selectable(true)
                         .ui(ui);
                     self.current_window_info()
                         .map(|window_info| window_info.show_ui(ui))
                         .unwrap_or_else(|| {
-                            egui::Label::new(Self::egui_text("无"))
+                            egui::Label::new(Self::egui_text(i18n::tr("common.none")))
                                 .selectable(true)
                                 .ui(ui);
                         });
@@ -131,45 +441,318 @@ impl App {
             });
         });
         ui.separator();
-        egui::Button::new(Self::egui_text("刷新窗口列表"))
+        ui.horizontal(|ui| {
+            egui::Button::new(Self::egui_text(i18n::tr("app.refresh_window_list")))
+                .ui(ui)
+                .clicked()
+                .then(|| self.refresh_window_list());
+            egui::Button::new(Self::egui_text(i18n::tr("app.window_tree")))
+                .ui(ui)
+                .clicked()
+                .then(|| {
+                    self.window_tree = Some(WindowTree::build(self.window_tree_include_invisible))
+                });
+            egui::Button::new(Self::egui_text(i18n::tr("app.pick_window")))
+                .ui(ui)
+                .clicked()
+                .then(|| {
+                    self.window_picker = WindowPicker::new()
+                        .map_err(|err| {
+                            notification::warning(i18n::tr_format(
+                                "app.pick_window_failed",
+                                &[("error", &err)],
+                            ))
+                        })
+                        .ok()
+                });
+            egui::Button::new(Self::egui_text(i18n::tr("app.hotkeys")))
+                .ui(ui)
+                .clicked()
+                .then(|| self.show_hotkey_window = true);
+            egui::Button::new(Self::egui_text(i18n::tr("app.script")))
+                .ui(ui)
+                .clicked()
+                .then(|| self.show_script_window = true);
+            egui::Button::new(Self::egui_text(i18n::tr("app.settings")))
+                .ui(ui)
+                .clicked()
+                .then(|| self.show_settings_window = true);
+            egui::Button::new(Self::egui_text(i18n::tr("app.log")))
+                .ui(ui)
+                .clicked()
+                .then(|| self.show_log_window = true);
+            let unread = notification::unread();
+            let notifications = match unread {
+                0 => i18n::tr("app.notifications").to_string(),
+                unread => format!("{} ({})", i18n::tr("app.notifications"), unread),
+            };
+            egui::Button::new(Self::egui_text(&notifications))
+                .ui(ui)
+                .clicked()
+                .then(|| self.show_notification_window = true);
+        });
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(self.tray_installed, |ui| {
+                egui::Button::new(Self::egui_text(i18n::tr("app.minimize_to_tray")))
+                    .ui(ui)
+                    .clicked()
+                    .then(tray::hide_main_window);
+            });
+            egui::Button::new(Self::egui_text(i18n::tr("app.rescue_offscreen_windows")))
+                .ui(ui)
+                .clicked()
+                .then(|| self.rescue_offscreen_windows());
+            let mut ipc_enabled = self.ipc_server.is_some();
+            egui::Checkbox::new(
+                &mut ipc_enabled,
+                Self::egui_text(i18n::tr("app.ipc_server")),
+            )
             .ui(ui)
-            .clicked()
+            .changed()
             .then(|| {
-                let pid_and_hwnd = self
-                    .current_window_info()
-                    .map(|window_info| (window_info.pid, window_info.hwnd));
-                self.window_modifier.update_window_info_list();
-                pid_and_hwnd.map(|(pid, hwnd)| {
-                    self.current_window_info_index = self
-                        .window_modifier
-                        .window_info_list()
-                        .iter()
-                        .position(|window_info| window_info.pid == pid && window_info.hwnd == hwnd);
-                });
+                self.ipc_server = match ipc_enabled {
+                    true => Self::start_ipc_server(),
+                    false => {
+                        tracing::info!("ipc server stopped");
+                        None
+                    }
+                };
+                self.settings.ipc_server_enabled = ipc_enabled;
+                settings::set_settings(self.settings.clone());
+            });
+            self.ipc_server.as_ref().map(|ipc_server| {
+                egui::Label::new(Self::egui_text(ipc_server.endpoint()))
+                    .selectable(true)
+                    .ui(ui)
             });
+        });
+        ui.separator();
+
+        self.window_list_view
+            .show_options_ui(ui, self.window_modifier.window_info_list());
+        self.window_list_view
+            .show_selection_ui(ui, self.window_modifier.window_info_list());
         ui.separator();
 
-        egui::ScrollArea::both().show_rows(
-            ui,
-            ui.text_style_height(&egui::TextStyle::Body),
-            self.window_modifier.window_info_list().len(),
-            |ui, range| {
-                self.window_modifier
-                    .window_info_list()
-                    .iter()
-                    .enumerate()
-                    .skip(range.start)
-                    .take(range.end - range.start)
-                    .for_each(|(index, window_info)| {
-                        window_info
-                            .show_selectable_ui(ui)
-                            .then(|| self.current_window_info_index = Some(index));
+        let current = self.current_window_info().map(WindowInfo::key);
+        self.window_list_view
+            .show_ui(ui, self.window_modifier.window_info_list(), current)
+            .map(|index| self.select_window(index));
+    }
+
+    fn window_tree_window(&mut self, ctx: &egui::Context) {
+        let Some(window_tree) = self.window_tree.as_ref() else {
+            return;
+        };
+        let mut open = true;
+        let mut rebuild = false;
+        let mut selected = None;
+        egui::Window::new(Self::egui_text(i18n::tr("app.window_tree")))
+            .id(egui::Id::new("window_tree"))
+            .open(&mut open)
+            .default_size([720.0, 540.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    rebuild |= egui::Button::new(Self::egui_text(i18n::tr("common.refresh")))
+                        .ui(ui)
+                        .clicked();
+                    rebuild |= egui::Checkbox::new(
+                        &mut self.window_tree_include_invisible,
+                        Self::egui_text(i18n::tr("app.show_invisible_windows")),
+                    )
+                    .ui(ui)
+                    .changed();
+                });
+                ui.separator();
+                egui::ScrollArea::both().show(ui, |ui| selected = window_tree.show_ui(ui));
+            });
+        if !open {
+            self.window_tree = None;
+        } else if rebuild {
+            self.window_tree = Some(WindowTree::build(self.window_tree_include_invisible));
+        }
+        selected.map(|hwnd| {
+            WindowInfo::from_hwnd(hwnd)
+                .map(|window_info| {
+                    let index = self.window_modifier.insert_window_info(window_info);
+                    self.select_window(index);
+                })
+                .unwrap_or_else(|| {
+                    notification::info(i18n::tr("app.current_window_invalid"));
+                })
+        });
+    }
+
+    fn exit_question_modal(&mut self, ctx: &egui::Context) {
+        let Some(question) = self.exit_question.as_ref() else {
+            return;
+        };
+        let mut answer = None;
+        let response = egui::Modal::new(egui::Id::new("exit_question")).show(ctx, |ui| {
+            egui::Label::new(Self::egui_text(question)).ui(ui);
+            ui.separator();
+            ui.horizontal(|ui| {
+                egui::Button::new(Self::egui_text(i18n::tr("common.yes")))
+                    .ui(ui)
+                    .clicked()
+                    .then(|| answer = Some(Some(true)));
+                egui::Button::new(Self::egui_text(i18n::tr("common.no")))
+                    .ui(ui)
+                    .clicked()
+                    .then(|| answer = Some(Some(false)));
+                egui::Button::new(Self::egui_text(i18n::tr("common.cancel")))
+                    .ui(ui)
+                    .clicked()
+                    .then(|| answer = Some(None));
+            });
+        });
+        let answer = match answer {
+            Some(answer) => answer,
+            None if response.should_close() => None,
+            None => return,
+        };
+        self.exit_question = None;
+        self.exit_answer = answer;
+        if answer.is_some() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        } else {
+            self.quitting = false;
+        }
+    }
+
+    fn window_picker_window(&mut self, ctx: &egui::Context) {
+        let Some(window_picker) = self.window_picker.as_mut() else {
+            return;
+        };
+        match window_picker.update() {
+            PickerEvent::None => {}
+            PickerEvent::Picked(window_info) => {
+                let index = self.window_modifier.insert_window_info(window_info);
+                self.select_window(index);
+                self.window_picker = None;
+                return;
+            }
+            PickerEvent::Cancelled => {
+                self.window_picker = None;
+                return;
+            }
+        }
+        ctx.request_repaint_after(std::time::Duration::from_millis(30));
+        let hovered = window_picker.hovered();
+        egui::Window::new(Self::egui_text(i18n::tr("app.pick_window")))
+            .id(egui::Id::new("window_picker"))
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Label::new(Self::egui_text(i18n::tr("app.pick_window_hint"))).ui(ui);
+                ui.separator();
+                hovered
+                    .map(|window_info| window_info.show_ui(ui))
+                    .unwrap_or_else(|| {
+                        egui::Label::new(Self::egui_text(i18n::tr("common.none"))).ui(ui);
+                    });
+            });
+        let Some((window_info, rect)) = hovered.and_then(|window_info| {
+            window_info
+                .get_window_rect()
+                .ok()
+                .map(|rect| (window_info, rect))
+        }) else {
+            return;
+        };
+        // the highlight is placed in the points of the monitor it is shown on
+        let pixels_per_point = monitor::monitor_of_window(window_info.hwnd)
+            .map_or(1.0, |monitor_info| monitor_info.scale_factor())
+            * ctx.zoom_factor();
+        let min = egui::pos2(rect.left as f32, rect.top as f32) / pixels_per_point;
+        let max = egui::pos2(rect.right as f32, rect.bottom as f32) / pixels_per_point;
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("window_picker_highlight"),
+            ViewportBuilder::default()
+                .with_title("Window Picker Highlight")
+                .with_position(min)
+                .with_inner_size(max - min)
+                .with_decorations(false)
+                .with_transparent(true)
+                .with_mouse_passthrough(true)
+                .with_taskbar(false)
+                .with_active(false)
+                .with_window_level(egui::WindowLevel::AlwaysOnTop),
+            |ctx, _class| {
+                // immediate viewports are cleared transparent, the frame paints only the stroke
+                egui::CentralPanel::default()
+                    .frame(egui::Frame::NONE)
+                    .show(ctx, |ui| {
+                        ui.painter().rect_stroke(
+                            ui.max_rect(),
+                            0.0,
+                            egui::Stroke::new(4.0, egui::Color32::RED),
+                            egui::StrokeKind::Inside,
+                        );
                     });
-                ui.add_space(10.0);
             },
         );
     }
 
+    fn hotkey_window(&mut self, ctx: &egui::Context) {
+        self.hotkey_manager
+            .poll_events()
+            .into_iter()
+            .for_each(notification::warning);
+        let mut save_error = None;
+        egui::Window::new(Self::egui_text(i18n::tr("app.hotkeys")))
+            .id(egui::Id::new("hotkeys"))
+            .open(&mut self.show_hotkey_window)
+            .show(ctx, |ui| save_error = self.hotkey_manager.show_ui(ui));
+        save_error.map(notification::warning);
+    }
+
+    fn script_window(&mut self, ctx: &egui::Context) {
+        let mut file_error = None;
+        egui::Window::new(Self::egui_text(i18n::tr("app.script")))
+            .id(egui::Id::new("script"))
+            .open(&mut self.show_script_window)
+            .default_size([720.0, 640.0])
+            .show(ctx, |ui| file_error = self.script_panel.show_ui(ui));
+        file_error.map(notification::warning);
+    }
+
+    fn settings_window(&mut self, ctx: &egui::Context) {
+        let mut changed = false;
+        egui::Window::new(Self::egui_text(i18n::tr("app.settings")))
+            .id(egui::Id::new("settings"))
+            .open(&mut self.show_settings_window)
+            .show(ctx, |ui| changed = self.settings.show_ui(ui));
+        if !changed {
+            return;
+        }
+        self.settings.apply(ctx);
+        if self.settings.ui_font != settings::settings().ui_font {
+            ctx.set_fonts(font::font_definitions(self.settings.ui_font.as_ref()));
+        }
+        if self.settings.language() != i18n::language() {
+            i18n::set_language(self.settings.language());
+            tray::update_texts();
+        }
+        settings::set_settings(self.settings.clone());
+    }
+
+    fn log_window(&mut self, ctx: &egui::Context) {
+        egui::Window::new(Self::egui_text(i18n::tr("app.log")))
+            .id(egui::Id::new("log"))
+            .open(&mut self.show_log_window)
+            .default_size([900.0, 480.0])
+            .show(ctx, |ui| self.log_viewer.show_ui(ui));
+    }
+
+    fn notification_window(&mut self, ctx: &egui::Context) {
+        egui::Window::new(Self::egui_text(i18n::tr("app.notifications")))
+            .id(egui::Id::new("notifications"))
+            .open(&mut self.show_notification_window)
+            .default_size([600.0, 480.0])
+            .show(ctx, notification::show_history_ui);
+    }
+
     fn modification_panel(&mut self, ui: &mut egui::Ui) {
         egui::ScrollArea::both().show(ui, |ui| {
             egui::Grid::new(ui.next_auto_id())
@@ -191,60 +774,93 @@ impl App {
                     ui.end_row();
                     self.modify_window_fullscreen_status(ui);
                     ui.end_row();
+                    self.arrange_windows(ui);
+                    ui.end_row();
+                    self.align_windows(ui);
+                    ui.end_row();
+                    self.lock_windows(ui);
+                    ui.end_row();
+                    self.restore_window_original_status(ui);
+                    ui.end_row();
                 });
             ui.add_space(10.0);
         });
     }
 
     fn modify_window_size(&mut self, ui: &mut egui::Ui) {
+        let current_window_info = self.current_window_info().cloned();
         ui.horizontal_centered(|ui| {
-            egui::Label::new(Self::egui_text("窗口大小: "))
+            egui::Label::new(Self::egui_text(i18n::tr("app.window_size")))
                 .selectable(false)
                 .ui(ui);
-            egui::Button::new(Self::egui_text("读取"))
+            egui::Button::new(Self::egui_text(i18n::tr("common.read")))
                 .ui(ui)
                 .clicked()
                 .then(|| {
                     self.update_current_window_info();
-                    self.current_window_info_index.as_ref().map(|&index| {
-                        self.window_modifier
-                            .window_info_list()
-                            .get(index)
-                            .unwrap()
-                            .get_size()
-                            .map(|[width, height]| {
+                    self.current_window_info()
+                        .map(|window_info| window_info.get_size())
+                        .map(|size| {
+                            size.map(|[width, height]| {
                                 self.window_modification_cache.width = width;
                                 self.window_modification_cache.height = height;
+                                self.window_modification_cache.size_expressions =
+                                    Default::default();
                             })
-                            .map_err(|err| message_dialog::warning(&err.to_string()).show())
-                    });
+                            .map_err(|err| notification::error(&err))
+                        });
                 });
-            egui::Button::new(Self::egui_text("应用"))
+            egui::Button::new(Self::egui_text(i18n::tr("common.apply")))
                 .ui(ui)
                 .clicked()
                 .then(|| {
-                    self.update_current_window_info();
-                    self.current_window_info().map(|window_info| {
-                        window_info
-                            .resize([
-                                self.window_modification_cache.width,
-                                self.window_modification_cache.height,
-                            ])
-                            .map_err(|err| message_dialog::warning(&err.to_string()).show())
-                    });
+                    let size = [
+                        self.window_modification_cache.width,
+                        self.window_modification_cache.height,
+                    ];
+                    self.apply_expressions(
+                        "app.window_size",
+                        self.window_modification_cache.size_expressions.clone(),
+                        size,
+                        WindowInfo::resize,
+                    );
                 });
-            egui::Label::new(Self::egui_text("宽度: ")).ui(ui);
-            egui::Slider::new(&mut self.window_modification_cache.width, 0..=8192)
-                .logarithmic(true)
-                .drag_value_speed(1.0)
-                .ui(ui);
-            egui::Label::new(Self::egui_text("高度: ")).ui(ui);
-            egui::Slider::new(&mut self.window_modification_cache.height, 0..=8192)
-                .logarithmic(true)
-                .drag_value_speed(1.0)
-                .ui(ui);
+            egui::Label::new(Self::egui_text(i18n::tr("common.width"))).ui(ui);
+            egui::Slider::new(
+                &mut self.window_modification_cache.width,
+                0..=self.settings.slider_max,
+            )
+            .logarithmic(self.settings.logarithmic_sliders)
+            .drag_value_speed(1.0)
+            .ui(ui)
+            .changed()
+            .then(|| self.window_modification_cache.size_expressions[0].clear());
+            Self::expression_field(
+                ui,
+                &mut self.window_modification_cache.size_expressions[0],
+                &mut self.window_modification_cache.width,
+                current_window_info.as_ref(),
+                true,
+            );
+            egui::Label::new(Self::egui_text(i18n::tr("common.height"))).ui(ui);
+            egui::Slider::new(
+                &mut self.window_modification_cache.height,
+                0..=self.settings.slider_max,
+            )
+            .logarithmic(self.settings.logarithmic_sliders)
+            .drag_value_speed(1.0)
+            .ui(ui)
+            .changed()
+            .then(|| self.window_modification_cache.size_expressions[1].clear());
+            Self::expression_field(
+                ui,
+                &mut self.window_modification_cache.size_expressions[1],
+                &mut self.window_modification_cache.height,
+                current_window_info.as_ref(),
+                false,
+            );
             ui.separator();
-            egui::Label::new(Self::egui_text("当前比例: ")).ui(ui);
+            egui::Label::new(Self::egui_text(i18n::tr("app.current_ratio"))).ui(ui);
             egui::Label::new(Self::egui_text(Self::size_to_ratio_string([
                 self.window_modification_cache.width,
                 self.window_modification_cache.height,
@@ -254,9 +870,10 @@ impl App {
     }
 
     fn modify_window_inner_size(&mut self, ui: &mut egui::Ui) {
+        let current_window_info = self.current_window_info().cloned();
         ui.horizontal_centered(|ui| {
-            egui::Label::new(Self::egui_text("窗口内部大小: ")).ui(ui);
-            egui::Button::new(Self::egui_text("读取"))
+            egui::Label::new(Self::egui_text(i18n::tr("app.window_inner_size"))).ui(ui);
+            egui::Button::new(Self::egui_text(i18n::tr("common.read")))
                 .ui(ui)
                 .clicked()
                 .then(|| {
@@ -268,36 +885,65 @@ impl App {
                                 .map(|[inner_width, inner_height]| {
                                     self.window_modification_cache.inner_width = inner_width;
                                     self.window_modification_cache.inner_height = inner_height;
+                                    self.window_modification_cache.inner_size_expressions =
+                                        Default::default();
                                 })
-                                .map_err(|err| message_dialog::warning(&err.to_string()).show())
+                                .map_err(|err| notification::error(&err))
                         });
                 });
-            egui::Button::new(Self::egui_text("应用"))
+            egui::Button::new(Self::egui_text(i18n::tr("common.apply")))
                 .ui(ui)
                 .clicked()
                 .then(|| {
-                    self.update_current_window_info();
-                    self.current_window_info().map(|window_info| {
-                        window_info
-                            .resize_inner([
-                                self.window_modification_cache.inner_width,
-                                self.window_modification_cache.inner_height,
-                            ])
-                            .map_err(|err| message_dialog::warning(&err.to_string()).show())
-                    });
+                    let inner_size = [
+                        self.window_modification_cache.inner_width,
+                        self.window_modification_cache.inner_height,
+                    ];
+                    self.apply_expressions(
+                        "app.window_inner_size",
+                        self.window_modification_cache
+                            .inner_size_expressions
+                            .clone(),
+                        inner_size,
+                        WindowInfo::resize_inner,
+                    );
                 });
-            egui::Label::new(Self::egui_text("宽度: ")).ui(ui);
-            egui::Slider::new(&mut self.window_modification_cache.inner_width, 0..=8192)
-                .logarithmic(true)
-                .drag_value_speed(1.0)
-                .ui(ui);
-            egui::Label::new(Self::egui_text("高度: ")).ui(ui);
-            egui::Slider::new(&mut self.window_modification_cache.inner_height, 0..=8192)
-                .logarithmic(true)
-                .drag_value_speed(1.0)
-                .ui(ui);
+            egui::Label::new(Self::egui_text(i18n::tr("common.width"))).ui(ui);
+            egui::Slider::new(
+                &mut self.window_modification_cache.inner_width,
+                0..=self.settings.slider_max,
+            )
+            .logarithmic(self.settings.logarithmic_sliders)
+            .drag_value_speed(1.0)
+            .ui(ui)
+            .changed()
+            .then(|| self.window_modification_cache.inner_size_expressions[0].clear());
+            Self::expression_field(
+                ui,
+                &mut self.window_modification_cache.inner_size_expressions[0],
+                &mut self.window_modification_cache.inner_width,
+                current_window_info.as_ref(),
+                true,
+            );
+            egui::Label::new(Self::egui_text(i18n::tr("common.height"))).ui(ui);
+            egui::Slider::new(
+                &mut self.window_modification_cache.inner_height,
+                0..=self.settings.slider_max,
+            )
+            .logarithmic(self.settings.logarithmic_sliders)
+            .drag_value_speed(1.0)
+            .ui(ui)
+            .changed()
+            .then(|| self.window_modification_cache.inner_size_expressions[1].clear());
+            Self::expression_field(
+                ui,
+                &mut self.window_modification_cache.inner_size_expressions[1],
+                &mut self.window_modification_cache.inner_height,
+                current_window_info.as_ref(),
+                false,
+            );
             ui.separator();
-            egui::Label::new(Self::egui_text("当前比例: ")).ui(ui);
+            egui::Label::new(Self::egui_text(i18n::tr("app.current_ratio"))).ui(ui);
             egui::Label::new(Self::egui_text(Self::size_to_ratio_string([
                 self.window_modification_cache.inner_width,
                 self.window_modification_cache.inner_height,
@@ -307,9 +953,10 @@ impl App {
     }
 
     fn modify_window_position(&mut self, ui: &mut egui::Ui) {
+        let current_window_info = self.current_window_info().cloned();
         ui.horizontal_centered(|ui| {
-            egui::Label::new(Self::egui_text("窗口位置: ")).ui(ui);
-            egui::Button::new(Self::egui_text("读取"))
+            egui::Label::new(Self::egui_text(i18n::tr("app.window_position"))).ui(ui);
+            egui::Button::new(Self::egui_text(i18n::tr("common.read")))
                 .ui(ui)
                 .clicked()
                 .then(|| {
@@ -321,153 +968,288 @@ impl App {
                                 .map(|[x, y]| {
                                     self.window_modification_cache.x = x;
                                     self.window_modification_cache.y = y;
+                                    self.window_modification_cache.position_expressions =
+                                        Default::default();
                                 })
-                                .map_err(|err| message_dialog::warning(&err.to_string()).show())
+                                .map_err(|err| notification::error(&err))
                         });
                 });
-            egui::Button::new(Self::egui_text("应用"))
+            egui::Button::new(Self::egui_text(i18n::tr("common.apply")))
                 .ui(ui)
                 .clicked()
                 .then(|| {
-                    self.update_current_window_info();
-                    self.current_window_info().map(|window_info| {
-                        window_info
-                            .move_to([
-                                self.window_modification_cache.x,
-                                self.window_modification_cache.y,
-                            ])
-                            .map_err(|err| message_dialog::warning(&err.to_string()).show())
-                    });
+                    let position = [
+                        self.window_modification_cache.x,
+                        self.window_modification_cache.y,
+                    ];
+                    self.apply_expressions(
+                        "app.window_position",
+                        self.window_modification_cache.position_expressions.clone(),
+                        position,
+                        WindowInfo::move_to,
+                    );
                 });
             egui::Label::new(Self::egui_text("x: ")).ui(ui);
-            egui::Slider::new(&mut self.window_modification_cache.x, -8192..=8192)
-                .logarithmic(true)
-                .drag_value_speed(1.0)
-                .ui(ui);
+            egui::Slider::new(
+                &mut self.window_modification_cache.x,
+                -self.settings.slider_max..=self.settings.slider_max,
+            )
+            .logarithmic(self.settings.logarithmic_sliders)
+            .drag_value_speed(1.0)
+            .ui(ui)
+            .changed()
+            .then(|| self.window_modification_cache.position_expressions[0].clear());
+            Self::expression_field(
+                ui,
+                &mut self.window_modification_cache.position_expressions[0],
+                &mut self.window_modification_cache.x,
+                current_window_info.as_ref(),
+                true,
+            );
             egui::Label::new(Self::egui_text("y: ")).ui(ui);
-            egui::Slider::new(&mut self.window_modification_cache.y, -8192..=8192)
-                .logarithmic(true)
-                .drag_value_speed(1.0)
-                .ui(ui);
+            egui::Slider::new(
+                &mut self.window_modification_cache.y,
+                -self.settings.slider_max..=self.settings.slider_max,
+            )
+            .logarithmic(self.settings.logarithmic_sliders)
+            .drag_value_speed(1.0)
+            .ui(ui)
+            .changed()
+            .then(|| self.window_modification_cache.position_expressions[1].clear());
+            Self::expression_field(
+                ui,
+                &mut self.window_modification_cache.position_expressions[1],
+                &mut self.window_modification_cache.y,
+                current_window_info.as_ref(),
+                false,
+            );
         });
     }
 
     fn modify_window_top_most(&mut self, ui: &mut egui::Ui) {
         ui.horizontal_centered(|ui| {
-            egui::Label::new(Self::egui_text("窗口置顶设置: ")).ui(ui);
-            egui::Button::new(Self::egui_text("启用"))
+            egui::Label::new(Self::egui_text(i18n::tr("app.top_most"))).ui(ui);
+            egui::Button::new(Self::egui_text(i18n::tr("common.enable")))
                 .ui(ui)
                 .clicked()
                 .then(|| {
-                    self.update_current_window_info();
-                    self.current_window_info_mut().map(|window_info| {
-                        window_info
-                            .set_top_most(true)
-                            .map_err(|err| message_dialog::warning(&err.to_string()).show())
-                    });
+                    self.apply(
+                        &Self::operation_name("app.top_most", "common.enable"),
+                        |window_info| window_info.set_top_most(true),
+                    );
                 });
-            egui::Button::new(Self::egui_text("禁用"))
+            egui::Button::new(Self::egui_text(i18n::tr("common.disable")))
                 .ui(ui)
                 .clicked()
                 .then(|| {
-                    self.update_current_window_info();
-                    self.current_window_info_mut().map(|window_info| {
-                        window_info
-                            .set_top_most(false)
-                            .map_err(|err| message_dialog::warning(&err.to_string()).show())
-                    });
+                    self.apply(
+                        &Self::operation_name("app.top_most", "common.disable"),
+                        |window_info| window_info.set_top_most(false),
+                    );
                 });
         });
     }
 
     fn modify_window_maximizable_and_minimizable(&mut self, ui: &mut egui::Ui) {
         ui.horizontal_centered(|ui| {
-            egui::Label::new(Self::egui_text("窗口最大化/最小化设置: ")).ui(ui);
-            egui::Label::new(Self::egui_text("最大化: ")).ui(ui);
-            egui::Button::new(Self::egui_text("启用"))
+            egui::Label::new(Self::egui_text(i18n::tr("app.maximizable_and_minimizable"))).ui(ui);
+            egui::Label::new(Self::egui_text(i18n::tr("app.maximizable"))).ui(ui);
+            egui::Button::new(Self::egui_text(i18n::tr("common.enable")))
                 .ui(ui)
                 .clicked()
                 .then(|| {
-                    self.update_current_window_info();
-                    self.current_window_info_mut()
-                        .map(|window_info| window_info.set_maximizable(true));
+                    self.apply(
+                        &Self::operation_name("app.maximizable", "common.enable"),
+                        |window_info| window_info.set_maximizable(true),
+                    );
                 });
-            egui::Button::new(Self::egui_text("禁用"))
+            egui::Button::new(Self::egui_text(i18n::tr("common.disable")))
                 .ui(ui)
                 .clicked()
                 .then(|| {
-                    self.update_current_window_info();
-                    self.current_window_info_mut()
-                        .map(|window_info| window_info.set_maximizable(false));
+                    self.apply(
+                        &Self::operation_name("app.maximizable", "common.disable"),
+                        |window_info| window_info.set_maximizable(false),
+                    );
                 });
             ui.separator();
-            egui::Label::new(Self::egui_text("最小化: ")).ui(ui);
-            egui::Button::new(Self::egui_text("启用"))
+            egui::Label::new(Self::egui_text(i18n::tr("app.minimizable"))).ui(ui);
+            egui::Button::new(Self::egui_text(i18n::tr("common.enable")))
                 .ui(ui)
                 .clicked()
                 .then(|| {
-                    self.update_current_window_info();
-                    self.current_window_info_mut()
-                        .map(|window_info| window_info.set_minimizable(true));
+                    self.apply(
+                        &Self::operation_name("app.minimizable", "common.enable"),
+                        |window_info| window_info.set_minimizable(true),
+                    );
                 });
-            egui::Button::new(Self::egui_text("禁用"))
+            egui::Button::new(Self::egui_text(i18n::tr("common.disable")))
                 .ui(ui)
                 .clicked()
                 .then(|| {
-                    self.update_current_window_info();
-                    self.current_window_info_mut()
-                        .map(|window_info| window_info.set_minimizable(false));
+                    self.apply(
+                        &Self::operation_name("app.minimizable", "common.disable"),
+                        |window_info| window_info.set_minimizable(false),
+                    );
                 });
         });
     }
 
     fn modify_window_resizable(&mut self, ui: &mut egui::Ui) {
         ui.horizontal_centered(|ui| {
-            egui::Label::new(Self::egui_text("窗口边框拖拽设置: ")).ui(ui);
-            egui::Button::new(Self::egui_text("启用"))
+            egui::Label::new(Self::egui_text(i18n::tr("app.resizable"))).ui(ui);
+            egui::Button::new(Self::egui_text(i18n::tr("common.enable")))
                 .ui(ui)
                 .clicked()
                 .then(|| {
-                    self.update_current_window_info();
-                    self.current_window_info_mut()
-                        .map(|window_info| window_info.set_resizable(true));
+                    self.apply(
+                        &Self::operation_name("app.resizable", "common.enable"),
+                        |window_info| window_info.set_resizable(true),
+                    );
                 });
-            egui::Button::new(Self::egui_text("禁用"))
+            egui::Button::new(Self::egui_text(i18n::tr("common.disable")))
                 .ui(ui)
                 .clicked()
                 .then(|| {
-                    self.update_current_window_info();
-                    self.current_window_info_mut()
-                        .map(|window_info| window_info.set_resizable(false));
+                    self.apply(
+                        &Self::operation_name("app.resizable", "common.disable"),
+                        |window_info| window_info.set_resizable(false),
+                    );
                 });
         });
     }
 
     fn modify_window_fullscreen_status(&mut self, ui: &mut egui::Ui) {
         ui.horizontal_centered(|ui| {
-            egui::Label::new(Self::egui_text("窗口全屏设置: ")).ui(ui);
-            egui::Button::new(Self::egui_text("强制无边框全屏"))
+            egui::Label::new(Self::egui_text(i18n::tr("app.fullscreen"))).ui(ui);
+            egui::Button::new(Self::egui_text(i18n::tr("app.set_borderless_fullscreen")))
                 .ui(ui)
                 .clicked()
                 .then(|| {
-                    self.update_current_window_info();
-                    self.current_window_info_mut().map(|window_info| {
-                        window_info
-                            .set_borderless_fullscreen()
-                            .map_err(|err| message_dialog::warning(&err.to_string()).show())
+                    self.apply(i18n::tr("app.set_borderless_fullscreen"), |window_info| {
+                        window_info.set_borderless_fullscreen()
+                    });
+                });
+            egui::Button::new(Self::egui_text(i18n::tr(
+                "app.restore_from_borderless_fullscreen",
+            )))
+            .ui(ui)
+            .clicked()
+            .then(|| {
+                self.apply(
+                    i18n::tr("app.restore_from_borderless_fullscreen"),
+                    |window_info| window_info.restore_from_borderless_fullscreen(),
+                );
+            });
+        });
+    }
+
+    fn arrange_windows(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_centered(|ui| {
+            egui::Label::new(Self::egui_text(i18n::tr("app.arrange"))).ui(ui);
+            let (arrange, undo) = self.arrangement_panel.show_ui(ui);
+            arrange.then(|| {
+                self.update_current_window_info();
+                let windows = match self.arrangement_panel.whole_process() {
+                    true => self
+                        .current_window_info()
+                        .map(|current| {
+                            self.window_modifier
+                                .window_info_list()
+                                .iter()
+                                .filter(|window_info| window_info.pid == current.pid)
+                                .cloned()
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                    false => self.target_windows(),
+                };
+                self.arrangement_panel
+                    .arrange(windows)
+                    .map(|batch_report| batch_report.notify())
+            });
+            undo.then(|| {
+                self.arrangement_panel
+                    .undo()
+                    .map(|batch_report| batch_report.notify())
+            });
+        });
+    }
+
+    fn align_windows(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_centered(|ui| {
+            egui::Label::new(Self::egui_text(i18n::tr("app.align"))).ui(ui);
+            self.alignment_panel
+                .show_ui(ui, self.window_modifier.window_info_list())
+                .map(|anchor| {
+                    let alignment_panel = self.alignment_panel.clone();
+                    self.apply(anchor.name(), |window_info| {
+                        alignment_panel.align(window_info, anchor)
+                    });
+                });
+        });
+    }
+
+    fn lock_windows(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_centered(|ui| {
+            egui::Label::new(Self::egui_text(i18n::tr("app.lock"))).ui(ui);
+            lock::KINDS.into_iter().for_each(|kind| {
+                let mut checked = self.lock_kinds.contains(&kind);
+                egui::Checkbox::new(&mut checked, Self::egui_text(kind.name()))
+                    .ui(ui)
+                    .changed()
+                    .then(|| match checked {
+                        true => self.lock_kinds.insert(kind),
+                        false => self.lock_kinds.remove(&kind),
                     });
+            });
+            ui.add_enabled(
+                !self.lock_kinds.is_empty(),
+                egui::Button::new(Self::egui_text(i18n::tr("lock.lock"))),
+            )
+            .clicked()
+            .then(|| {
+                let kinds = self.lock_kinds.clone();
+                self.apply(i18n::tr("lock.lock"), |window_info| {
+                    lock::lock(window_info, &kinds)
                 });
-            egui::Button::new(Self::egui_text("还原窗口化"))
+            });
+            egui::Button::new(Self::egui_text(i18n::tr("lock.unlock")))
                 .ui(ui)
                 .clicked()
                 .then(|| {
-                    self.update_current_window_info();
-                    self.current_window_info_mut().map(|window_info| {
-                        window_info
-                            .restore_from_borderless_fullscreen()
-                            .map_err(|err| message_dialog::warning(&err.to_string()).show())
+                    self.apply(i18n::tr("lock.unlock"), |window_info| {
+                        lock::unlock(window_info.key());
+                        Ok(())
                     });
                 });
+            ui.menu_button(
+                Self::egui_text(i18n::tr_format(
+                    "app.active_locks",
+                    &[("count", &lock::count())],
+                )),
+                lock::show_locks_ui,
+            );
+        });
+    }
+
+    fn restore_window_original_status(&mut self, ui: &mut egui::Ui) {
+        let is_modified = self
+            .target_windows()
+            .iter()
+            .any(|window_info| window_info.is_modified());
+        ui.horizontal_centered(|ui| {
+            egui::Label::new(Self::egui_text(i18n::tr("app.original_status"))).ui(ui);
+            ui.add_enabled(
+                is_modified,
+                egui::Button::new(Self::egui_text(i18n::tr("app.restore_original_status"))),
+            )
+            .clicked()
+            .then(|| {
+                self.apply(i18n::tr("app.restore_original_status"), |window_info| {
+                    window_info.restore_original_status()
+                });
+            });
         });
     }
 }
@@ -475,10 +1257,63 @@ impl App {
 impl App {
     pub const FONT_SIZE: f32 = 16.0;
 
+    /// A field for an expression of `value` like `mw-200` or `50%`, evaluated for `window_info`
+    /// when enter is pressed. `%` is of the width of the monitor if `horizontal`, else its height.
+    fn expression_field(
+        ui: &mut egui::Ui,
+        source: &mut String,
+        value: &mut i32,
+        window_info: Option<&WindowInfo>,
+        horizontal: bool,
+    ) {
+        let error = (!source.trim().is_empty())
+            .then(|| Expression::parse(source).err())
+            .flatten();
+        let text_color = error.as_ref().map(|_| ui.visuals().error_fg_color);
+        let response = egui::TextEdit::singleline(source)
+            .hint_text(i18n::tr("app.expression_hint"))
+            .text_color_opt(text_color)
+            .desired_width(90.0)
+            .ui(ui)
+            .on_hover_text(
+                error
+                    .map(|err| err.to_string())
+                    .unwrap_or_else(|| i18n::tr("app.expression_help").to_string()),
+            );
+        let submitted =
+            response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+        if !submitted || source.trim().is_empty() {
+            return;
+        }
+        let variables = match window_info {
+            Some(window_info) => Variables::of_window(window_info),
+            None => Ok(Variables::of_primary_monitor()),
+        };
+        let result = variables
+            .map_err(|err| err.message())
+            .and_then(|variables| {
+                let [_, _, width, height] = variables.monitor;
+                Expression::parse(source)
+                    .and_then(|expression| {
+                        expression.evaluate(&variables, if horizontal { width } else { height })
+                    })
+                    .map_err(|err| err.to_string())
+            });
+        match result {
+            Ok(result) => *value = result,
+            Err(message) => notification::warning(message),
+        }
+    }
+
     fn egui_text(text: impl Into<String>) -> egui::RichText {
         egui::RichText::new(text).size(Self::FONT_SIZE)
     }
 
+    /// like `Always on top: Enable`, the label of a row and the button clicked in it
+    fn operation_name(label_key: &'static str, button_key: &'static str) -> String {
+        format!("{} {}", i18n::tr(label_key), i18n::tr(button_key))
+    }
+
     fn size_to_ratio_string(size: [i32; 2]) -> String {
         let [width, height] = size;
         if width == 0 || height == 0 {
@@ -0,0 +1,391 @@
+use egui::Widget;
+
+use crate::{
+    batch::BatchReport,
+    error::Result,
+    i18n,
+    monitor::{self, MonitorInfo},
+    window_modifier::WindowInfo,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arrangement {
+    /// overlapping windows, each offset from the previous one
+    Cascade,
+    /// an automatic grid, as square as possible
+    Tile,
+    /// full-width rows, top to bottom
+    StackVertical,
+    /// full-height columns, left to right
+    StackHorizontal,
+    /// a fixed number of equal-width columns, each split into rows
+    Columns,
+}
+
+impl Arrangement {
+    pub const ALL: [Self; 5] = [
+        Self::Cascade,
+        Self::Tile,
+        Self::StackVertical,
+        Self::StackHorizontal,
+        Self::Columns,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Cascade => i18n::tr("arrangement.cascade"),
+            Self::Tile => i18n::tr("arrangement.tile"),
+            Self::StackVertical => i18n::tr("arrangement.stack_vertical"),
+            Self::StackHorizontal => i18n::tr("arrangement.stack_horizontal"),
+            Self::Columns => i18n::tr("arrangement.columns"),
+        }
+    }
+}
+
+/// Splits `length` pixels starting at `start` into `count` parts differing by at most one pixel,
+/// returns `(start, length)` of each part.
+fn split(start: i32, length: i32, count: usize) -> Vec<(i32, i32)> {
+    let count = count.max(1) as i32;
+    (0..count)
+        .map(|index| {
+            let part_start = start + length * index / count;
+            let part_end = start + length * (index + 1) / count;
+            (part_start, part_end - part_start)
+        })
+        .collect()
+}
+
+/// `[x, y, width, height]` of `count` windows arranged in `work_area`, in the order of the
+/// windows.
+///
+/// `cascade_offset` is in points, scaled by `scale_factor` to pixels of the monitor.
+pub fn layout(
+    arrangement: Arrangement,
+    count: usize,
+    work_area: [i32; 4],
+    scale_factor: f32,
+    cascade_offset: i32,
+    columns: usize,
+) -> Vec<[i32; 4]> {
+    let [x, y, width, height] = work_area;
+    if count == 0 {
+        return Vec::new();
+    }
+    // `counts[column]` windows stacked in each column
+    let grid = |counts: &[usize]| -> Vec<[i32; 4]> {
+        split(x, width, counts.len())
+            .into_iter()
+            .zip(counts)
+            .flat_map(|((column_x, column_width), &count)| {
+                split(y, height, count)
+                    .into_iter()
+                    .map(move |(row_y, row_height)| [column_x, row_y, column_width, row_height])
+            })
+            .collect()
+    };
+    match arrangement {
+        Arrangement::Cascade => {
+            let offset = ((cascade_offset as f32 * scale_factor).round() as i32).max(1);
+            let [window_width, window_height] = [width * 2 / 3, height * 2 / 3];
+            // windows that fit before starting again from the top left corner
+            let capacity = ((width - window_width).min(height - window_height) / offset).max(0) + 1;
+            (0..count as i32)
+                .map(|index| {
+                    let step = index % capacity;
+                    [
+                        x + step * offset,
+                        y + step * offset,
+                        window_width,
+                        window_height,
+                    ]
+                })
+                .collect()
+        }
+        Arrangement::Tile => {
+            let columns = (count as f64).sqrt().ceil() as usize;
+            let rows = count.div_ceil(columns);
+            // row by row, the last row may have fewer windows and they get wider
+            split(y, height, rows)
+                .into_iter()
+                .enumerate()
+                .flat_map(|(row, (row_y, row_height))| {
+                    let row_count = columns.min(count - row * columns);
+                    split(x, width, row_count)
+                        .into_iter()
+                        .map(move |(column_x, column_width)| {
+                            [column_x, row_y, column_width, row_height]
+                        })
+                })
+                .collect()
+        }
+        Arrangement::StackVertical => grid(&[count]),
+        Arrangement::StackHorizontal => grid(&vec![1; count]),
+        Arrangement::Columns => {
+            let columns = columns.clamp(1, count);
+            // the first columns take one more window if the count is uneven
+            let counts: Vec<_> = (0..columns)
+                .map(|column| count / columns + usize::from(column < count % columns))
+                .collect();
+            grid(&counts)
+        }
+    }
+}
+
+/// `rect: [x, y, width, height]`
+fn move_and_resize(window_info: &WindowInfo, rect: [i32; 4]) -> Result<()> {
+    let [x, y, width, height] = rect;
+    window_info.move_to([x, y])?;
+    window_info.resize([width, height])
+}
+
+/// The options of the arrangement row, kept between frames.
+pub struct ArrangementPanel {
+    arrangement: Arrangement,
+    /// index into `enumerate_monitors`, the primary monitor first
+    monitor: usize,
+    /// points between cascaded windows
+    cascade_offset: i32,
+    columns: usize,
+    /// arrange every window of the process of the current window instead of the selection
+    whole_process: bool,
+    /// `[x, y, width, height]` of the windows before the last arrangement, to undo it
+    last_arrangement: Option<Vec<(WindowInfo, [i32; 4])>>,
+}
+
+impl ArrangementPanel {
+    pub fn new() -> Self {
+        Self {
+            arrangement: Arrangement::Tile,
+            monitor: 0,
+            cascade_offset: 32,
+            columns: 2,
+            whole_process: false,
+            last_arrangement: None,
+        }
+    }
+
+    /// `true` if the windows of the current process are arranged instead of the selection
+    pub fn whole_process(&self) -> bool {
+        self.whole_process
+    }
+
+    /// Arranges `windows` on the chosen monitor, replacing the arrangement to undo.
+    pub fn arrange(&mut self, windows: Vec<WindowInfo>) -> Option<BatchReport> {
+        let monitors = monitor::enumerate_monitors();
+        let monitor_info = monitors.get(self.monitor).or(monitors.first())?;
+        let rects = layout(
+            self.arrangement,
+            windows.len(),
+            monitor_info.work_area,
+            monitor_info.scale_factor(),
+            self.cascade_offset,
+            self.columns,
+        );
+        self.last_arrangement = Some(
+            windows
+                .iter()
+                .filter_map(|window_info| {
+                    let [x, y] = window_info.get_position().ok()?;
+                    let [width, height] = window_info.get_size().ok()?;
+                    Some((window_info.clone(), [x, y, width, height]))
+                })
+                .collect(),
+        );
+        Some(BatchReport::run_with(
+            self.arrangement.name(),
+            windows.into_iter().zip(rects).collect(),
+            move_and_resize,
+        ))
+    }
+
+    /// Moves the windows of the last arrangement back as one step, `None` if there is none.
+    pub fn undo(&mut self) -> Option<BatchReport> {
+        self.last_arrangement.take().map(|last_arrangement| {
+            BatchReport::run_with(
+                i18n::tr("arrangement.undo"),
+                last_arrangement,
+                move_and_resize,
+            )
+        })
+    }
+
+    /// The options, returns `(arrange clicked, undo clicked)`.
+    pub fn show_ui(&mut self, ui: &mut egui::Ui) -> (bool, bool) {
+        const FONT_SIZE: f32 = crate::app::App::FONT_SIZE;
+        let text = |text: &str| egui::RichText::new(text).size(FONT_SIZE);
+        let monitors = monitor::enumerate_monitors();
+        let mut arrange = false;
+        let mut undo = false;
+        egui::ComboBox::from_id_salt("arrangement")
+            .selected_text(text(self.arrangement.name()))
+            .show_ui(ui, |ui| {
+                Arrangement::ALL.into_iter().for_each(|arrangement| {
+                    ui.selectable_value(
+                        &mut self.arrangement,
+                        arrangement,
+                        text(arrangement.name()),
+                    );
+                });
+            });
+        let monitor_name = |monitor_info: &MonitorInfo| {
+            i18n::tr_format(
+                "arrangement.monitor",
+                &[
+                    ("name", &monitor_info.name),
+                    ("width", &monitor_info.rect[2]),
+                    ("height", &monitor_info.rect[3]),
+                ],
+            )
+        };
+        egui::ComboBox::from_id_salt("arrangement_monitor")
+            .selected_text(text(
+                &monitors
+                    .get(self.monitor)
+                    .map(monitor_name)
+                    .unwrap_or_default(),
+            ))
+            .show_ui(ui, |ui| {
+                monitors
+                    .iter()
+                    .enumerate()
+                    .for_each(|(index, monitor_info)| {
+                        ui.selectable_value(
+                            &mut self.monitor,
+                            index,
+                            text(&monitor_name(monitor_info)),
+                        );
+                    });
+            });
+        match self.arrangement {
+            Arrangement::Cascade => {
+                egui::Label::new(text(i18n::tr("arrangement.cascade_offset"))).ui(ui);
+                egui::DragValue::new(&mut self.cascade_offset)
+                    .range(1..=256)
+                    .ui(ui);
+            }
+            Arrangement::Columns => {
+                egui::Label::new(text(i18n::tr("arrangement.column_count"))).ui(ui);
+                egui::DragValue::new(&mut self.columns).range(1..=16).ui(ui);
+            }
+            _ => (),
+        }
+        egui::Checkbox::new(
+            &mut self.whole_process,
+            text(i18n::tr("arrangement.whole_process")),
+        )
+        .ui(ui);
+        arrange |= egui::Button::new(text(i18n::tr("common.apply")))
+            .ui(ui)
+            .clicked();
+        undo |= ui
+            .add_enabled(
+                self.last_arrangement.is_some(),
+                egui::Button::new(text(i18n::tr("arrangement.undo"))),
+            )
+            .clicked();
+        (arrange, undo)
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    /// every rect lies inside `work_area`
+    #[allow(dead_code)]
+    fn inside(rects: &[[i32; 4]], work_area: [i32; 4]) -> bool {
+        let [x, y, width, height] = work_area;
+        rects
+            .iter()
+            .all(|&[rect_x, rect_y, rect_width, rect_height]| {
+                rect_x >= x
+                    && rect_y >= y
+                    && rect_x + rect_width <= x + width
+                    && rect_y + rect_height <= y + height
+            })
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(split(0, 10, 3), [(0, 3), (3, 3), (6, 4)]);
+        assert_eq!(split(-1920, 1920, 2), [(-1920, 960), (-960, 960)]);
+        assert_eq!(split(0, 10, 0), [(0, 10)]);
+    }
+
+    #[test]
+    fn test_tile_uneven() {
+        let work_area = [0, 0, 1920, 1040];
+        let rects = layout(Arrangement::Tile, 5, work_area, 1.0, 32, 2);
+        // 3 columns, the last row has 2 wider windows
+        assert_eq!(
+            rects,
+            [
+                [0, 0, 640, 520],
+                [640, 0, 640, 520],
+                [1280, 0, 640, 520],
+                [0, 520, 960, 520],
+                [960, 520, 960, 520],
+            ]
+        );
+        assert!(inside(&rects, work_area));
+    }
+
+    #[test]
+    fn test_columns_uneven() {
+        let rects = layout(Arrangement::Columns, 5, [100, 0, 1000, 900], 1.0, 32, 2);
+        assert_eq!(
+            rects,
+            [
+                [100, 0, 500, 300],
+                [100, 300, 500, 300],
+                [100, 600, 500, 300],
+                [600, 0, 500, 450],
+                [600, 450, 500, 450],
+            ]
+        );
+        // more columns than windows
+        assert_eq!(
+            layout(Arrangement::Columns, 1, [0, 0, 100, 100], 1.0, 32, 4).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_stacks() {
+        let work_area = [0, 0, 1000, 999];
+        assert_eq!(
+            layout(Arrangement::StackVertical, 3, work_area, 1.0, 32, 2),
+            [[0, 0, 1000, 333], [0, 333, 1000, 333], [0, 666, 1000, 333]]
+        );
+        assert_eq!(
+            layout(Arrangement::StackHorizontal, 3, work_area, 1.0, 32, 2),
+            [[0, 0, 333, 999], [333, 0, 333, 999], [666, 0, 334, 999]]
+        );
+    }
+
+    #[test]
+    fn test_cascade_dpi_scaled() {
+        // a 150% monitor left of the primary one, the taskbar at the bottom
+        let work_area = [-2880, 0, 2880, 1776];
+        let rects = layout(Arrangement::Cascade, 3, work_area, 1.5, 32, 2);
+        assert_eq!(
+            rects,
+            [
+                [-2880, 0, 1920, 1184],
+                [-2832, 48, 1920, 1184],
+                [-2784, 96, 1920, 1184],
+            ]
+        );
+        // starts again from the corner instead of leaving the work area
+        let rects = layout(Arrangement::Cascade, 100, work_area, 1.5, 32, 2);
+        assert!(inside(&rects, work_area));
+        assert_eq!(rects[13], rects[0]);
+    }
+
+    #[test]
+    fn test_empty() {
+        Arrangement::ALL.into_iter().for_each(|arrangement| {
+            assert!(layout(arrangement, 0, [0, 0, 100, 100], 1.0, 32, 2).is_empty())
+        });
+    }
+}
@@ -0,0 +1,110 @@
+use crate::{
+    error::Result,
+    i18n,
+    notification::{self, Severity},
+    window_modifier::WindowInfo,
+};
+
+/// The result of one operation applied to every selected window.
+pub struct BatchReport {
+    /// the name of the operation, shown in the summary
+    pub operation: String,
+    pub results: Vec<(WindowInfo, Result<()>)>,
+}
+
+impl BatchReport {
+    /// Applies `modify` to every window, a failure doesn't stop the others.
+    pub fn run(
+        operation: &str,
+        windows: Vec<WindowInfo>,
+        modify: impl Fn(&WindowInfo) -> Result<()>,
+    ) -> Self {
+        let targets = windows.into_iter().map(|window_info| (window_info, ()));
+        Self::run_with(operation, targets.collect(), |window_info, ()| {
+            modify(window_info)
+        })
+    }
+
+    /// Like `run`, with a value for each window, e.g. where to move it.
+    pub fn run_with<T>(
+        operation: &str,
+        targets: Vec<(WindowInfo, T)>,
+        modify: impl Fn(&WindowInfo, T) -> Result<()>,
+    ) -> Self {
+        let results = targets
+            .into_iter()
+            .map(|(window_info, value)| {
+                let result = modify(&window_info, value);
+                (window_info, result)
+            })
+            .collect();
+        Self {
+            // the labels of the rows end with a colon
+            operation: operation.trim_end_matches([':', '：', ' ']).to_string(),
+            results,
+        }
+    }
+
+    pub fn failed_count(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|(_, result)| result.is_err())
+            .count()
+    }
+
+    pub fn summary(&self) -> String {
+        let failed = self.failed_count();
+        i18n::tr_format(
+            "batch.summary",
+            &[
+                ("operation", &self.operation),
+                ("succeeded", &(self.results.len() - failed)),
+                ("failed", &failed),
+            ],
+        )
+    }
+
+    /// one line per window, the failed ones first
+    pub fn details(&self) -> String {
+        let mut lines: Vec<_> = self
+            .results
+            .iter()
+            .map(|(window_info, result)| match result {
+                Ok(()) => (
+                    true,
+                    i18n::tr_format("batch.succeeded", &[("title", &window_info.title)]),
+                ),
+                Err(err) => (
+                    false,
+                    i18n::tr_format(
+                        "batch.failed",
+                        &[("title", &window_info.title), ("error", &err)],
+                    ),
+                ),
+            })
+            .collect();
+        // stable, keeps the order of the list inside the failed and the succeeded ones
+        lines.sort_by_key(|&(succeeded, _)| succeeded);
+        lines
+            .into_iter()
+            .map(|(_, line)| line)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// A toast with the summary, the result of every window in its details.
+    pub fn notify(&self) {
+        let severity = match self.failed_count() {
+            0 => Severity::Info,
+            failed if failed == self.results.len() => Severity::Error,
+            _ => Severity::Warning,
+        };
+        tracing::info!(
+            operation = self.operation,
+            count = self.results.len(),
+            failed = self.failed_count(),
+            "batch operation applied"
+        );
+        notification::push(severity, self.summary(), Some(self.details()));
+    }
+}
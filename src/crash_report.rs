@@ -0,0 +1,289 @@
+use std::{
+    backtrace::Backtrace,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use windows::{
+    Wdk::System::SystemServices::RtlGetVersion, Win32::System::SystemInformation::OSVERSIONINFOW,
+};
+
+use crate::{i18n, logging, message_dialog, notification, utils};
+
+/// recent log records included in a report
+const RECENT_OPERATION_COUNT: usize = 50;
+
+/// older reports are deleted
+const MAX_REPORT_COUNT: usize = 20;
+
+/// reports the user has been told about are moved here
+const REVIEWED_DIR_NAME: &str = "reviewed";
+
+/// the report of the last panic of this process
+static LAST_REPORT: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+pub struct CrashInfo {
+    pub time: String,
+    pub version: &'static str,
+    pub os_version: String,
+    pub thread: String,
+    /// `file:line:column` of the panic
+    pub location: String,
+    pub message: String,
+    pub backtrace: String,
+    pub recent_operations: Vec<String>,
+}
+
+impl CrashInfo {
+    pub fn to_report(&self) -> String {
+        let mut report = format!(
+            "WindowModifier crash report\n\
+             time: {}\n\
+             version: {}\n\
+             os: {}\n\
+             thread: {}\n\
+             location: {}\n\
+             message: {}\n\n\
+             backtrace:\n{}\n\n\
+             recent operations:\n",
+            self.time,
+            self.version,
+            self.os_version,
+            self.thread,
+            self.location,
+            self.message,
+            self.backtrace.trim_end()
+        );
+        self.recent_operations.iter().for_each(|operation| {
+            report += operation;
+            report.push('\n');
+        });
+        report
+    }
+}
+
+/// `crash_reports` in the app data dir, created if missing.
+pub fn report_dir() -> PathBuf {
+    let dir = utils::app_data_dir().join("crash_reports");
+    let _ = std::fs::create_dir_all(&dir);
+    dir
+}
+
+/// like `Windows 10.0.22631`, `GetVersionExW` would report the version of the manifest instead
+fn os_version() -> String {
+    let mut version_info = OSVERSIONINFOW {
+        dwOSVersionInfoSize: size_of::<OSVERSIONINFOW>() as _,
+        ..Default::default()
+    };
+    if unsafe { RtlGetVersion(&mut version_info) }.is_err() {
+        return "Windows".to_string();
+    }
+    format!(
+        "Windows {}.{}.{}",
+        version_info.dwMajorVersion, version_info.dwMinorVersion, version_info.dwBuildNumber
+    )
+}
+
+fn payload_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(str) = payload.downcast_ref::<&str>() {
+        str.to_string()
+    } else if let Some(string) = payload.downcast_ref::<String>() {
+        string.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
+/// The reports that have not been shown to the user yet, oldest first.
+fn pending_reports(dir: &Path) -> Vec<PathBuf> {
+    let mut reports: Vec<_> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+        .collect();
+    // the file names start with the time
+    reports.sort_unstable();
+    reports
+}
+
+/// Moves the reports to the reviewed directory, keeping only the latest `MAX_REPORT_COUNT`.
+/// Returns the new paths, or the old ones of the reports that could not be moved.
+fn mark_reviewed(dir: &Path, reports: &[PathBuf]) -> Vec<PathBuf> {
+    let reviewed_dir = dir.join(REVIEWED_DIR_NAME);
+    let _ = std::fs::create_dir_all(&reviewed_dir);
+    let moved_reports = reports
+        .iter()
+        .map(|report| {
+            report
+                .file_name()
+                .map(|file_name| reviewed_dir.join(file_name))
+                .filter(|moved_report| std::fs::rename(report, moved_report).is_ok())
+                .unwrap_or_else(|| report.clone())
+        })
+        .collect();
+    let reviewed_reports = pending_reports(&reviewed_dir);
+    reviewed_reports
+        .iter()
+        .take(reviewed_reports.len().saturating_sub(MAX_REPORT_COUNT))
+        .for_each(|report| {
+            let _ = std::fs::remove_file(report);
+        });
+    moved_reports
+}
+
+fn open_folder_of(path: &Path) {
+    let _ = std::process::Command::new("explorer")
+        .arg(format!("/select,{}", path.display()))
+        .spawn();
+}
+
+/// Writes a crash report for every panic, on any thread, then runs the default hook.
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |panic_hook_info| {
+        let time = logging::local_time();
+        let recent_records = logging::recent_records();
+        let crash_info = CrashInfo {
+            time: time.clone(),
+            version: env!("CARGO_PKG_VERSION"),
+            os_version: os_version(),
+            thread: std::thread::current()
+                .name()
+                .unwrap_or("<unnamed>")
+                .to_string(),
+            location: panic_hook_info
+                .location()
+                .map(|location| location.to_string())
+                .unwrap_or_default(),
+            message: payload_message(panic_hook_info.payload()),
+            backtrace: Backtrace::force_capture().to_string(),
+            recent_operations: recent_records
+                .iter()
+                .skip(recent_records.len().saturating_sub(RECENT_OPERATION_COUNT))
+                .map(|record| record.to_string())
+                .collect(),
+        };
+        // `2025-01-31 08:00:00.000` to `crash-20250131-080000000.txt`
+        let file_name = format!(
+            "crash-{}.txt",
+            time.replace(['-', ':', '.'], "").replace(' ', "-")
+        );
+        let path = report_dir().join(file_name);
+        match std::fs::write(&path, crash_info.to_report()) {
+            Ok(()) => *LAST_REPORT.lock().unwrap_or_else(|err| err.into_inner()) = Some(path),
+            Err(err) => tracing::error!(error = %err, "crash report could not be written"),
+        }
+        tracing::error!(
+            thread = crash_info.thread,
+            location = crash_info.location,
+            "panicked: {}",
+            crash_info.message
+        );
+        default_hook(panic_hook_info);
+    }));
+}
+
+/// The error dialog of a panic caught by `graceful_run`, offering to open the report.
+pub fn show_crash_dialog(payload: &(dyn std::any::Any + Send)) {
+    let message = payload_message(payload);
+    let report = LAST_REPORT
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .clone();
+    let Some(report) = report else {
+        message_dialog::error(&message).show();
+        return;
+    };
+    // the user is told about it now, it is not pending on the next launch
+    let report = mark_reviewed(&report_dir(), &[report]).remove(0);
+    let result = message_dialog::error(&i18n::tr_format(
+        "crash.dialog",
+        &[("message", &message), ("path", &report.display())],
+    ))
+    .set_buttons(rfd::MessageButtons::YesNo)
+    .show();
+    if result == rfd::MessageDialogResult::Yes {
+        open_folder_of(&report);
+    }
+}
+
+/// Tells about reports written by earlier runs that crashed before showing the dialog, with a
+/// notification that opens their folder. They are moved aside, so they are told about once.
+pub fn check_pending_reports() {
+    let dir = report_dir();
+    let reports = pending_reports(&dir);
+    if reports.is_empty() {
+        return;
+    }
+    let Some(latest) = mark_reviewed(&dir, &reports).pop() else {
+        return;
+    };
+    let message = i18n::tr_format(
+        "crash.pending_reports",
+        &[
+            ("count", &reports.len()),
+            ("path", &dir.join(REVIEWED_DIR_NAME).display()),
+        ],
+    );
+    tracing::warn!(count = reports.len(), "crash reports of earlier runs found");
+    notification::push_with_action(
+        notification::Severity::Warning,
+        message,
+        None,
+        Some(notification::Action {
+            label: i18n::tr("crash.open_folder").to_string(),
+            run: Arc::new(move || open_folder_of(&latest)),
+        }),
+    );
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_to_report() {
+        let report = CrashInfo {
+            time: "2025-01-31 08:00:00.000".to_string(),
+            version: "0.1.0",
+            os_version: "Windows 10.0.22631".to_string(),
+            thread: "main".to_string(),
+            location: "src/app.rs:1:1".to_string(),
+            message: "oops".to_string(),
+            backtrace: "0: main\n".to_string(),
+            recent_operations: vec!["first".to_string(), "second".to_string()],
+        }
+        .to_report();
+        assert!(report.contains("thread: main\nlocation: src/app.rs:1:1\nmessage: oops\n"));
+        assert!(report.ends_with("backtrace:\n0: main\n\nrecent operations:\nfirst\nsecond\n"));
+    }
+
+    #[test]
+    fn test_pending_and_reviewed_reports() {
+        let dir = std::env::temp_dir().join(format!(
+            "window_modifier_test_crash_reports_{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        ["crash-2.txt", "crash-1.txt", "notes.md"]
+            .into_iter()
+            .for_each(|file_name| std::fs::write(dir.join(file_name), "").unwrap());
+        let reports = pending_reports(&dir);
+        assert_eq!(reports, [dir.join("crash-1.txt"), dir.join("crash-2.txt")]);
+        let reviewed_dir = dir.join(REVIEWED_DIR_NAME);
+        assert_eq!(
+            mark_reviewed(&dir, &reports),
+            [
+                reviewed_dir.join("crash-1.txt"),
+                reviewed_dir.join("crash-2.txt")
+            ]
+        );
+        assert!(pending_reports(&dir).is_empty());
+        assert_eq!(pending_reports(&dir.join(REVIEWED_DIR_NAME)).len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,406 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        LazyLock, Mutex, MutexGuard,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use windows::{
+    Win32::{
+        Foundation::{CloseHandle, FILETIME, HANDLE, HWND, LPARAM},
+        System::{
+            ProcessStatus::GetModuleFileNameExW,
+            Threading::{
+                GetProcessTimes, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_VM_READ,
+            },
+        },
+        UI::WindowsAndMessaging::{EnumChildWindows, IsWindowVisible},
+    },
+    core::BOOL,
+};
+
+use crate::window_modifier::{WindowInfo, window_pid, window_title};
+
+/// the windows found are sent to the UI in batches of this size
+const BATCH_SIZE: usize = 64;
+
+/// A window found by the enumeration, `HWND` is not `Send`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumeratedWindow {
+    pub pid: u32,
+    pub hwnd: usize,
+    pub title: String,
+    pub program_path: String,
+}
+
+impl EnumeratedWindow {
+    pub fn into_window_info(self) -> WindowInfo {
+        WindowInfo {
+            pid: self.pid,
+            hwnd: HWND(self.hwnd as _),
+            title: self.title,
+            program_path: self.program_path,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ProcessInfo {
+    /// a pid can be reused by a later process, which has another creation time
+    created_at: u64,
+    program_path: String,
+}
+
+/// The program paths of the processes with windows, so a refresh doesn't read the module of
+/// every process again.
+#[derive(Debug, Default)]
+struct ProcessCache {
+    processes: HashMap<u32, ProcessInfo>,
+}
+
+impl ProcessCache {
+    /// The cached program path of `pid`, `load`ed if the process was not seen yet or the pid
+    /// belongs to another process now.
+    fn program_path(
+        &mut self,
+        pid: u32,
+        created_at: u64,
+        load: impl FnOnce() -> Option<String>,
+    ) -> Option<String> {
+        if let Some(process) = self
+            .processes
+            .get(&pid)
+            .filter(|process| process.created_at == created_at)
+        {
+            return Some(process.program_path.clone());
+        }
+        let program_path = load()?;
+        self.processes.insert(
+            pid,
+            ProcessInfo {
+                created_at,
+                program_path: program_path.clone(),
+            },
+        );
+        Some(program_path)
+    }
+
+    /// Forgets the processes that have no window anymore.
+    fn retain(&mut self, pids: &HashSet<u32>) {
+        self.processes.retain(|pid, _| pids.contains(pid));
+    }
+}
+
+static PROCESS_CACHE: LazyLock<Mutex<ProcessCache>> = LazyLock::new(Default::default);
+
+fn process_cache() -> MutexGuard<'static, ProcessCache> {
+    PROCESS_CACHE.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// in 100 ns intervals since 1601
+fn creation_time(process: HANDLE) -> Option<u64> {
+    let [
+        mut created_at,
+        mut exited_at,
+        mut kernel_time,
+        mut user_time,
+    ] = [FILETIME::default(); 4];
+    unsafe {
+        GetProcessTimes(
+            process,
+            &mut created_at,
+            &mut exited_at,
+            &mut kernel_time,
+            &mut user_time,
+        )
+    }
+    .ok()?;
+    Some(u64::from(created_at.dwHighDateTime) << 32 | u64::from(created_at.dwLowDateTime))
+}
+
+fn module_file_name(process: HANDLE) -> String {
+    let mut program_path_buf = [0u16; 1024];
+    let program_path_length =
+        unsafe { GetModuleFileNameExW(Some(process), None, &mut program_path_buf) };
+    String::from_utf16_lossy(&program_path_buf[..program_path_length as usize])
+}
+
+/// The path of the executable of `pid`, cached while the process lives. `None` if the process
+/// can't be opened, e.g. a protected system process.
+pub fn program_path(pid: u32) -> Option<String> {
+    let process = unsafe {
+        OpenProcess(
+            PROCESS_QUERY_LIMITED_INFORMATION | PROCESS_VM_READ,
+            false,
+            pid,
+        )
+    }
+    .ok()?;
+    let program_path = match creation_time(process) {
+        Some(created_at) => {
+            process_cache().program_path(pid, created_at, || Some(module_file_name(process)))
+        }
+        None => Some(module_file_name(process)),
+    };
+    let _ = unsafe { CloseHandle(process) };
+    program_path
+}
+
+/// `(pid, hwnd)` of the visible top-level and child windows.
+fn visible_windows() -> Vec<(u32, usize)> {
+    unsafe extern "system" fn enum_child_windows_proc(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        const DEFAULT_RETURN_VALUE: BOOL = BOOL(1);
+        if !unsafe { IsWindowVisible(hwnd) }.as_bool() {
+            return DEFAULT_RETURN_VALUE;
+        }
+        let pid = window_pid(hwnd);
+        if pid == 0 {
+            return DEFAULT_RETURN_VALUE;
+        }
+        let windows = unsafe { &mut *(lparam.0 as *mut Vec<(u32, usize)>) };
+        windows.push((pid, hwnd.0 as usize));
+        DEFAULT_RETURN_VALUE
+    }
+
+    let mut windows = Vec::with_capacity(1024);
+    let _ = unsafe {
+        EnumChildWindows(
+            None,
+            Some(enum_child_windows_proc),
+            LPARAM(&mut windows as *mut _ as _),
+        )
+    };
+    windows
+}
+
+/// The windows of `found` that are not `known`, each once, in the order found.
+fn unknown_windows(found: &[(u32, usize)], known: &HashSet<(u32, usize)>) -> Vec<(u32, usize)> {
+    let mut seen = HashSet::with_capacity(found.len());
+    found
+        .iter()
+        .copied()
+        .filter(|key| !known.contains(key) && seen.insert(*key))
+        .collect()
+}
+
+/// Looks up the title and the program path of the windows, skipping the untitled ones and the
+/// ones whose process can't be opened.
+fn describe_windows(
+    windows: &[(u32, usize)],
+    mut title: impl FnMut(usize) -> String,
+    mut program_path: impl FnMut(u32) -> Option<String>,
+) -> Vec<EnumeratedWindow> {
+    windows
+        .iter()
+        .filter_map(|&(pid, hwnd)| {
+            let title = title(hwnd);
+            if title.is_empty() {
+                return None;
+            }
+            Some(EnumeratedWindow {
+                pid,
+                hwnd,
+                title,
+                program_path: program_path(pid)?,
+            })
+        })
+        .collect()
+}
+
+/// `program_path` called once per process, the windows of a process share its result.
+fn per_process(
+    mut program_path: impl FnMut(u32) -> Option<String>,
+) -> impl FnMut(u32) -> Option<String> {
+    let mut program_paths = HashMap::new();
+    move |pid| {
+        program_paths
+            .entry(pid)
+            .or_insert_with(|| program_path(pid))
+            .clone()
+    }
+}
+
+/// Finds the visible, titled windows that are not `known`, `on_batch` is called with every
+/// [`BATCH_SIZE`] of them.
+pub fn enumerate(known: &HashSet<(u32, usize)>, mut on_batch: impl FnMut(Vec<EnumeratedWindow>)) {
+    let found = visible_windows();
+    // opens every process once per pass instead of once per window
+    let mut program_path = per_process(program_path);
+    unknown_windows(&found, known)
+        .chunks(BATCH_SIZE)
+        .for_each(|windows| {
+            on_batch(describe_windows(
+                windows,
+                |hwnd| window_title(HWND(hwnd as _)),
+                &mut program_path,
+            ))
+        });
+    process_cache().retain(&found.iter().map(|&(pid, _)| pid).collect());
+}
+
+pub enum EnumerationEvent {
+    /// windows found by the running pass
+    Windows(Vec<EnumeratedWindow>),
+    /// the pass is complete
+    Finished { count: usize, elapsed: Duration },
+}
+
+/// Owns a thread that enumerates the windows when requested, so a slow window or process
+/// doesn't freeze the UI. The windows are sent as they are found.
+#[derive(Debug)]
+pub struct EnumerationWorker {
+    /// `None` once dropped, which stops the thread
+    request_sender: Option<Sender<HashSet<(u32, usize)>>>,
+    event_receiver: Receiver<EnumerationEvent>,
+    join_handle: Option<JoinHandle<()>>,
+}
+
+impl EnumerationWorker {
+    pub fn spawn(ctx: egui::Context) -> Self {
+        let (request_sender, request_receiver) = mpsc::channel();
+        let (event_sender, event_receiver) = mpsc::channel();
+        let join_handle = std::thread::Builder::new()
+            .name("enumeration".to_string())
+            .spawn(move || enumeration_thread(ctx, request_receiver, event_sender))
+            .unwrap();
+        Self {
+            request_sender: Some(request_sender),
+            event_receiver,
+            join_handle: Some(join_handle),
+        }
+    }
+
+    /// Starts a pass that skips the `known` windows, requests made while a pass runs are
+    /// merged into one pass after it.
+    pub fn request(&self, known: HashSet<(u32, usize)>) {
+        self.request_sender
+            .as_ref()
+            .map(|request_sender| request_sender.send(known));
+    }
+
+    /// the events since the last call
+    pub fn events(&self) -> impl Iterator<Item = EnumerationEvent> + '_ {
+        self.event_receiver.try_iter()
+    }
+}
+
+impl Drop for EnumerationWorker {
+    fn drop(&mut self) {
+        self.request_sender.take();
+        self.join_handle
+            .take()
+            .map(|join_handle| join_handle.join());
+    }
+}
+
+fn enumeration_thread(
+    ctx: egui::Context,
+    request_receiver: Receiver<HashSet<(u32, usize)>>,
+    event_sender: Sender<EnumerationEvent>,
+) {
+    while let Ok(known) = request_receiver.recv() {
+        let known = request_receiver.try_iter().last().unwrap_or(known);
+        let started_at = Instant::now();
+        let mut count = 0;
+        enumerate(&known, |windows| {
+            count += windows.len();
+            let _ = event_sender.send(EnumerationEvent::Windows(windows));
+            ctx.request_repaint();
+        });
+        let _ = event_sender.send(EnumerationEvent::Finished {
+            count,
+            elapsed: started_at.elapsed(),
+        });
+        ctx.request_repaint();
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_process_cache() {
+        let mut cache = ProcessCache::default();
+        let mut loads = 0;
+        let mut program_path = |cache: &mut ProcessCache, created_at, path: &str| {
+            cache.program_path(42, created_at, || {
+                loads += 1;
+                Some(path.to_string())
+            })
+        };
+        assert_eq!(
+            program_path(&mut cache, 1, "a.exe").as_deref(),
+            Some("a.exe")
+        );
+        assert_eq!(
+            program_path(&mut cache, 1, "a.exe").as_deref(),
+            Some("a.exe")
+        );
+        // the pid was reused by another process
+        assert_eq!(
+            program_path(&mut cache, 2, "b.exe").as_deref(),
+            Some("b.exe")
+        );
+        assert_eq!(loads, 2);
+        cache.retain(&HashSet::new());
+        assert!(cache.processes.is_empty());
+    }
+
+    #[test]
+    fn test_unknown_windows() {
+        let found = [(1, 10), (1, 11), (2, 20), (1, 10), (3, 30)];
+        let known = HashSet::from([(2, 20)]);
+        assert_eq!(unknown_windows(&found, &known), [(1, 10), (1, 11), (3, 30)]);
+    }
+
+    /// `cargo test bench_enumeration -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_enumeration() {
+        const PROCESSES: u32 = 400;
+        const WINDOWS: usize = 5000;
+        let found: Vec<_> = (0..WINDOWS)
+            .map(|index| (index as u32 % PROCESSES + 1000, 0x10000 + index * 4))
+            .collect();
+        let title = |hwnd: usize| format!("window {:#x}", hwnd);
+        let mut cache = ProcessCache::default();
+        let mut pass = |known: &HashSet<(u32, usize)>| {
+            let started_at = Instant::now();
+            // every call stands for an `OpenProcess` and the creation time check
+            let mut opens = 0;
+            let mut loads = 0;
+            let mut program_path = per_process(|pid| {
+                opens += 1;
+                cache.program_path(pid, 1, || {
+                    loads += 1;
+                    Some(format!("C:\\Program Files\\app {}\\app.exe", pid))
+                })
+            });
+            let windows: Vec<_> = unknown_windows(&found, known)
+                .chunks(BATCH_SIZE)
+                .flat_map(|windows| describe_windows(windows, title, &mut program_path))
+                .collect();
+            // releases the counters
+            drop(program_path);
+            (windows, opens, loads, started_at.elapsed())
+        };
+
+        let (windows, opens, loads, elapsed) = pass(&HashSet::new());
+        println!("first pass: {} windows in {:?}", windows.len(), elapsed);
+        assert_eq!(windows.len(), WINDOWS);
+        assert_eq!(opens, PROCESSES as usize);
+        assert_eq!(loads, PROCESSES as usize);
+
+        // a refresh with every window known except the newest tenth
+        let known: HashSet<_> = found[..WINDOWS / 10 * 9].iter().copied().collect();
+        let (windows, opens, loads, elapsed) = pass(&known);
+        println!("refresh: {} new windows in {:?}", windows.len(), elapsed);
+        assert_eq!(windows.len(), WINDOWS / 10);
+        assert_eq!(opens, PROCESSES as usize);
+        assert_eq!(loads, 0);
+    }
+}
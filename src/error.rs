@@ -0,0 +1,117 @@
+use std::fmt::Display;
+
+use windows::Win32::Foundation::{E_ACCESSDENIED, ERROR_INVALID_WINDOW_HANDLE};
+
+use crate::{i18n, logging};
+
+/// A failed operation on a window. The UI shows [`Error::message`], the IPC server and scripts
+/// report [`Error::code`] so clients can react to specific failures.
+#[derive(Debug, Clone)]
+pub enum Error {
+    /// the window was closed, with the error of the Win32 call that noticed it
+    WindowGone(Option<windows::core::Error>),
+    /// the process of the window runs at a higher integrity level, e.g. as administrator, and
+    /// User Interface Privilege Isolation blocks changing its windows
+    ElevatedTarget,
+    /// access denied for another reason
+    AccessDenied(windows::core::Error),
+    /// the window ended up different from the request, e.g. clamped to its minimum size
+    Refused { message: String },
+    /// the window is on no monitor
+    InvalidMonitor,
+    /// the operation doesn't apply to the window, the key of the reason
+    Unsupported(&'static str),
+    /// e.g. an expression that can't be evaluated
+    InvalidArgument(String),
+    /// any other failed Win32 call
+    Os(windows::core::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Stable across versions and languages.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::WindowGone(_) => "window_gone",
+            Self::ElevatedTarget => "elevated_target",
+            Self::AccessDenied(_) => "access_denied",
+            Self::Refused { .. } => "refused",
+            Self::InvalidMonitor => "invalid_monitor",
+            Self::Unsupported(_) => "unsupported",
+            Self::InvalidArgument(_) => "invalid_argument",
+            Self::Os(_) => "os_error",
+        }
+    }
+
+    /// The localized message for the user.
+    pub fn message(&self) -> String {
+        match self {
+            Self::WindowGone(_) => i18n::tr("error.window_gone").to_string(),
+            Self::ElevatedTarget => i18n::tr("error.elevated_target").to_string(),
+            Self::AccessDenied(err) => {
+                i18n::tr_format("error.access_denied", &[("message", &err.message())])
+            }
+            Self::Refused { message } => message.clone(),
+            Self::InvalidMonitor => i18n::tr("error.invalid_monitor").to_string(),
+            Self::Unsupported(reason) => i18n::tr(reason).to_string(),
+            Self::InvalidArgument(message) => message.clone(),
+            Self::Os(err) => err.message(),
+        }
+    }
+
+    /// The underlying OS error, if a Win32 call failed.
+    pub fn os_error(&self) -> Option<&windows::core::Error> {
+        match self {
+            Self::WindowGone(err) => err.as_ref(),
+            Self::AccessDenied(err) | Self::Os(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// like `access_denied 0x80070005`, copied along with the message
+    pub fn details(&self) -> String {
+        match self.os_error() {
+            Some(err) => format!("{} {}", self.code(), logging::error_code(err)),
+            None => self.code().to_string(),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message())
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<windows::core::Error> for Error {
+    fn from(err: windows::core::Error) -> Self {
+        match err.code() {
+            code if code == ERROR_INVALID_WINDOW_HANDLE.to_hresult() => Self::WindowGone(Some(err)),
+            E_ACCESSDENIED => Self::AccessDenied(err),
+            _ => Self::Os(err),
+        }
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_from_os_error() {
+        let window_gone = Error::from(windows::core::Error::from(ERROR_INVALID_WINDOW_HANDLE));
+        assert_eq!(window_gone.code(), "window_gone");
+        assert_eq!(window_gone.details(), "window_gone 0x80070578");
+        assert!(Error::WindowGone(None).os_error().is_none());
+        let access_denied = Error::from(windows::core::Error::from(E_ACCESSDENIED));
+        assert_eq!(access_denied.code(), "access_denied");
+        assert_eq!(
+            access_denied.os_error().map(|err| err.code()),
+            Some(E_ACCESSDENIED)
+        );
+        assert_eq!(access_denied.details(), "access_denied 0x80070005");
+    }
+}
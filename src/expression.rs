@@ -0,0 +1,491 @@
+use std::fmt::Display;
+
+use crate::{error::Error, i18n, monitor, window_modifier::WindowInfo};
+
+/// The names usable in an expression, `[x, y, width, height]` of the monitor, the work area and
+/// the window, and the inner size of the window.
+const VARIABLES: [&str; 14] = [
+    "mx", "my", "mw", "mh", "ax", "ay", "aw", "ah", "x", "y", "w", "h", "iw", "ih",
+];
+
+/// Deeper expressions are rejected, parsing, evaluating and dropping them recurses once per
+/// level and would overflow the stack, which aborts without unwinding.
+const MAX_DEPTH: usize = 64;
+
+/// The values of [`VARIABLES`] for one window.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Variables {
+    /// `[x, y, width, height]`
+    pub monitor: [i32; 4],
+    /// `[x, y, width, height]`
+    pub work_area: [i32; 4],
+    /// `[x, y, width, height]`
+    pub window: [i32; 4],
+    /// `[width, height]`
+    pub inner_size: [i32; 2],
+}
+
+impl Variables {
+    pub fn of_window(window_info: &WindowInfo) -> Result<Self, Error> {
+        let monitor_info =
+            monitor::monitor_of_window(window_info.hwnd).ok_or(Error::InvalidMonitor)?;
+        let [x, y] = window_info.get_position()?;
+        let [width, height] = window_info.get_size()?;
+        Ok(Self {
+            monitor: monitor_info.rect,
+            work_area: monitor_info.work_area,
+            window: [x, y, width, height],
+            inner_size: window_info.get_inner_size()?,
+        })
+    }
+
+    /// only the monitor and the work area of the primary monitor, for when there is no window
+    pub fn of_primary_monitor() -> Self {
+        monitor::enumerate_monitors()
+            .into_iter()
+            .find(|monitor_info| monitor_info.primary)
+            .map(|monitor_info| Self {
+                monitor: monitor_info.rect,
+                work_area: monitor_info.work_area,
+                ..Default::default()
+            })
+            .unwrap_or_default()
+    }
+
+    fn value(&self, name: &str) -> f64 {
+        let values = [
+            self.monitor.as_slice(),
+            &self.work_area,
+            &self.window,
+            &self.inner_size,
+        ]
+        .concat();
+        VARIABLES
+            .iter()
+            .position(|&variable| variable == name)
+            .map(|index| values[index] as f64)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpressionError {
+    Empty,
+    /// `position` counts characters from 1
+    UnexpectedCharacter {
+        character: char,
+        position: usize,
+    },
+    UnexpectedEnd,
+    /// e.g. `1.2.3`
+    InvalidNumber {
+        text: String,
+        position: usize,
+    },
+    UnknownVariable {
+        name: String,
+        position: usize,
+    },
+    /// the `(` at `position` is not closed
+    UnclosedParenthesis {
+        position: usize,
+    },
+    DivisionByZero,
+    /// not a finite number in the range of `i32`
+    OutOfRange,
+    /// nested deeper than [`MAX_DEPTH`]
+    TooDeep,
+}
+
+impl Display for ExpressionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            Self::Empty => i18n::tr("expression.empty").to_string(),
+            Self::UnexpectedCharacter {
+                character,
+                position,
+            } => i18n::tr_format(
+                "expression.unexpected_character",
+                &[("character", character), ("position", position)],
+            ),
+            Self::UnexpectedEnd => i18n::tr("expression.unexpected_end").to_string(),
+            Self::InvalidNumber { text, position } => i18n::tr_format(
+                "expression.invalid_number",
+                &[("text", text), ("position", position)],
+            ),
+            Self::UnknownVariable { name, position } => i18n::tr_format(
+                "expression.unknown_variable",
+                &[
+                    ("name", name),
+                    ("position", position),
+                    ("variables", &VARIABLES.join(", ")),
+                ],
+            ),
+            Self::UnclosedParenthesis { position } => {
+                i18n::tr_format("expression.unclosed_parenthesis", &[("position", position)])
+            }
+            Self::DivisionByZero => i18n::tr("expression.division_by_zero").to_string(),
+            Self::OutOfRange => i18n::tr("expression.out_of_range").to_string(),
+            Self::TooDeep => i18n::tr_format("expression.too_deep", &[("max_depth", &MAX_DEPTH)]),
+        };
+        f.write_str(&message)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Number(f64),
+    Variable(&'static str),
+    /// percent of the width or the height of the monitor, depending on the field
+    Percent(Box<Node>),
+    Negate(Box<Node>),
+    Binary(Box<Node>, Operator, Box<Node>),
+}
+
+/// A parsed expression like `1920*0.75`, `50%`, `mw-200` or `w/2`.
+///
+/// Numbers, the [`VARIABLES`], `+ - * /`, parentheses and a `%` suffix are supported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    root: Node,
+}
+
+/// A recursive descent parser over the characters of the source.
+struct Parser {
+    chars: Vec<char>,
+    index: usize,
+    /// levels of the tree above the node being parsed
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&mut self) -> Option<char> {
+        while self
+            .chars
+            .get(self.index)
+            .is_some_and(|c| c.is_whitespace())
+        {
+            self.index += 1;
+        }
+        self.chars.get(self.index).copied()
+    }
+
+    fn unexpected(&mut self) -> ExpressionError {
+        match self.peek() {
+            Some(character) => ExpressionError::UnexpectedCharacter {
+                character,
+                position: self.index + 1,
+            },
+            None => ExpressionError::UnexpectedEnd,
+        }
+    }
+
+    /// Enters one more level of the tree, a failure ends the parsing.
+    fn descend(&mut self) -> Result<(), ExpressionError> {
+        self.depth += 1;
+        match self.depth > MAX_DEPTH {
+            true => Err(ExpressionError::TooDeep),
+            false => Ok(()),
+        }
+    }
+
+    /// `term (('+' | '-') term)*`, every operator nests the terms before it one level deeper
+    fn expression(&mut self) -> Result<Node, ExpressionError> {
+        let depth = self.depth;
+        let mut node = self.term()?;
+        while let Some(operator) = self.peek().and_then(|c| match c {
+            '+' => Some(Operator::Add),
+            '-' => Some(Operator::Subtract),
+            _ => None,
+        }) {
+            self.index += 1;
+            self.descend()?;
+            node = Node::Binary(Box::new(node), operator, Box::new(self.term()?));
+        }
+        self.depth = depth;
+        Ok(node)
+    }
+
+    /// `unary (('*' | '/') unary)*`
+    fn term(&mut self) -> Result<Node, ExpressionError> {
+        let depth = self.depth;
+        let mut node = self.unary()?;
+        while let Some(operator) = self.peek().and_then(|c| match c {
+            '*' => Some(Operator::Multiply),
+            '/' => Some(Operator::Divide),
+            _ => None,
+        }) {
+            self.index += 1;
+            self.descend()?;
+            node = Node::Binary(Box::new(node), operator, Box::new(self.unary()?));
+        }
+        self.depth = depth;
+        Ok(node)
+    }
+
+    /// `('-' | '+') unary | primary '%'?`
+    fn unary(&mut self) -> Result<Node, ExpressionError> {
+        match self.peek() {
+            Some('-') => {
+                self.index += 1;
+                self.descend()?;
+                let node = Node::Negate(Box::new(self.unary()?));
+                self.depth -= 1;
+                Ok(node)
+            }
+            Some('+') => {
+                self.index += 1;
+                self.descend()?;
+                let node = self.unary()?;
+                self.depth -= 1;
+                Ok(node)
+            }
+            _ => {
+                let node = self.primary()?;
+                match self.peek() {
+                    Some('%') => {
+                        self.index += 1;
+                        Ok(Node::Percent(Box::new(node)))
+                    }
+                    _ => Ok(node),
+                }
+            }
+        }
+    }
+
+    /// `number | variable | '(' expression ')'`
+    fn primary(&mut self) -> Result<Node, ExpressionError> {
+        match self.peek() {
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let start = self.index;
+                while self
+                    .chars
+                    .get(self.index)
+                    .is_some_and(|&c| c.is_ascii_digit() || c == '.')
+                {
+                    self.index += 1;
+                }
+                let text: String = self.chars[start..self.index].iter().collect();
+                text.parse()
+                    .map(Node::Number)
+                    .map_err(|_| ExpressionError::InvalidNumber {
+                        text,
+                        position: start + 1,
+                    })
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let start = self.index;
+                while self
+                    .chars
+                    .get(self.index)
+                    .is_some_and(|c| c.is_ascii_alphanumeric())
+                {
+                    self.index += 1;
+                }
+                let name: String = self.chars[start..self.index].iter().collect();
+                VARIABLES
+                    .iter()
+                    .find(|&&variable| variable == name.to_ascii_lowercase())
+                    .map(|&variable| Node::Variable(variable))
+                    .ok_or(ExpressionError::UnknownVariable {
+                        name,
+                        position: start + 1,
+                    })
+            }
+            Some('(') => {
+                let position = self.index + 1;
+                self.index += 1;
+                self.descend()?;
+                let node = self.expression()?;
+                self.depth -= 1;
+                match self.peek() {
+                    Some(')') => {
+                        self.index += 1;
+                        Ok(node)
+                    }
+                    None => Err(ExpressionError::UnclosedParenthesis { position }),
+                    Some(_) => Err(self.unexpected()),
+                }
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Self, ExpressionError> {
+        let mut parser = Parser {
+            chars: source.chars().collect(),
+            index: 0,
+            depth: 0,
+        };
+        if parser.peek().is_none() {
+            return Err(ExpressionError::Empty);
+        }
+        let root = parser.expression()?;
+        match parser.peek() {
+            Some(_) => Err(parser.unexpected()),
+            None => Ok(Self { root }),
+        }
+    }
+
+    /// The value rounded to the nearest integer, `percent_of` is what `100%` is.
+    pub fn evaluate(&self, variables: &Variables, percent_of: i32) -> Result<i32, ExpressionError> {
+        fn evaluate(
+            node: &Node,
+            variables: &Variables,
+            percent_of: f64,
+        ) -> Result<f64, ExpressionError> {
+            Ok(match node {
+                Node::Number(number) => *number,
+                Node::Variable(name) => variables.value(name),
+                Node::Percent(node) => evaluate(node, variables, percent_of)? * percent_of / 100.0,
+                Node::Negate(node) => -evaluate(node, variables, percent_of)?,
+                Node::Binary(left, operator, right) => {
+                    let left = evaluate(left, variables, percent_of)?;
+                    let right = evaluate(right, variables, percent_of)?;
+                    match operator {
+                        Operator::Add => left + right,
+                        Operator::Subtract => left - right,
+                        Operator::Multiply => left * right,
+                        Operator::Divide if right == 0.0 => {
+                            return Err(ExpressionError::DivisionByZero);
+                        }
+                        Operator::Divide => left / right,
+                    }
+                }
+            })
+        }
+        let value = evaluate(&self.root, variables, percent_of as f64)?.round();
+        match value.is_finite() && value >= i32::MIN as f64 && value <= i32::MAX as f64 {
+            true => Ok(value as i32),
+            false => Err(ExpressionError::OutOfRange),
+        }
+    }
+}
+
+/// Parses the expressions of a `[horizontal, vertical]` pair of fields, `None` for the empty ones.
+pub fn parse_pair(sources: &[String; 2]) -> Result<[Option<Expression>; 2], ExpressionError> {
+    let [horizontal, vertical] = sources.each_ref().map(|source| {
+        (!source.trim().is_empty())
+            .then(|| Expression::parse(source))
+            .transpose()
+    });
+    Ok([horizontal?, vertical?])
+}
+
+/// `values` with the expressions of the pair evaluated for `window_info`, `%` is of the width
+/// or the height of its monitor.
+pub fn resolve_pair(
+    expressions: &[Option<Expression>; 2],
+    values: [i32; 2],
+    window_info: &WindowInfo,
+) -> Result<[i32; 2], Error> {
+    if expressions.iter().all(Option::is_none) {
+        return Ok(values);
+    }
+    let variables = Variables::of_window(window_info)?;
+    let [_, _, width, height] = variables.monitor;
+    let evaluate = |expression: &Option<Expression>, value: i32, percent_of: i32| {
+        expression
+            .as_ref()
+            .map_or(Ok(value), |expression| {
+                expression.evaluate(&variables, percent_of)
+            })
+            .map_err(|err| Error::InvalidArgument(err.to_string()))
+    };
+    Ok([
+        evaluate(&expressions[0], values[0], width)?,
+        evaluate(&expressions[1], values[1], height)?,
+    ])
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[allow(dead_code)]
+    fn variables() -> Variables {
+        Variables {
+            monitor: [0, 0, 1920, 1080],
+            work_area: [0, 0, 1920, 1040],
+            window: [100, 50, 800, 600],
+            inner_size: [784, 561],
+        }
+    }
+
+    #[allow(dead_code)]
+    fn evaluate(source: &str) -> Result<i32, ExpressionError> {
+        Expression::parse(source)?.evaluate(&variables(), 1920)
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(evaluate("1920*0.75"), Ok(1440));
+        assert_eq!(evaluate("50%"), Ok(960));
+        assert_eq!(evaluate("mw-200"), Ok(1720));
+        assert_eq!(evaluate("w/2"), Ok(400));
+        assert_eq!(evaluate(" (aw - w) / 2 + ax "), Ok(560));
+        assert_eq!(evaluate("-x + 2 * 3"), Ok(-94));
+        assert_eq!(evaluate("(mh - ah) * 2"), Ok(80));
+        assert_eq!(evaluate("25% + 10"), Ok(490));
+        assert_eq!(evaluate("W/3"), Ok(267));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(evaluate(""), Err(ExpressionError::Empty));
+        assert_eq!(evaluate("2 +"), Err(ExpressionError::UnexpectedEnd));
+        assert_eq!(
+            evaluate("2 $ 3"),
+            Err(ExpressionError::UnexpectedCharacter {
+                character: '$',
+                position: 3
+            })
+        );
+        assert_eq!(
+            evaluate("width/2"),
+            Err(ExpressionError::UnknownVariable {
+                name: "width".to_string(),
+                position: 1
+            })
+        );
+        assert_eq!(
+            evaluate("(w - 2"),
+            Err(ExpressionError::UnclosedParenthesis { position: 1 })
+        );
+        assert_eq!(
+            evaluate("1.2.3"),
+            Err(ExpressionError::InvalidNumber {
+                text: "1.2.3".to_string(),
+                position: 1
+            })
+        );
+        assert_eq!(evaluate("w/(h-h)"), Err(ExpressionError::DivisionByZero));
+        assert_eq!(evaluate("mw*mw*mw"), Err(ExpressionError::OutOfRange));
+        assert_eq!(
+            evaluate(&"(".repeat(100_000)),
+            Err(ExpressionError::TooDeep)
+        );
+        assert_eq!(
+            evaluate(&"-".repeat(100_000)),
+            Err(ExpressionError::TooDeep)
+        );
+        assert_eq!(
+            evaluate(&["1"; 100_000].join("+")),
+            Err(ExpressionError::TooDeep)
+        );
+        assert_eq!(
+            evaluate(&format!("{}1{}", "(".repeat(20), ")".repeat(20))),
+            Ok(1)
+        );
+    }
+}
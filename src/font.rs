@@ -0,0 +1,316 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Once, OnceLock},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// A face of an installed font file, `index` is its index in a font collection (`.ttc`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FontFace {
+    pub family: String,
+    pub path: PathBuf,
+    pub index: u32,
+}
+
+/// Tried in order after the default fonts of egui, the first font that has a glyph draws it.
+#[cfg(windows)]
+const FALLBACK_FILE_NAMES: [(&str, u32); 7] = [
+    // Chinese, also has Japanese kana
+    ("msyh.ttc", 0),
+    ("simhei.ttf", 0),
+    // Korean
+    ("malgun.ttf", 0),
+    // Japanese kanji variants
+    ("YuGothM.ttc", 0),
+    ("seguisym.ttf", 0),
+    ("seguiemj.ttf", 0),
+    ("seguihis.ttf", 0),
+];
+
+/// fontconfig patterns, resolved with `fc-match`
+#[cfg(unix)]
+const FALLBACK_PATTERNS: [&str; 5] = [
+    "sans-serif:lang=zh-cn",
+    "sans-serif:lang=ko",
+    "sans-serif:lang=ja",
+    "symbol",
+    "emoji",
+];
+
+#[cfg(feature = "bundled-font")]
+const BUNDLED_FONT: &[u8] = include_bytes!("simhei.ttf");
+
+/// The directories Windows installs fonts to, for all users and for the current user.
+#[cfg(windows)]
+fn font_dirs() -> Vec<PathBuf> {
+    [
+        std::env::var_os("WINDIR").map(|dir| PathBuf::from(dir).join("Fonts")),
+        std::env::var_os("LOCALAPPDATA")
+            .map(|dir| PathBuf::from(dir).join(r"Microsoft\Windows\Fonts")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+#[cfg(unix)]
+fn font_dirs() -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    [
+        Some(PathBuf::from("/usr/share/fonts")),
+        Some(PathBuf::from("/usr/local/share/fonts")),
+        home.as_ref().map(|home| home.join(".local/share/fonts")),
+        home.as_ref().map(|home| home.join(".fonts")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Every font file, from fontconfig if available, otherwise found in the font directories.
+fn font_files() -> Vec<PathBuf> {
+    #[cfg(unix)]
+    if let Ok(output) = std::process::Command::new("fc-list")
+        .args(["--format", "%{file}\\n"])
+        .output()
+        && output.status.success()
+    {
+        let mut files: Vec<_> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(PathBuf::from)
+            .collect();
+        files.sort_unstable();
+        files.dedup();
+        return files;
+    }
+    fn visit(dir: &Path, files: &mut Vec<PathBuf>) {
+        std::fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .for_each(|path| {
+                if path.is_dir() {
+                    visit(&path, files);
+                } else if is_font_file(&path) {
+                    files.push(path);
+                }
+            });
+    }
+    let mut files = Vec::new();
+    font_dirs().iter().for_each(|dir| visit(dir, &mut files));
+    files
+}
+
+fn is_font_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            ["ttf", "ttc", "otf", "otc"]
+                .iter()
+                .any(|font_extension| extension.eq_ignore_ascii_case(font_extension))
+        })
+}
+
+/// The family name of a face, English if the font has it, `None` if it can't be parsed.
+fn family_name(face: &ttf_parser::Face) -> Option<String> {
+    const LANGUAGE_ENGLISH_US: u16 = 0x0409;
+    let names: Vec<_> = face
+        .names()
+        .into_iter()
+        .filter(|name| name.name_id == ttf_parser::name_id::FAMILY && name.is_unicode())
+        .collect();
+    names
+        .iter()
+        .find(|name| name.language_id == LANGUAGE_ENGLISH_US)
+        .or(names.first())
+        .and_then(|name| name.to_string())
+}
+
+/// `(face, regular)` of every face in the file.
+fn faces_of_file(path: &Path) -> Vec<(FontFace, bool)> {
+    let Ok(data) = std::fs::read(path) else {
+        return Vec::new();
+    };
+    let count = ttf_parser::fonts_in_collection(&data).unwrap_or(1);
+    (0..count)
+        .filter_map(|index| {
+            let face = ttf_parser::Face::parse(&data, index).ok()?;
+            let family = family_name(&face)?;
+            Some((
+                FontFace {
+                    family,
+                    path: path.to_path_buf(),
+                    index,
+                },
+                face.is_regular(),
+            ))
+        })
+        .collect()
+}
+
+/// One face per family, the regular one if there is one, sorted by family name.
+fn unique_families(mut faces: Vec<(FontFace, bool)>) -> Vec<FontFace> {
+    // stable, regular faces first inside a family
+    faces.sort_by(|(a, a_regular), (b, b_regular)| {
+        a.family
+            .to_lowercase()
+            .cmp(&b.family.to_lowercase())
+            .then(b_regular.cmp(a_regular))
+    });
+    faces.dedup_by(|(a, _), (b, _)| a.family.eq_ignore_ascii_case(&b.family));
+    faces.into_iter().map(|(face, _)| face).collect()
+}
+
+static INSTALLED_FONTS: OnceLock<Vec<FontFace>> = OnceLock::new();
+
+/// Installed font families, `None` while they are still being discovered.
+///
+/// Reading every font file takes a while, so the first call starts it on a background thread.
+pub fn installed_fonts() -> Option<&'static [FontFace]> {
+    static DISCOVERY: Once = Once::new();
+    DISCOVERY.call_once(|| {
+        std::thread::spawn(|| {
+            let faces = font_files()
+                .iter()
+                .flat_map(|path| faces_of_file(path))
+                .collect();
+            let _ = INSTALLED_FONTS.set(unique_families(faces));
+        });
+    });
+    INSTALLED_FONTS.get().map(Vec::as_slice)
+}
+
+/// The installed fonts used for glyphs the default fonts don't have, like CJK and emoji.
+fn fallback_faces() -> Vec<(PathBuf, u32)> {
+    #[cfg(windows)]
+    let faces = {
+        let dirs = font_dirs();
+        FALLBACK_FILE_NAMES
+            .iter()
+            .filter_map(|&(file_name, index)| {
+                dirs.iter()
+                    .map(|dir| dir.join(file_name))
+                    .find(|path| path.is_file())
+                    .map(|path| (path, index))
+            })
+            .collect::<Vec<_>>()
+    };
+    #[cfg(unix)]
+    let faces = {
+        let mut faces = Vec::<(PathBuf, u32)>::new();
+        FALLBACK_PATTERNS.iter().for_each(|pattern| {
+            std::process::Command::new("fc-match")
+                .args(["--format", "%{file}\\n%{index}", pattern])
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .and_then(|output| {
+                    let output = String::from_utf8_lossy(&output.stdout).into_owned();
+                    let (path, index) = output.split_once('\n')?;
+                    Some((PathBuf::from(path), index.trim().parse().unwrap_or(0)))
+                })
+                .filter(|face| !faces.contains(face))
+                .map(|face| faces.push(face));
+        });
+        faces
+    };
+    faces
+}
+
+fn load_font_data(path: &Path, index: u32) -> Option<egui::FontData> {
+    std::fs::read(path).ok().map(|data| {
+        let mut font_data = egui::FontData::from_owned(data);
+        font_data.index = index;
+        font_data
+    })
+}
+
+/// Builds the fonts of the user interface: `ui_font` first if it can be read, then the default
+/// fonts of egui, then the fallback chain of installed fonts, then the bundled font if the
+/// `bundled-font` feature is enabled.
+pub fn font_definitions(ui_font: Option<&FontFace>) -> egui::FontDefinitions {
+    fn add_font(
+        font_definitions: &mut egui::FontDefinitions,
+        name: &str,
+        font_data: egui::FontData,
+        first: bool,
+    ) {
+        font_definitions
+            .font_data
+            .insert(name.to_string(), Arc::new(font_data));
+        font_definitions
+            .families
+            .iter_mut()
+            .filter(|(family, _)| {
+                [egui::FontFamily::Proportional, egui::FontFamily::Monospace].contains(family)
+            })
+            .for_each(|(_, names)| match first {
+                true => names.insert(0, name.to_string()),
+                false => names.push(name.to_string()),
+            });
+    }
+    let mut font_definitions = egui::FontDefinitions::default();
+    ui_font
+        .and_then(|face| load_font_data(&face.path, face.index))
+        .map(|font_data| add_font(&mut font_definitions, "ui_font", font_data, true));
+    fallback_faces()
+        .into_iter()
+        .filter_map(|(path, index)| load_font_data(&path, index))
+        .enumerate()
+        .for_each(|(position, font_data)| {
+            add_font(
+                &mut font_definitions,
+                &format!("fallback_{}", position),
+                font_data,
+                false,
+            )
+        });
+    #[cfg(feature = "bundled-font")]
+    add_font(
+        &mut font_definitions,
+        "simhei",
+        egui::FontData::from_static(BUNDLED_FONT),
+        false,
+    );
+    font_definitions
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[allow(dead_code)]
+    fn face(family: &str, path: &str, regular: bool) -> (FontFace, bool) {
+        let face = FontFace {
+            family: family.to_string(),
+            path: PathBuf::from(path),
+            index: 0,
+        };
+        (face, regular)
+    }
+
+    #[test]
+    fn test_unique_families() {
+        let families = unique_families(vec![
+            face("Segoe UI", "seguisb.ttf", false),
+            face("arial", "arial.ttf", true),
+            face("Segoe UI", "segoeui.ttf", true),
+            face("Arial", "arialbd.ttf", false),
+        ]);
+        let paths: Vec<_> = families
+            .iter()
+            .map(|face| face.path.to_str().unwrap())
+            .collect();
+        assert_eq!(paths, ["arial.ttf", "segoeui.ttf"]);
+    }
+
+    #[test]
+    fn test_is_font_file() {
+        assert!(is_font_file(Path::new("msyh.TTC")));
+        assert!(is_font_file(Path::new("fonts/arial.ttf")));
+        assert!(!is_font_file(Path::new("fonts/fonts.dir")));
+        assert!(!is_font_file(Path::new("ttf")));
+    }
+}
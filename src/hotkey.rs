@@ -0,0 +1,790 @@
+use std::{
+    fmt::Display,
+    sync::mpsc::{self, Receiver, Sender},
+    thread::JoinHandle,
+};
+
+use egui::Widget;
+use serde::{Deserialize, Serialize};
+use windows::{
+    Win32::{
+        Foundation::{
+            E_UNEXPECTED, ERROR_HOTKEY_ALREADY_REGISTERED, HWND, LPARAM, LRESULT, WPARAM,
+        },
+        System::{LibraryLoader::GetModuleHandleW, Threading::GetCurrentThreadId},
+        UI::{
+            Input::KeyboardAndMouse::{
+                HOT_KEY_MODIFIERS, MOD_ALT, MOD_CONTROL, MOD_NOREPEAT, MOD_SHIFT, MOD_WIN,
+                RegisterHotKey, UnregisterHotKey,
+            },
+            WindowsAndMessaging::{
+                CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW,
+                GetForegroundWindow, GetMessageW, HWND_MESSAGE, MSG, PostThreadMessageW,
+                RegisterClassW, WINDOW_EX_STYLE, WINDOW_STYLE, WM_APP, WM_HOTKEY, WM_QUIT,
+                WNDCLASSW,
+            },
+        },
+    },
+    core::w,
+};
+
+use crate::{
+    error::Error,
+    expression::{self, Expression},
+    i18n, notification, utils,
+    window_modifier::WindowInfo,
+};
+
+const HOTKEY_BINDINGS_FILE_NAME: &str = "hotkeys.json";
+
+/// posted to the hotkey thread after new bindings were sent through the channel
+const WM_APP_UPDATE_BINDINGS: u32 = WM_APP + 1;
+
+/// keys that can be bound, with their virtual-key codes
+const KEY_TABLE: &[(egui::Key, u32)] = {
+    use egui::Key;
+    &[
+        (Key::A, 0x41),
+        (Key::B, 0x42),
+        (Key::C, 0x43),
+        (Key::D, 0x44),
+        (Key::E, 0x45),
+        (Key::F, 0x46),
+        (Key::G, 0x47),
+        (Key::H, 0x48),
+        (Key::I, 0x49),
+        (Key::J, 0x4A),
+        (Key::K, 0x4B),
+        (Key::L, 0x4C),
+        (Key::M, 0x4D),
+        (Key::N, 0x4E),
+        (Key::O, 0x4F),
+        (Key::P, 0x50),
+        (Key::Q, 0x51),
+        (Key::R, 0x52),
+        (Key::S, 0x53),
+        (Key::T, 0x54),
+        (Key::U, 0x55),
+        (Key::V, 0x56),
+        (Key::W, 0x57),
+        (Key::X, 0x58),
+        (Key::Y, 0x59),
+        (Key::Z, 0x5A),
+        (Key::Num0, 0x30),
+        (Key::Num1, 0x31),
+        (Key::Num2, 0x32),
+        (Key::Num3, 0x33),
+        (Key::Num4, 0x34),
+        (Key::Num5, 0x35),
+        (Key::Num6, 0x36),
+        (Key::Num7, 0x37),
+        (Key::Num8, 0x38),
+        (Key::Num9, 0x39),
+        (Key::F1, 0x70),
+        (Key::F2, 0x71),
+        (Key::F3, 0x72),
+        (Key::F4, 0x73),
+        (Key::F5, 0x74),
+        (Key::F6, 0x75),
+        (Key::F7, 0x76),
+        (Key::F8, 0x77),
+        (Key::F9, 0x78),
+        (Key::F10, 0x79),
+        (Key::F11, 0x7A),
+        (Key::F12, 0x7B),
+        (Key::Space, 0x20),
+        (Key::Enter, 0x0D),
+        (Key::Tab, 0x09),
+        (Key::Backspace, 0x08),
+        (Key::Insert, 0x2D),
+        (Key::Delete, 0x2E),
+        (Key::Home, 0x24),
+        (Key::End, 0x23),
+        (Key::PageUp, 0x21),
+        (Key::PageDown, 0x22),
+        (Key::ArrowLeft, 0x25),
+        (Key::ArrowUp, 0x26),
+        (Key::ArrowRight, 0x27),
+        (Key::ArrowDown, 0x28),
+    ]
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hotkey {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub win: bool,
+    pub virtual_key: u32,
+}
+
+impl Hotkey {
+    /// A global hotkey without modifiers would swallow the key in every application, so at
+    /// least one modifier is required.
+    pub fn from_egui(modifiers: egui::Modifiers, key: egui::Key) -> Option<Self> {
+        let &(_, virtual_key) = KEY_TABLE.iter().find(|(k, _)| *k == key)?;
+        let hotkey = Self {
+            ctrl: modifiers.ctrl,
+            alt: modifiers.alt,
+            shift: modifiers.shift,
+            win: false,
+            virtual_key,
+        };
+        (hotkey.ctrl || hotkey.alt || hotkey.shift).then_some(hotkey)
+    }
+
+    fn modifiers(&self) -> HOT_KEY_MODIFIERS {
+        [
+            (self.ctrl, MOD_CONTROL),
+            (self.alt, MOD_ALT),
+            (self.shift, MOD_SHIFT),
+            (self.win, MOD_WIN),
+        ]
+        .into_iter()
+        .filter(|&(enabled, _)| enabled)
+        .fold(MOD_NOREPEAT, |modifiers, (_, modifier)| {
+            modifiers | modifier
+        })
+    }
+}
+
+impl Display for Hotkey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        [
+            (self.ctrl, "Ctrl+"),
+            (self.alt, "Alt+"),
+            (self.shift, "Shift+"),
+            (self.win, "Win+"),
+        ]
+        .into_iter()
+        .filter(|&(enabled, _)| enabled)
+        .try_for_each(|(_, name)| f.write_str(name))?;
+        KEY_TABLE
+            .iter()
+            .find(|&&(_, virtual_key)| virtual_key == self.virtual_key)
+            .map(|(key, _)| f.write_str(key.name()))
+            .unwrap_or_else(|| write!(f, "0x{:02X}", self.virtual_key))
+    }
+}
+
+/// The `WindowInfo` operations that a hotkey applies to the foreground window.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HotkeyAction {
+    ToggleBorderlessFullscreen,
+    SetBorderlessFullscreen,
+    RestoreFromBorderlessFullscreen,
+    ToggleTopMost,
+    SetTopMost(bool),
+    SetResizable(bool),
+    SetMaximizable(bool),
+    SetMinimizable(bool),
+    /// `[width, height]` expressions, resolved for the foreground window when triggered
+    #[serde(deserialize_with = "deserialize_expressions")]
+    Resize([String; 2]),
+    /// `[width, height]` expressions
+    #[serde(deserialize_with = "deserialize_expressions")]
+    ResizeInner([String; 2]),
+    /// `[x, y]` expressions
+    #[serde(deserialize_with = "deserialize_expressions")]
+    MoveTo([String; 2]),
+}
+
+/// Bindings saved before expressions were supported store plain numbers.
+fn deserialize_expressions<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<[String; 2], D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Source {
+        Expression(String),
+        Number(i32),
+    }
+    let sources: [Source; 2] = Deserialize::deserialize(deserializer)?;
+    Ok(sources.map(|source| match source {
+        Source::Expression(source) => source,
+        Source::Number(number) => number.to_string(),
+    }))
+}
+
+impl HotkeyAction {
+    fn presets() -> Vec<Self> {
+        let pair =
+            |horizontal: &str, vertical: &str| [horizontal.to_string(), vertical.to_string()];
+        vec![
+            Self::ToggleBorderlessFullscreen,
+            Self::SetBorderlessFullscreen,
+            Self::RestoreFromBorderlessFullscreen,
+            Self::ToggleTopMost,
+            Self::SetTopMost(true),
+            Self::SetTopMost(false),
+            Self::SetResizable(true),
+            Self::SetResizable(false),
+            Self::SetMaximizable(true),
+            Self::SetMaximizable(false),
+            Self::SetMinimizable(true),
+            Self::SetMinimizable(false),
+            Self::Resize(pair("1280", "720")),
+            Self::ResizeInner(pair("1280", "720")),
+            Self::MoveTo(pair("0", "0")),
+        ]
+    }
+
+    /// Sizes and positions are evaluated for `window_info`, an empty expression keeps the
+    /// current value.
+    pub fn apply(&self, window_info: &WindowInfo) -> Result<(), Error> {
+        let resolve = |sources: &[String; 2], current: [i32; 2]| {
+            let expressions = expression::parse_pair(sources)
+                .map_err(|err| Error::InvalidArgument(err.to_string()))?;
+            expression::resolve_pair(&expressions, current, window_info)
+        };
+        match self {
+            Self::ToggleBorderlessFullscreen => {
+                if window_info.is_borderless_fullscreen() {
+                    window_info.restore_from_borderless_fullscreen()
+                } else {
+                    window_info.set_borderless_fullscreen()
+                }
+            }
+            Self::SetBorderlessFullscreen => window_info.set_borderless_fullscreen(),
+            Self::RestoreFromBorderlessFullscreen => {
+                window_info.restore_from_borderless_fullscreen()
+            }
+            Self::ToggleTopMost => window_info.set_top_most(!window_info.is_top_most()),
+            Self::SetTopMost(top_most) => window_info.set_top_most(*top_most),
+            Self::SetResizable(resizable) => window_info.set_resizable(*resizable),
+            Self::SetMaximizable(maximizable) => window_info.set_maximizable(*maximizable),
+            Self::SetMinimizable(minimizable) => window_info.set_minimizable(*minimizable),
+            Self::Resize(sources) => window_info.resize(resolve(sources, window_info.get_size()?)?),
+            Self::ResizeInner(sources) => {
+                window_info.resize_inner(resolve(sources, window_info.get_inner_size()?)?)
+            }
+            Self::MoveTo(sources) => {
+                window_info.move_to(resolve(sources, window_info.get_position()?)?)
+            }
+        }
+    }
+
+    /// parameters of sizes and positions are ignored
+    fn same_kind(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Resize(_), Self::Resize(_))
+            | (Self::ResizeInner(_), Self::ResizeInner(_))
+            | (Self::MoveTo(_), Self::MoveTo(_)) => true,
+            _ => self == other,
+        }
+    }
+
+    fn name(&self) -> String {
+        let switch_text = |key: &'static str, enabled: bool| {
+            let switch = if enabled {
+                i18n::tr("common.enable")
+            } else {
+                i18n::tr("common.disable")
+            };
+            i18n::tr_format(key, &[("switch", &switch)])
+        };
+        match self {
+            Self::ToggleBorderlessFullscreen => {
+                i18n::tr("hotkey.action.toggle_borderless_fullscreen").to_string()
+            }
+            Self::SetBorderlessFullscreen => {
+                i18n::tr("hotkey.action.set_borderless_fullscreen").to_string()
+            }
+            Self::RestoreFromBorderlessFullscreen => {
+                i18n::tr("hotkey.action.restore_from_borderless_fullscreen").to_string()
+            }
+            Self::ToggleTopMost => i18n::tr("hotkey.action.toggle_top_most").to_string(),
+            Self::SetTopMost(enabled) => switch_text("hotkey.action.set_top_most", *enabled),
+            Self::SetResizable(enabled) => switch_text("hotkey.action.set_resizable", *enabled),
+            Self::SetMaximizable(enabled) => switch_text("hotkey.action.set_maximizable", *enabled),
+            Self::SetMinimizable(enabled) => switch_text("hotkey.action.set_minimizable", *enabled),
+            Self::Resize(_) => i18n::tr("hotkey.action.resize").to_string(),
+            Self::ResizeInner(_) => i18n::tr("hotkey.action.resize_inner").to_string(),
+            Self::MoveTo(_) => i18n::tr("hotkey.action.move_to").to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HotkeyBinding {
+    pub hotkey: Hotkey,
+    pub action: HotkeyAction,
+}
+
+impl HotkeyBinding {
+    fn default_bindings() -> Vec<Self> {
+        const F: u32 = 0x46;
+        const T: u32 = 0x54;
+        let ctrl_alt = |virtual_key| Hotkey {
+            ctrl: true,
+            alt: true,
+            shift: false,
+            win: false,
+            virtual_key,
+        };
+        vec![
+            Self {
+                hotkey: ctrl_alt(F),
+                action: HotkeyAction::ToggleBorderlessFullscreen,
+            },
+            Self {
+                hotkey: ctrl_alt(T),
+                action: HotkeyAction::ToggleTopMost,
+            },
+        ]
+    }
+
+    /// The saved bindings, the defaults if there are none. A broken file is reported and backed
+    /// up, it is only overwritten when the user saves the bindings.
+    fn load() -> Vec<Self> {
+        utils::load_json(HOTKEY_BINDINGS_FILE_NAME)
+            .unwrap_or_else(|err| {
+                tracing::warn!(error = %err, "hotkey bindings could not be loaded");
+                notification::warning(i18n::tr_format(
+                    "hotkey.load_failed",
+                    &[("file", &HOTKEY_BINDINGS_FILE_NAME), ("error", &err)],
+                ));
+                None
+            })
+            .unwrap_or_else(Self::default_bindings)
+    }
+
+    fn save(bindings: &[Self]) -> std::io::Result<()> {
+        let text = serde_json::to_string_pretty(bindings)?;
+        std::fs::write(utils::app_data_dir().join(HOTKEY_BINDINGS_FILE_NAME), text)
+    }
+}
+
+pub enum HotkeyEvent {
+    /// registration result of every binding, with its hotkey as the bindings may have been
+    /// edited since they were sent
+    Registered(Vec<(Hotkey, windows::core::Result<()>)>),
+    Triggered {
+        binding: HotkeyBinding,
+        result: Result<(), Error>,
+    },
+}
+
+/// Owns a thread with a message-only window that receives `WM_HOTKEY`, so hotkeys work while
+/// the GUI is minimized or hidden.
+struct HotkeyService {
+    thread_id: u32,
+    bindings_sender: Sender<Vec<HotkeyBinding>>,
+    event_receiver: Receiver<HotkeyEvent>,
+    join_handle: Option<JoinHandle<()>>,
+}
+
+impl HotkeyService {
+    /// fails if the thread could not create its window, no hotkey can be registered then
+    fn spawn(ctx: egui::Context) -> windows::core::Result<Self> {
+        let (thread_id_sender, thread_id_receiver) = mpsc::channel();
+        let (bindings_sender, bindings_receiver) = mpsc::channel();
+        let (event_sender, event_receiver) = mpsc::channel();
+        let join_handle = std::thread::Builder::new()
+            .name("hotkey".to_string())
+            .spawn(move || hotkey_thread(ctx, thread_id_sender, bindings_receiver, event_sender))
+            .unwrap();
+        // the thread only hangs up without sending if it panicked
+        let thread_id = thread_id_receiver
+            .recv()
+            .unwrap_or_else(|_| Err(E_UNEXPECTED.into()));
+        let thread_id = match thread_id {
+            Ok(thread_id) => thread_id,
+            Err(err) => {
+                let _ = join_handle.join();
+                return Err(err);
+            }
+        };
+        Ok(Self {
+            thread_id,
+            bindings_sender,
+            event_receiver,
+            join_handle: Some(join_handle),
+        })
+    }
+
+    fn set_bindings(&self, bindings: Vec<HotkeyBinding>) {
+        if self.bindings_sender.send(bindings).is_err() {
+            return;
+        }
+        let _ = unsafe {
+            PostThreadMessageW(self.thread_id, WM_APP_UPDATE_BINDINGS, WPARAM(0), LPARAM(0))
+        };
+    }
+}
+
+impl Drop for HotkeyService {
+    fn drop(&mut self) {
+        let _ = unsafe { PostThreadMessageW(self.thread_id, WM_QUIT, WPARAM(0), LPARAM(0)) };
+        self.join_handle
+            .take()
+            .map(|join_handle| join_handle.join());
+    }
+}
+
+fn hotkey_thread(
+    ctx: egui::Context,
+    thread_id_sender: Sender<windows::core::Result<u32>>,
+    bindings_receiver: Receiver<Vec<HotkeyBinding>>,
+    event_sender: Sender<HotkeyEvent>,
+) {
+    unsafe extern "system" fn window_proc(
+        hwnd: HWND,
+        msg: u32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) }
+    }
+
+    let hwnd = match create_message_window(window_proc) {
+        Ok(hwnd) => hwnd,
+        Err(err) => {
+            let _ = thread_id_sender.send(Err(err));
+            return;
+        }
+    };
+    let _ = thread_id_sender.send(Ok(unsafe { GetCurrentThreadId() }));
+    let unregister_all = |bindings: &[HotkeyBinding]| {
+        (0..bindings.len()).for_each(|id| {
+            let _ = unsafe { UnregisterHotKey(Some(hwnd), id as i32) };
+        });
+    };
+    let mut bindings: Vec<HotkeyBinding> = Vec::new();
+    let mut msg = MSG::default();
+    while unsafe { GetMessageW(&mut msg, None, 0, 0) }.0 > 0 {
+        match msg.message {
+            WM_HOTKEY => {
+                let Some(binding) = bindings.get(msg.wParam.0) else {
+                    continue;
+                };
+                let foreground_hwnd = unsafe { GetForegroundWindow() };
+                let result = WindowInfo::from_hwnd(foreground_hwnd)
+                    .ok_or(Error::WindowGone(None))
+                    .and_then(|window_info| binding.action.apply(&window_info));
+                let _ = event_sender.send(HotkeyEvent::Triggered {
+                    binding: binding.clone(),
+                    result,
+                });
+                ctx.request_repaint();
+            }
+            WM_APP_UPDATE_BINDINGS => {
+                let Some(new_bindings) = bindings_receiver.try_iter().last() else {
+                    continue;
+                };
+                unregister_all(&bindings);
+                bindings = new_bindings;
+                let results = bindings
+                    .iter()
+                    .enumerate()
+                    .map(|(id, binding)| {
+                        let result = unsafe {
+                            RegisterHotKey(
+                                Some(hwnd),
+                                id as i32,
+                                binding.hotkey.modifiers(),
+                                binding.hotkey.virtual_key,
+                            )
+                        };
+                        (binding.hotkey, result)
+                    })
+                    .collect();
+                let _ = event_sender.send(HotkeyEvent::Registered(results));
+                ctx.request_repaint();
+            }
+            _ => {
+                unsafe { DispatchMessageW(&msg) };
+            }
+        }
+    }
+    unregister_all(&bindings);
+    let _ = unsafe { DestroyWindow(hwnd) };
+}
+
+fn create_message_window(
+    window_proc: unsafe extern "system" fn(HWND, u32, WPARAM, LPARAM) -> LRESULT,
+) -> windows::core::Result<HWND> {
+    let class_name = w!("WindowModifierHotkey");
+    let instance = unsafe { GetModuleHandleW(None) }?;
+    let window_class = WNDCLASSW {
+        lpfnWndProc: Some(window_proc),
+        hInstance: instance.into(),
+        lpszClassName: class_name,
+        ..Default::default()
+    };
+    unsafe { RegisterClassW(&window_class) };
+    unsafe {
+        CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            class_name,
+            class_name,
+            WINDOW_STYLE::default(),
+            0,
+            0,
+            0,
+            0,
+            Some(HWND_MESSAGE),
+            None,
+            Some(instance.into()),
+            None,
+        )
+    }
+}
+
+/// State of the hotkey settings: the edited binding table and the registration status of the
+/// applied one.
+pub struct HotkeyManager {
+    /// `None` if the service could not be started, the bindings can still be edited and saved
+    service: Option<HotkeyService>,
+    bindings: Vec<HotkeyBinding>,
+    /// registration status of the applied bindings
+    registered: Vec<(Hotkey, Result<(), String>)>,
+    /// index of the binding whose hotkey is being recorded
+    recording: Option<usize>,
+}
+
+impl HotkeyManager {
+    pub fn new(ctx: egui::Context) -> Self {
+        let service = HotkeyService::spawn(ctx)
+            .inspect_err(|err| {
+                tracing::warn!(error = %err, "hotkey service could not be started");
+                notification::warning(i18n::tr_format("hotkey.service_failed", &[("error", &err)]));
+            })
+            .ok();
+        let bindings = HotkeyBinding::load();
+        service
+            .as_ref()
+            .map(|service| service.set_bindings(bindings.clone()));
+        Self {
+            service,
+            bindings,
+            registered: Vec::new(),
+            recording: None,
+        }
+    }
+
+    /// returns the error messages of triggered hotkeys
+    pub fn poll_events(&mut self) -> Vec<String> {
+        let mut error_messages = Vec::new();
+        let events: Vec<HotkeyEvent> = self
+            .service
+            .as_ref()
+            .map(|service| service.event_receiver.try_iter().collect())
+            .unwrap_or_default();
+        for event in events {
+            match event {
+                HotkeyEvent::Registered(results) => {
+                    self.registered = results
+                        .into_iter()
+                        .map(|(hotkey, result)| {
+                            let result = result.map_err(|err| {
+                                if err.code() == ERROR_HOTKEY_ALREADY_REGISTERED.into() {
+                                    i18n::tr("hotkey.already_registered").to_string()
+                                } else {
+                                    err.to_string()
+                                }
+                            });
+                            (hotkey, result)
+                        })
+                        .collect();
+                }
+                HotkeyEvent::Triggered { binding, result } => {
+                    if let Err(err) = result {
+                        error_messages.push(format!(
+                            "{} ({}): {}",
+                            binding.hotkey,
+                            binding.action.name(),
+                            err
+                        ));
+                    }
+                }
+            }
+        }
+        error_messages
+    }
+
+    /// returns the error message if saving the bindings failed
+    pub fn show_ui(&mut self, ui: &mut egui::Ui) -> Option<String> {
+        const FONT_SIZE: f32 = crate::app::App::FONT_SIZE;
+        let text = |text: &str| egui::RichText::new(text).size(FONT_SIZE);
+        self.record_hotkey(ui);
+        let mut removed = None;
+        egui::Grid::new(ui.next_auto_id())
+            .num_columns(4)
+            .striped(true)
+            .show(ui, |ui| {
+                egui::Label::new(text(i18n::tr("hotkey.column.hotkey"))).ui(ui);
+                egui::Label::new(text(i18n::tr("hotkey.column.action"))).ui(ui);
+                egui::Label::new(text(i18n::tr("hotkey.column.status"))).ui(ui);
+                ui.end_row();
+                (0..self.bindings.len()).for_each(|index| {
+                    ui.horizontal(|ui| {
+                        let hotkey_text = if self.recording == Some(index) {
+                            i18n::tr("hotkey.recording").to_string()
+                        } else {
+                            self.bindings[index].hotkey.to_string()
+                        };
+                        egui::Button::new(text(&hotkey_text))
+                            .ui(ui)
+                            .clicked()
+                            .then(|| self.recording = Some(index));
+                        egui::Checkbox::new(&mut self.bindings[index].hotkey.win, text("Win"))
+                            .ui(ui);
+                    });
+                    self.action_ui(ui, index);
+                    egui::Label::new(text(&self.status_text(index))).ui(ui);
+                    egui::Button::new(text(i18n::tr("hotkey.delete")))
+                        .ui(ui)
+                        .clicked()
+                        .then(|| removed = Some(index));
+                    ui.end_row();
+                });
+            });
+        if let Some(index) = removed {
+            self.bindings.remove(index);
+            self.recording = None;
+        }
+        let mut save_result = None;
+        ui.horizontal(|ui| {
+            egui::Button::new(text(i18n::tr("hotkey.add")))
+                .ui(ui)
+                .clicked()
+                .then(|| {
+                    let hotkey = Hotkey {
+                        ctrl: true,
+                        alt: true,
+                        shift: false,
+                        win: false,
+                        virtual_key: 0x31,
+                    };
+                    self.bindings.push(HotkeyBinding {
+                        hotkey,
+                        action: HotkeyAction::ToggleBorderlessFullscreen,
+                    });
+                    self.recording = Some(self.bindings.len() - 1);
+                });
+            egui::Button::new(text(i18n::tr("hotkey.save_and_apply")))
+                .ui(ui)
+                .clicked()
+                .then(|| {
+                    self.recording = None;
+                    self.service
+                        .as_ref()
+                        .map(|service| service.set_bindings(self.bindings.clone()));
+                    save_result = Some(HotkeyBinding::save(&self.bindings));
+                });
+        });
+        save_result.and_then(|result| result.err().map(|err| err.to_string()))
+    }
+
+    fn record_hotkey(&mut self, ui: &mut egui::Ui) {
+        let Some(index) = self.recording else {
+            return;
+        };
+        let pressed = ui.input(|input| {
+            input.events.iter().find_map(|event| match event {
+                egui::Event::Key {
+                    key,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } => Some((*key, *modifiers)),
+                _ => None,
+            })
+        });
+        match pressed {
+            Some((egui::Key::Escape, _)) => self.recording = None,
+            Some((key, modifiers)) => {
+                if let Some(hotkey) = Hotkey::from_egui(modifiers, key) {
+                    let win = self.bindings[index].hotkey.win;
+                    self.bindings[index].hotkey = Hotkey { win, ..hotkey };
+                    self.recording = None;
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn action_ui(&mut self, ui: &mut egui::Ui, index: usize) {
+        const FONT_SIZE: f32 = crate::app::App::FONT_SIZE;
+        let action = &mut self.bindings[index].action;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt(("hotkey_action", index))
+                .selected_text(egui::RichText::new(action.name()).size(FONT_SIZE))
+                .show_ui(ui, |ui| {
+                    HotkeyAction::presets().into_iter().for_each(|preset| {
+                        let selected = action.same_kind(&preset);
+                        if ui
+                            .selectable_label(
+                                selected,
+                                egui::RichText::new(preset.name()).size(FONT_SIZE),
+                            )
+                            .clicked()
+                            && !selected
+                        {
+                            *action = preset;
+                        }
+                    });
+                });
+            match action {
+                HotkeyAction::Resize(sources)
+                | HotkeyAction::ResizeInner(sources)
+                | HotkeyAction::MoveTo(sources) => {
+                    sources.iter_mut().for_each(|source| {
+                        let error = (!source.trim().is_empty())
+                            .then(|| Expression::parse(source).err())
+                            .flatten();
+                        let text_color = error.as_ref().map(|_| ui.visuals().error_fg_color);
+                        egui::TextEdit::singleline(source)
+                            .hint_text(i18n::tr("app.expression_hint"))
+                            .text_color_opt(text_color)
+                            .desired_width(90.0)
+                            .ui(ui)
+                            .on_hover_text(
+                                error.map(|err| err.to_string()).unwrap_or_else(|| {
+                                    i18n::tr("hotkey.expression_help").to_string()
+                                }),
+                            );
+                    });
+                }
+                _ => {}
+            }
+        });
+    }
+
+    fn status_text(&self, index: usize) -> String {
+        let hotkey = self.bindings[index].hotkey;
+        if let Some(first) = self.bindings[..index]
+            .iter()
+            .position(|binding| binding.hotkey == hotkey)
+        {
+            return i18n::tr_format("hotkey.conflict", &[("row", &(first + 1))]);
+        }
+        self.registered
+            .iter()
+            .find(|(registered_hotkey, _)| *registered_hotkey == hotkey)
+            .map(|(_, result)| match result {
+                Ok(()) => i18n::tr("hotkey.registered").to_string(),
+                Err(message) => message.clone(),
+            })
+            .unwrap_or_else(|| i18n::tr("hotkey.not_applied").to_string())
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_action_expressions() {
+        let action = HotkeyAction::MoveTo(["(aw-w)/2+ax".to_string(), String::new()]);
+        let json = serde_json::to_string(&action).unwrap();
+        assert_eq!(json, r#"{"MoveTo":["(aw-w)/2+ax",""]}"#);
+        assert_eq!(serde_json::from_str::<HotkeyAction>(&json).unwrap(), action);
+        // saved before expressions were supported
+        assert_eq!(
+            serde_json::from_str::<HotkeyAction>(r#"{"Resize":[1280,720]}"#).unwrap(),
+            HotkeyAction::Resize(["1280".to_string(), "720".to_string()])
+        );
+    }
+}
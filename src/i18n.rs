@@ -0,0 +1,187 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::{
+        LazyLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use windows::Win32::Globalization::GetUserDefaultUILanguage;
+
+/// `LANG_CHINESE`, the primary language of a `LANGID`
+const PRIMARY_LANGUAGE_CHINESE: u16 = 0x04;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    ZhCn,
+    EnUs,
+}
+
+impl Language {
+    pub const ALL: [Self; 2] = [Self::ZhCn, Self::EnUs];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::ZhCn => "zh-CN",
+            Self::EnUs => "en-US",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|language| language.code() == code)
+    }
+
+    /// the name of the language in itself
+    pub fn name(self) -> &'static str {
+        CATALOGS[self as usize]
+            .get("language.name")
+            .map(String::as_str)
+            .unwrap_or(self.code())
+    }
+
+    /// the language of the user interface of Windows, English if not supported
+    pub fn system() -> Self {
+        let language_id = unsafe { GetUserDefaultUILanguage() };
+        if language_id & 0x3ff == PRIMARY_LANGUAGE_CHINESE {
+            Self::ZhCn
+        } else {
+            Self::EnUs
+        }
+    }
+
+    fn catalog_source(self) -> &'static str {
+        match self {
+            Self::ZhCn => include_str!("../locales/zh-CN.txt"),
+            Self::EnUs => include_str!("../locales/en-US.txt"),
+        }
+    }
+}
+
+/// Indexed by `Language as usize`.
+static CATALOGS: LazyLock<Vec<HashMap<&'static str, String>>> = LazyLock::new(|| {
+    Language::ALL
+        .into_iter()
+        .map(|language| parse_catalog(language.catalog_source()))
+        .collect()
+});
+
+static CURRENT_LANGUAGE: AtomicUsize = AtomicUsize::new(Language::ZhCn as usize);
+
+pub fn language() -> Language {
+    Language::ALL[CURRENT_LANGUAGE.load(Ordering::Relaxed)]
+}
+
+pub fn set_language(language: Language) {
+    CURRENT_LANGUAGE.store(language as usize, Ordering::Relaxed);
+}
+
+/// Lines of `key = value`, `#` starts a comment line, `\n` in a value is a line break.
+fn parse_catalog(source: &'static str) -> HashMap<&'static str, String> {
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim().replace("\\n", "\n")))
+        .collect()
+}
+
+/// Translates `key` into the current language, falls back to Chinese and then to the key itself.
+pub fn tr(key: &'static str) -> &'static str {
+    tr_in(language(), key)
+}
+
+fn tr_in(language: Language, key: &'static str) -> &'static str {
+    [language, Language::ZhCn]
+        .into_iter()
+        .find_map(|language| CATALOGS[language as usize].get(key))
+        .map(String::as_str)
+        .unwrap_or(key)
+}
+
+/// [`tr`] with every `{name}` replaced by its argument
+pub fn tr_format(key: &'static str, args: &[(&str, &dyn Display)]) -> String {
+    format(tr(key), args)
+}
+
+fn format(text: &str, args: &[(&str, &dyn Display)]) -> String {
+    args.iter().fold(text.to_string(), |text, (name, value)| {
+        text.replace(&format!("{{{}}}", name), &value.to_string())
+    })
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[allow(dead_code)]
+    fn placeholders(text: &str) -> Vec<&str> {
+        let mut placeholders: Vec<_> = text
+            .split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+            .collect();
+        placeholders.sort_unstable();
+        placeholders
+    }
+
+    #[test]
+    fn test_every_key_in_every_catalog() {
+        Language::ALL.into_iter().for_each(|language| {
+            Language::ALL
+                .into_iter()
+                .filter(|&other| other != language)
+                .for_each(|other| {
+                    let catalog = &CATALOGS[language as usize];
+                    CATALOGS[other as usize].iter().for_each(|(key, value)| {
+                        let translated = catalog.get(key).unwrap_or_else(|| {
+                            panic!("`{}` is missing in {}", key, language.code())
+                        });
+                        assert_eq!(
+                            placeholders(translated),
+                            placeholders(value),
+                            "placeholders of `{}` differ between {} and {}",
+                            key,
+                            language.code(),
+                            other.code()
+                        );
+                    });
+                });
+        });
+    }
+
+    #[test]
+    fn test_no_duplicate_keys() {
+        Language::ALL.into_iter().for_each(|language| {
+            let key_count = language
+                .catalog_source()
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .count();
+            assert_eq!(
+                key_count,
+                CATALOGS[language as usize].len(),
+                "{}",
+                language.code()
+            );
+        });
+    }
+
+    #[test]
+    fn test_tr_format() {
+        assert_eq!(
+            parse_catalog("hotkey.conflict = 与第 {row} 行冲突")["hotkey.conflict"],
+            "与第 {row} 行冲突"
+        );
+        // the current language is shared with the tests running in parallel
+        assert_eq!(
+            format(tr_in(Language::EnUs, "hotkey.conflict"), &[("row", &2)]),
+            "Conflicts with row 2"
+        );
+        assert_eq!(tr_in(Language::EnUs, "no.such.key"), "no.such.key");
+    }
+}
@@ -0,0 +1,371 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use windows::{
+    Win32::{
+        Foundation::{HWND, LPARAM, WPARAM},
+        Graphics::Gdi::{
+            BI_RGB, BITMAP, BITMAPINFO, BITMAPINFOHEADER, DIB_RGB_COLORS, DeleteObject, GetDC,
+            GetDIBits, GetObjectW, HBITMAP, HGDIOBJ, ReleaseDC,
+        },
+        UI::{
+            Shell::ExtractIconExW,
+            WindowsAndMessaging::{
+                DestroyIcon, GCLP_HICON, GCLP_HICONSM, GetClassLongPtrW, GetIconInfo, HICON,
+                ICON_BIG, ICON_SMALL, ICON_SMALL2, ICONINFO, SMTO_ABORTIFHUNG, SendMessageTimeoutW,
+                WM_GETICON,
+            },
+        },
+    },
+    core::HSTRING,
+};
+
+use crate::window_modifier::WindowInfo;
+
+/// milliseconds to wait for a window to answer `WM_GETICON`, hung windows must not hold up the
+/// other icons
+const GET_ICON_TIMEOUT: u32 = 50;
+
+/// how often the UI looks for loaded icons while some are loading
+const LOADED_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+pub const ICON_SIZE: f32 = 20.0;
+
+/// Converts the pixels of an icon read by `GetDIBits` as top-down 32-bit BGRA into RGBA.
+///
+/// `mask` is the AND mask, also as 32-bit BGRA, a white pixel is transparent. It is only used if
+/// `color` has no alpha channel, i.e. every alpha is zero. Monochrome icons have no color bitmap,
+/// their mask is twice as high, the AND mask on top of the XOR mask.
+pub fn icon_bitmap_to_rgba(color: Option<&[u8]>, mask: &[u8]) -> Vec<u8> {
+    let is_transparent = |mask_pixel: &[u8]| mask_pixel[0] != 0;
+    match color {
+        Some(color) => {
+            let has_alpha = color.chunks_exact(4).any(|pixel| pixel[3] != 0);
+            color
+                .chunks_exact(4)
+                .zip(mask.chunks_exact(4).chain(std::iter::repeat(&[0u8; 4][..])))
+                .flat_map(|(pixel, mask_pixel)| {
+                    let alpha = match (has_alpha, is_transparent(mask_pixel)) {
+                        (true, _) => pixel[3],
+                        (false, true) => 0,
+                        (false, false) => 255,
+                    };
+                    [pixel[2], pixel[1], pixel[0], alpha]
+                })
+                .collect()
+        }
+        None => {
+            let (and_mask, xor_mask) = mask.split_at(mask.len() / 2);
+            and_mask
+                .chunks_exact(4)
+                .zip(xor_mask.chunks_exact(4))
+                .flat_map(|(and_pixel, xor_pixel)| {
+                    if is_transparent(and_pixel) {
+                        [0, 0, 0, 0]
+                    } else {
+                        [xor_pixel[2], xor_pixel[1], xor_pixel[0], 255]
+                    }
+                })
+                .collect()
+        }
+    }
+}
+
+/// Reads a bitmap as top-down 32-bit BGRA, returns `(width, height, pixels)`.
+fn bitmap_bgra(hbitmap: HBITMAP) -> Option<(usize, usize, Vec<u8>)> {
+    if hbitmap.is_invalid() {
+        return None;
+    }
+    let mut bitmap = BITMAP::default();
+    let size = unsafe {
+        GetObjectW(
+            HGDIOBJ(hbitmap.0),
+            size_of::<BITMAP>() as _,
+            Some(&mut bitmap as *mut BITMAP as _),
+        )
+    };
+    if size == 0 || bitmap.bmWidth <= 0 || bitmap.bmHeight <= 0 {
+        return None;
+    }
+    let (width, height) = (bitmap.bmWidth as usize, bitmap.bmHeight as usize);
+    let mut bitmap_info = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: size_of::<BITMAPINFOHEADER>() as _,
+            biWidth: width as _,
+            // negative for top-down rows
+            biHeight: -(height as i32),
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut pixels = vec![0u8; width * height * 4];
+    let hdc = unsafe { GetDC(None) };
+    let lines = unsafe {
+        GetDIBits(
+            hdc,
+            hbitmap,
+            0,
+            height as _,
+            Some(pixels.as_mut_ptr() as _),
+            &mut bitmap_info,
+            DIB_RGB_COLORS,
+        )
+    };
+    unsafe { ReleaseDC(None, hdc) };
+    (lines == height as i32).then_some((width, height, pixels))
+}
+
+fn hicon_to_color_image(hicon: HICON) -> Option<egui::ColorImage> {
+    let mut icon_info = ICONINFO::default();
+    unsafe { GetIconInfo(hicon, &mut icon_info) }.ok()?;
+    let color = bitmap_bgra(icon_info.hbmColor);
+    let mask = bitmap_bgra(icon_info.hbmMask);
+    [icon_info.hbmColor, icon_info.hbmMask]
+        .into_iter()
+        .filter(|hbitmap| !hbitmap.is_invalid())
+        .for_each(|hbitmap| {
+            let _ = unsafe { DeleteObject(HGDIOBJ(hbitmap.0)) };
+        });
+    let (mask_width, mask_height, mask) = mask?;
+    let (width, height) = color
+        .as_ref()
+        .map(|(width, height, _)| (*width, *height))
+        .unwrap_or((mask_width, mask_height / 2));
+    let rgba = icon_bitmap_to_rgba(color.as_ref().map(|(_, _, pixels)| &pixels[..]), &mask);
+    (rgba.len() == width * height * 4)
+        .then(|| egui::ColorImage::from_rgba_unmultiplied([width, height], &rgba))
+}
+
+/// The icon of the window, `None` if it has none.
+///
+/// The returned icon is owned by the window or its class and must not be destroyed.
+fn window_hicon(hwnd: HWND) -> Option<HICON> {
+    [ICON_SMALL2, ICON_SMALL, ICON_BIG]
+        .into_iter()
+        .find_map(|icon_type| {
+            let mut result = 0;
+            let succeeded = unsafe {
+                SendMessageTimeoutW(
+                    hwnd,
+                    WM_GETICON,
+                    WPARAM(icon_type as _),
+                    LPARAM(0),
+                    SMTO_ABORTIFHUNG,
+                    GET_ICON_TIMEOUT,
+                    Some(&mut result),
+                )
+            };
+            (succeeded.0 != 0 && result != 0).then_some(HICON(result as _))
+        })
+        .or_else(|| {
+            [GCLP_HICONSM, GCLP_HICON].into_iter().find_map(|index| {
+                let hicon = unsafe { GetClassLongPtrW(hwnd, index) };
+                (hicon != 0).then_some(HICON(hicon as _))
+            })
+        })
+}
+
+/// The first icon of the executable.
+fn program_color_image(program_path: &str) -> Option<egui::ColorImage> {
+    let mut small_icon = HICON::default();
+    let count = unsafe {
+        ExtractIconExW(
+            &HSTRING::from(program_path),
+            0,
+            None,
+            Some(&mut small_icon),
+            1,
+        )
+    };
+    if count == 0 || small_icon.is_invalid() {
+        return None;
+    }
+    let color_image = hicon_to_color_image(small_icon);
+    let _ = unsafe { DestroyIcon(small_icon) };
+    color_image
+}
+
+fn load_color_image(hwnd: HWND, program_path: &str) -> Option<egui::ColorImage> {
+    window_hicon(hwnd)
+        .and_then(hicon_to_color_image)
+        .or_else(|| program_color_image(program_path))
+}
+
+/// A window whose program has no icon loaded yet, `HWND` is not `Send`.
+struct IconRequest {
+    program_path: String,
+    hwnd: usize,
+}
+
+/// Owns a thread that loads the icons, as `WM_GETICON` waits for the window and `ExtractIconExW`
+/// reads the executable. Only the textures are created on the UI thread.
+struct IconWorker {
+    /// `None` once dropped, which stops the thread
+    request_sender: Option<Sender<IconRequest>>,
+    /// `(program path, icon)`, `None` once dropped, so the thread stops after the current icon
+    loaded_receiver: Option<Receiver<(String, Option<egui::ColorImage>)>>,
+    join_handle: Option<JoinHandle<()>>,
+}
+
+impl IconWorker {
+    fn spawn() -> Self {
+        let (request_sender, request_receiver) = mpsc::channel();
+        let (loaded_sender, loaded_receiver) = mpsc::channel();
+        let join_handle = std::thread::Builder::new()
+            .name("icon".to_string())
+            .spawn(move || icon_thread(request_receiver, loaded_sender))
+            .unwrap();
+        Self {
+            request_sender: Some(request_sender),
+            loaded_receiver: Some(loaded_receiver),
+            join_handle: Some(join_handle),
+        }
+    }
+
+    fn request(&self, window_info: &WindowInfo) {
+        self.request_sender.as_ref().map(|request_sender| {
+            request_sender.send(IconRequest {
+                program_path: window_info.program_path.clone(),
+                hwnd: window_info.hwnd.0 as usize,
+            })
+        });
+    }
+
+    /// the icons loaded since the last call
+    fn loaded(&self) -> Vec<(String, Option<egui::ColorImage>)> {
+        self.loaded_receiver
+            .as_ref()
+            .map(|loaded_receiver| loaded_receiver.try_iter().collect())
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for IconWorker {
+    fn drop(&mut self) {
+        self.request_sender.take();
+        self.loaded_receiver.take();
+        self.join_handle
+            .take()
+            .map(|join_handle| join_handle.join());
+    }
+}
+
+fn icon_thread(
+    request_receiver: Receiver<IconRequest>,
+    loaded_sender: Sender<(String, Option<egui::ColorImage>)>,
+) {
+    while let Ok(IconRequest { program_path, hwnd }) = request_receiver.recv() {
+        let color_image = load_color_image(HWND(hwnd as _), &program_path);
+        if loaded_sender.send((program_path, color_image)).is_err() {
+            break;
+        }
+    }
+}
+
+/// Textures of window icons, keyed by program path, kept in the egui context.
+#[derive(Clone, Default)]
+struct IconCache {
+    /// `None` if the program has no icon
+    textures: Arc<HashMap<String, Option<egui::TextureHandle>>>,
+    /// program paths whose icon is loading
+    requested: Arc<HashSet<String>>,
+    /// started by the first icon shown
+    worker: Option<Arc<Mutex<IconWorker>>>,
+}
+
+/// Shows the icon of the window, or an empty space of the same size while it is loading.
+pub fn show_icon(ui: &mut egui::Ui, window_info: &WindowInfo) {
+    let id = egui::Id::new("icon_cache");
+    let ctx = ui.ctx().clone();
+    let mut cache = ctx.data_mut(|data| data.get_temp_mut_or_default::<IconCache>(id).clone());
+    let worker = cache
+        .worker
+        .get_or_insert_with(|| Arc::new(Mutex::new(IconWorker::spawn())))
+        .clone();
+    let worker = worker.lock().unwrap_or_else(|err| err.into_inner());
+    worker
+        .loaded()
+        .into_iter()
+        .for_each(|(program_path, color_image)| {
+            let texture = color_image.map(|color_image| {
+                ctx.load_texture(
+                    format!("icon:{}", program_path),
+                    color_image,
+                    egui::TextureOptions::LINEAR,
+                )
+            });
+            Arc::make_mut(&mut cache.requested).remove(&program_path);
+            Arc::make_mut(&mut cache.textures).insert(program_path, texture);
+        });
+    let texture = match cache.textures.get(&window_info.program_path) {
+        Some(texture) => texture.clone(),
+        None => {
+            if !cache.requested.contains(&window_info.program_path) {
+                worker.request(window_info);
+                Arc::make_mut(&mut cache.requested).insert(window_info.program_path.clone());
+            }
+            None
+        }
+    };
+    if !cache.requested.is_empty() {
+        ctx.request_repaint_after(LOADED_POLL_INTERVAL);
+    }
+    drop(worker);
+    ctx.data_mut(|data| data.insert_temp(id, cache));
+    match texture {
+        Some(texture) => {
+            ui.add(egui::Image::new(&texture).fit_to_exact_size(egui::Vec2::splat(ICON_SIZE)));
+        }
+        None => {
+            ui.allocate_space(egui::Vec2::splat(ICON_SIZE));
+        }
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_icon_bitmap_to_rgba_with_alpha() {
+        let color = [1, 2, 3, 128, 4, 5, 6, 0];
+        let mask = [255, 255, 255, 0, 0, 0, 0, 0];
+        assert_eq!(
+            icon_bitmap_to_rgba(Some(&color), &mask),
+            [3, 2, 1, 128, 6, 5, 4, 0]
+        );
+    }
+
+    #[test]
+    fn test_icon_bitmap_to_rgba_with_mask() {
+        let color = [1, 2, 3, 0, 4, 5, 6, 0];
+        let mask = [255, 255, 255, 0, 0, 0, 0, 0];
+        assert_eq!(
+            icon_bitmap_to_rgba(Some(&color), &mask),
+            [3, 2, 1, 0, 6, 5, 4, 255]
+        );
+    }
+
+    #[test]
+    fn test_icon_bitmap_to_rgba_monochrome() {
+        // AND mask rows on top of XOR mask rows
+        let mask = [
+            255, 255, 255, 0, 0, 0, 0, 0, //
+            0, 0, 0, 0, 255, 255, 255, 0,
+        ];
+        assert_eq!(
+            icon_bitmap_to_rgba(None, &mask),
+            [0, 0, 0, 0, 255, 255, 255, 255]
+        );
+    }
+}
@@ -0,0 +1,585 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use windows::Win32::{Foundation::HWND, UI::WindowsAndMessaging::IsWindow};
+
+use crate::{
+    error::Error,
+    window_modifier::{WindowInfo, find_windows},
+};
+
+pub mod client;
+/// named pipes on Windows, Unix domain sockets elsewhere
+pub mod transport;
+
+/// Bumped on incompatible changes of a method's params or result; requests may name the version
+/// they were written for in `params.version`, missing means 1.
+pub const PROTOCOL_VERSION: u32 = 1;
+pub const SUPPORTED_VERSIONS: [u32; 1] = [1];
+
+pub mod error_code {
+    pub const PARSE_ERROR: i32 = -32700;
+    pub const INVALID_REQUEST: i32 = -32600;
+    pub const METHOD_NOT_FOUND: i32 = -32601;
+    pub const INVALID_PARAMS: i32 = -32602;
+    pub const UNSUPPORTED_VERSION: i32 = -32001;
+    pub const WINDOW_NOT_FOUND: i32 = -32002;
+    /// `data` is the HRESULT of the failed Win32 call
+    pub const OS_ERROR: i32 = -32003;
+    /// the window belongs to an elevated process, or access was denied otherwise
+    pub const ACCESS_DENIED: i32 = -32004;
+    /// the window ended up different from the request, e.g. clamped to its minimum size
+    pub const REFUSED: i32 = -32005;
+    /// the operation doesn't apply to the window, or it is on no monitor
+    pub const UNSUPPORTED: i32 = -32006;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Request {
+    pub jsonrpc: String,
+    #[serde(default)]
+    pub id: Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i32,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    pub fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+impl std::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+/// `data` is the [`Error::code`], like `elevated_target`, except for `OS_ERROR`.
+impl From<Error> for RpcError {
+    fn from(err: Error) -> Self {
+        let code = match err {
+            Error::WindowGone(_) => error_code::WINDOW_NOT_FOUND,
+            Error::ElevatedTarget | Error::AccessDenied(_) => error_code::ACCESS_DENIED,
+            Error::Refused { .. } => error_code::REFUSED,
+            Error::InvalidMonitor | Error::Unsupported(_) => error_code::UNSUPPORTED,
+            Error::InvalidArgument(_) => error_code::INVALID_PARAMS,
+            Error::Os(_) => error_code::OS_ERROR,
+        };
+        let data = match err.os_error() {
+            Some(os_error) if code == error_code::OS_ERROR => os_error.code().0.into(),
+            _ => err.code().into(),
+        };
+        Self {
+            code,
+            message: err.message(),
+            data: Some(data),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Response {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+/// Result of `version`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VersionInfo {
+    pub versions: Vec<u32>,
+    pub app_version: String,
+}
+
+/// An item of the results of `window.list` and `window.query`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowSummary {
+    pub pid: u32,
+    pub hwnd: u64,
+    pub title: String,
+    pub program_path: String,
+}
+
+/// Result of `window.get_geometry`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Geometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub inner_width: i32,
+    pub inner_height: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryParams {
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowParams {
+    pub hwnd: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResizeParams {
+    pub hwnd: u64,
+    pub width: i32,
+    pub height: i32,
+    /// resize the client area instead of the whole window
+    #[serde(default)]
+    pub inner: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoveParams {
+    pub hwnd: u64,
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopMostParams {
+    pub hwnd: u64,
+    pub top_most: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FullscreenParams {
+    pub hwnd: u64,
+    pub fullscreen: bool,
+}
+
+/// Answers the requests of every connection, shared between the connection threads.
+pub trait RequestHandler: Send + Sync + 'static {
+    /// `params` without `version`, which is already checked
+    fn handle(&self, method: &str, params: Value) -> Result<Value, RpcError>;
+}
+
+pub fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params)
+        .map_err(|err| RpcError::new(error_code::INVALID_PARAMS, err.to_string()))
+}
+
+fn to_value(value: impl Serialize) -> Result<Value, RpcError> {
+    Ok(serde_json::to_value(value).unwrap())
+}
+
+/// Checks the envelope and the version, then calls `handler`.
+pub fn handle_line(handler: &dyn RequestHandler, line: &str) -> Response {
+    let response = |id: Value, result: Result<Value, RpcError>| {
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(err) => (None, Some(err)),
+        };
+        Response {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result,
+            error,
+        }
+    };
+    let request: Request = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(err) => {
+            let code = if serde_json::from_str::<Value>(line).is_ok() {
+                error_code::INVALID_REQUEST
+            } else {
+                error_code::PARSE_ERROR
+            };
+            return response(Value::Null, Err(RpcError::new(code, err.to_string())));
+        }
+    };
+    if request.jsonrpc != "2.0" {
+        return response(
+            request.id,
+            Err(RpcError::new(
+                error_code::INVALID_REQUEST,
+                "`jsonrpc` must be \"2.0\"",
+            )),
+        );
+    }
+    let mut params = match request.params {
+        Value::Null => Value::Object(Default::default()),
+        params => params,
+    };
+    let version = params
+        .as_object_mut()
+        .and_then(|params| params.remove("version"))
+        .map(|version| version.as_u64().unwrap_or(0) as u32)
+        .unwrap_or(1);
+    if !SUPPORTED_VERSIONS.contains(&version) {
+        return response(
+            request.id,
+            Err(RpcError::new(
+                error_code::UNSUPPORTED_VERSION,
+                format!("unsupported version {}", version),
+            )),
+        );
+    }
+    let result = match request.method.as_str() {
+        "version" => to_value(VersionInfo {
+            versions: SUPPORTED_VERSIONS.to_vec(),
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+        }),
+        method => handler.handle(method, params),
+    };
+    response(request.id, result)
+}
+
+/// Serves every connection on its own thread until dropped.
+pub struct IpcServer {
+    endpoint: String,
+    stopped: Arc<AtomicBool>,
+    connections: Arc<Mutex<Vec<Connection>>>,
+    join_handle: Option<JoinHandle<()>>,
+}
+
+impl IpcServer {
+    pub fn start(endpoint: &str, handler: impl RequestHandler) -> std::io::Result<Self> {
+        let mut listener = transport::Listener::bind(endpoint)?;
+        let handler = Arc::new(handler);
+        let stopped = Arc::new(AtomicBool::new(false));
+        let connections: Arc<Mutex<Vec<Connection>>> = Default::default();
+        let thread_stopped = stopped.clone();
+        let thread_connections = connections.clone();
+        let join_handle = std::thread::Builder::new()
+            .name("ipc".to_string())
+            .spawn(move || {
+                while let Ok(stream) = listener.accept() {
+                    if thread_stopped.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let Ok(connection_stream) = stream.try_clone() else {
+                        continue;
+                    };
+                    let handler = handler.clone();
+                    let stopped = thread_stopped.clone();
+                    let Ok(join_handle) = std::thread::Builder::new()
+                        .name("ipc connection".to_string())
+                        .spawn(move || serve_connection(stream, handler.as_ref(), &stopped))
+                    else {
+                        continue;
+                    };
+                    let mut connections = thread_connections
+                        .lock()
+                        .unwrap_or_else(|err| err.into_inner());
+                    connections.retain(|connection| !connection.is_finished());
+                    connections.push(Connection {
+                        stream: connection_stream,
+                        join_handle,
+                    });
+                }
+            })?;
+        Ok(Self {
+            endpoint: endpoint.to_string(),
+            stopped,
+            connections,
+            join_handle: Some(join_handle),
+        })
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        // wakes up the blocking accept
+        let _ = transport::connect(&self.endpoint);
+        self.join_handle
+            .take()
+            .map(|join_handle| join_handle.join());
+        let connections = std::mem::take(
+            &mut *self
+                .connections
+                .lock()
+                .unwrap_or_else(|err| err.into_inner()),
+        );
+        connections.into_iter().for_each(Connection::close);
+    }
+}
+
+/// A connection served on its own thread, closed when the server is dropped so no client keeps
+/// modifying windows after the server was disabled.
+struct Connection {
+    /// a clone of the served stream
+    stream: transport::Stream,
+    join_handle: JoinHandle<()>,
+}
+
+impl Connection {
+    fn is_finished(&self) -> bool {
+        self.join_handle.is_finished()
+    }
+
+    fn close(self) {
+        // the thread may start another read right after one was interrupted
+        while !self.is_finished() {
+            transport::shutdown(&self.stream);
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.join_handle.join();
+    }
+}
+
+/// one request per line, one response per line, until the client disconnects or the server stops
+fn serve_connection(stream: transport::Stream, handler: &dyn RequestHandler, stopped: &AtomicBool) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    BufReader::new(stream)
+        .lines()
+        .map_while(Result::ok)
+        .take_while(|_| !stopped.load(Ordering::Relaxed))
+        .filter(|line| !line.trim().is_empty())
+        .try_for_each(|line| {
+            let response = handle_line(handler, &line);
+            let mut text = serde_json::to_string(&response).unwrap();
+            text.push('\n');
+            writer
+                .write_all(text.as_bytes())
+                .and_then(|_| writer.flush())
+        })
+        .unwrap_or_default();
+}
+
+/// Serves the requests with the real windows of the desktop.
+pub struct WindowHandler;
+
+impl WindowHandler {
+    fn window_info(hwnd: u64) -> Result<WindowInfo, RpcError> {
+        let hwnd = HWND(hwnd as usize as _);
+        unsafe { IsWindow(Some(hwnd)) }
+            .as_bool()
+            .then(|| WindowInfo::from_hwnd(hwnd))
+            .flatten()
+            .ok_or_else(|| RpcError::new(error_code::WINDOW_NOT_FOUND, "window not found"))
+    }
+
+    fn summaries(window_info_list: Vec<WindowInfo>) -> Vec<WindowSummary> {
+        window_info_list
+            .into_iter()
+            .map(|window_info| WindowSummary {
+                pid: window_info.pid,
+                hwnd: window_info.hwnd.0 as usize as u64,
+                title: window_info.title,
+                program_path: window_info.program_path,
+            })
+            .collect()
+    }
+}
+
+impl RequestHandler for WindowHandler {
+    fn handle(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "window.list" => to_value(Self::summaries(find_windows(""))),
+            "window.query" => {
+                let QueryParams { text } = parse_params(params)?;
+                to_value(Self::summaries(find_windows(&text)))
+            }
+            "window.get_geometry" => {
+                let WindowParams { hwnd } = parse_params(params)?;
+                let window_info = Self::window_info(hwnd)?;
+                let [x, y] = window_info.get_position()?;
+                let [width, height] = window_info.get_size()?;
+                let [inner_width, inner_height] = window_info.get_inner_size()?;
+                to_value(Geometry {
+                    x,
+                    y,
+                    width,
+                    height,
+                    inner_width,
+                    inner_height,
+                })
+            }
+            "window.resize" => {
+                let ResizeParams {
+                    hwnd,
+                    width,
+                    height,
+                    inner,
+                } = parse_params(params)?;
+                let window_info = Self::window_info(hwnd)?;
+                if inner {
+                    window_info.resize_inner([width, height])?;
+                } else {
+                    window_info.resize([width, height])?;
+                }
+                Ok(Value::Null)
+            }
+            "window.move" => {
+                let MoveParams { hwnd, x, y } = parse_params(params)?;
+                Self::window_info(hwnd)?.move_to([x, y])?;
+                Ok(Value::Null)
+            }
+            "window.set_top_most" => {
+                let TopMostParams { hwnd, top_most } = parse_params(params)?;
+                Self::window_info(hwnd)?.set_top_most(top_most)?;
+                Ok(Value::Null)
+            }
+            "window.set_fullscreen" => {
+                let FullscreenParams { hwnd, fullscreen } = parse_params(params)?;
+                let window_info = Self::window_info(hwnd)?;
+                if fullscreen {
+                    window_info.set_borderless_fullscreen()?;
+                } else {
+                    window_info.restore_from_borderless_fullscreen()?;
+                }
+                Ok(Value::Null)
+            }
+            "window.restore" => {
+                let WindowParams { hwnd } = parse_params(params)?;
+                Self::window_info(hwnd)?.restore_original_status()?;
+                Ok(Value::Null)
+            }
+            _ => Err(RpcError::new(
+                error_code::METHOD_NOT_FOUND,
+                format!("method `{}` not found", method),
+            )),
+        }
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::{client::IpcClient, *};
+
+    /// answers `echo` with its params, `fail` with an error, and a single fake window
+    #[allow(dead_code)]
+    struct EchoHandler;
+
+    impl RequestHandler for EchoHandler {
+        fn handle(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+            match method {
+                "echo" => Ok(params),
+                "window.list" => to_value(vec![WindowSummary {
+                    pid: 1,
+                    hwnd: 2,
+                    title: "title".to_string(),
+                    program_path: "path".to_string(),
+                }]),
+                "window.resize" => {
+                    let ResizeParams { width, height, .. } = parse_params(params)?;
+                    match width > 0 && height > 0 {
+                        true => Ok(Value::Null),
+                        false => Err(RpcError::new(error_code::INVALID_PARAMS, "empty size")),
+                    }
+                }
+                "fail" => Err(RpcError::new(
+                    error_code::WINDOW_NOT_FOUND,
+                    "window not found",
+                )),
+                _ => Err(RpcError::new(error_code::METHOD_NOT_FOUND, method)),
+            }
+        }
+    }
+
+    #[allow(dead_code)]
+    fn test_endpoint(name: &str) -> String {
+        let unique_name = format!("{}_{}_{}", name, std::process::id(), line!());
+        if cfg!(windows) {
+            format!(r"\\.\pipe\WindowModifierTest_{}", unique_name)
+        } else {
+            std::env::temp_dir()
+                .join(format!("window_modifier_test_{}.sock", unique_name))
+                .to_string_lossy()
+                .into_owned()
+        }
+    }
+
+    #[test]
+    fn test_handle_line() {
+        let response = handle_line(&EchoHandler, "{");
+        assert_eq!(response.error.unwrap().code, error_code::PARSE_ERROR);
+        let response = handle_line(&EchoHandler, r#"{"id": 1}"#);
+        assert_eq!(response.error.unwrap().code, error_code::INVALID_REQUEST);
+        let response = handle_line(
+            &EchoHandler,
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "echo", "params": {"version": 2}}"#,
+        );
+        assert_eq!(response.id, 2);
+        assert_eq!(
+            response.error.unwrap().code,
+            error_code::UNSUPPORTED_VERSION
+        );
+        let response = handle_line(
+            &EchoHandler,
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "echo", "params": {"version": 1, "a": 1}}"#,
+        );
+        assert_eq!(response.result.unwrap(), serde_json::json!({"a": 1}));
+    }
+
+    #[test]
+    fn test_rpc_error_from_error() {
+        let err = RpcError::from(Error::ElevatedTarget);
+        assert_eq!(err.code, error_code::ACCESS_DENIED);
+        assert_eq!(err.data, Some("elevated_target".into()));
+        let os_error = windows::core::Error::from(windows::Win32::Foundation::E_FAIL);
+        let err = RpcError::from(Error::from(os_error));
+        assert_eq!(err.code, error_code::OS_ERROR);
+        assert_eq!(err.data, Some(windows::Win32::Foundation::E_FAIL.0.into()));
+    }
+
+    #[test]
+    fn test_client_server() {
+        let endpoint = test_endpoint("client_server");
+        let server = IpcServer::start(&endpoint, EchoHandler).unwrap();
+        assert!(IpcServer::start(&endpoint, EchoHandler).is_err());
+        let mut client = IpcClient::connect(server.endpoint()).unwrap();
+        assert_eq!(client.version().unwrap().versions, SUPPORTED_VERSIONS);
+        let result: Value = client
+            .call("echo", serde_json::json!({"text": "hi"}))
+            .unwrap();
+        assert_eq!(result, serde_json::json!({"text": "hi"}));
+        let err = client.call::<Value>("fail", Value::Null).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<RpcError>().unwrap().code,
+            error_code::WINDOW_NOT_FOUND
+        );
+        assert_eq!(client.list_windows().unwrap()[0].hwnd, 2);
+        assert!(client.resize(2, [800, 600]).is_ok());
+        let err = client.resize(2, [0, 600]).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<RpcError>().unwrap().code,
+            error_code::INVALID_PARAMS
+        );
+        // several clients at the same time
+        let mut other_client = IpcClient::connect(server.endpoint()).unwrap();
+        assert!(other_client.version().is_ok());
+        assert!(client.version().is_ok());
+        // the connections are closed with the server
+        drop(server);
+        assert!(client.version().is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use std::io::{BufRead, BufReader, Write};
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use windows::Win32::System::Console::{ATTACH_PARENT_PROCESS, AttachConsole};
+
+use super::{
+    Geometry, PROTOCOL_VERSION, Request, Response, RpcError, VersionInfo, WindowSummary, transport,
+};
+
+/// A connection to a running instance, one request at a time.
+///
+/// The typed methods are for tools embedding this module, the command line only uses `call`.
+pub struct IpcClient {
+    reader: BufReader<transport::Stream>,
+    writer: transport::Stream,
+    next_id: u64,
+}
+
+impl IpcClient {
+    pub fn connect(endpoint: &str) -> std::io::Result<Self> {
+        let stream = transport::connect(endpoint)?;
+        let writer = stream.try_clone()?;
+        Ok(Self {
+            reader: BufReader::new(stream),
+            writer,
+            next_id: 1,
+        })
+    }
+
+    /// Sends a request of the current protocol version, a `RpcError` is returned as the error if
+    /// the server answers with one.
+    pub fn call<T: DeserializeOwned>(
+        &mut self,
+        method: &str,
+        params: Value,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        let mut params = match params {
+            Value::Null => Value::Object(Default::default()),
+            params => params,
+        };
+        params
+            .as_object_mut()
+            .map(|params| params.insert("version".to_string(), PROTOCOL_VERSION.into()));
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = Request {
+            jsonrpc: "2.0".to_string(),
+            id: id.into(),
+            method: method.to_string(),
+            params,
+        };
+        let mut text = serde_json::to_string(&request)?;
+        text.push('\n');
+        self.writer.write_all(text.as_bytes())?;
+        self.writer.flush()?;
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err("connection closed by the server".into());
+        }
+        let response: Response = serde_json::from_str(&line)?;
+        if response.id != id {
+            return Err(format!("unexpected response id {}", response.id).into());
+        }
+        match (response.result, response.error) {
+            (_, Some(err)) => Err(Box::new(err)),
+            (result, None) => Ok(serde_json::from_value(result.unwrap_or_default())?),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn version(&mut self) -> Result<VersionInfo, Box<dyn std::error::Error>> {
+        self.call("version", Value::Null)
+    }
+
+    #[allow(dead_code)]
+    pub fn list_windows(&mut self) -> Result<Vec<WindowSummary>, Box<dyn std::error::Error>> {
+        self.call("window.list", Value::Null)
+    }
+
+    #[allow(dead_code)]
+    pub fn query_windows(
+        &mut self,
+        text: &str,
+    ) -> Result<Vec<WindowSummary>, Box<dyn std::error::Error>> {
+        self.call("window.query", serde_json::json!({ "text": text }))
+    }
+
+    #[allow(dead_code)]
+    pub fn get_geometry(&mut self, hwnd: u64) -> Result<Geometry, Box<dyn std::error::Error>> {
+        self.call("window.get_geometry", serde_json::json!({ "hwnd": hwnd }))
+    }
+
+    #[allow(dead_code)]
+    pub fn resize(&mut self, hwnd: u64, size: [i32; 2]) -> Result<(), Box<dyn std::error::Error>> {
+        self.call(
+            "window.resize",
+            serde_json::json!({ "hwnd": hwnd, "width": size[0], "height": size[1] }),
+        )
+    }
+
+    #[allow(dead_code)]
+    pub fn move_to(
+        &mut self,
+        hwnd: u64,
+        position: [i32; 2],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.call(
+            "window.move",
+            serde_json::json!({ "hwnd": hwnd, "x": position[0], "y": position[1] }),
+        )
+    }
+
+    #[allow(dead_code)]
+    pub fn set_top_most(
+        &mut self,
+        hwnd: u64,
+        top_most: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.call(
+            "window.set_top_most",
+            serde_json::json!({ "hwnd": hwnd, "top_most": top_most }),
+        )
+    }
+
+    #[allow(dead_code)]
+    pub fn set_fullscreen(
+        &mut self,
+        hwnd: u64,
+        fullscreen: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.call(
+            "window.set_fullscreen",
+            serde_json::json!({ "hwnd": hwnd, "fullscreen": fullscreen }),
+        )
+    }
+
+    #[allow(dead_code)]
+    pub fn restore(&mut self, hwnd: u64) -> Result<(), Box<dyn std::error::Error>> {
+        self.call("window.restore", serde_json::json!({ "hwnd": hwnd }))
+    }
+}
+
+/// `--ipc <method> [params]`, sends one request to the running instance and prints the result.
+pub fn run_cli(method: String, params: Option<String>) -> std::process::ExitCode {
+    // release builds use the windows subsystem and have no console of their own
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+    let result = (|| -> Result<Value, Box<dyn std::error::Error>> {
+        let params = match params {
+            Some(params) => serde_json::from_str(&params)?,
+            None => Value::Null,
+        };
+        IpcClient::connect(&transport::default_endpoint())?.call(&method, params)
+    })();
+    match result {
+        Ok(result) => {
+            println!("{}", serde_json::to_string_pretty(&result).unwrap());
+            std::process::ExitCode::SUCCESS
+        }
+        Err(err) => {
+            match err.downcast_ref::<RpcError>() {
+                Some(RpcError {
+                    data: Some(data), ..
+                }) => eprintln!("{}: {}", err, data),
+                _ => eprintln!("{}", err),
+            }
+            std::process::ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,179 @@
+#[cfg(windows)]
+pub use self::named_pipe::{Listener, Stream, connect, default_endpoint, shutdown};
+#[cfg(unix)]
+pub use self::unix_socket::{Listener, Stream, connect, default_endpoint, shutdown};
+
+#[cfg(windows)]
+mod named_pipe {
+    use std::{
+        fs::{File, OpenOptions},
+        io,
+        os::windows::io::{AsRawHandle, FromRawHandle},
+        time::Duration,
+    };
+
+    use windows::{
+        Win32::{
+            Foundation::{CloseHandle, ERROR_PIPE_BUSY, ERROR_PIPE_CONNECTED, HANDLE},
+            Storage::FileSystem::{FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX},
+            System::{
+                IO::CancelIoEx,
+                Pipes::{
+                    ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE,
+                    PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES,
+                    PIPE_WAIT,
+                },
+            },
+        },
+        core::HSTRING,
+    };
+
+    const BUFFER_SIZE: u32 = 64 * 1024;
+
+    pub type Stream = File;
+
+    pub fn default_endpoint() -> String {
+        r"\\.\pipe\WindowModifier".to_string()
+    }
+
+    /// A pipe instance is always waiting, so clients can connect between two `accept` calls.
+    pub struct Listener {
+        name: HSTRING,
+        pending: HANDLE,
+    }
+
+    // the pending handle is only used by the thread that owns the listener
+    unsafe impl Send for Listener {}
+
+    impl Listener {
+        fn create_instance(name: &HSTRING, first: bool) -> io::Result<HANDLE> {
+            let first_flag = if first {
+                FILE_FLAG_FIRST_PIPE_INSTANCE
+            } else {
+                Default::default()
+            };
+            let handle = unsafe {
+                CreateNamedPipeW(
+                    name,
+                    PIPE_ACCESS_DUPLEX | first_flag,
+                    PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                    PIPE_UNLIMITED_INSTANCES,
+                    BUFFER_SIZE,
+                    BUFFER_SIZE,
+                    0,
+                    None,
+                )
+            };
+            if handle.is_invalid() {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(handle)
+        }
+
+        /// fails if another process already serves `endpoint`
+        pub fn bind(endpoint: &str) -> io::Result<Self> {
+            let name = HSTRING::from(endpoint);
+            let pending = Self::create_instance(&name, true)?;
+            Ok(Self { name, pending })
+        }
+
+        pub fn accept(&mut self) -> io::Result<Stream> {
+            match unsafe { ConnectNamedPipe(self.pending, None) } {
+                Ok(()) => (),
+                Err(err) if err.code() == ERROR_PIPE_CONNECTED.to_hresult() => (),
+                Err(err) => return Err(err.into()),
+            }
+            let connected =
+                std::mem::replace(&mut self.pending, Self::create_instance(&self.name, false)?);
+            Ok(unsafe { File::from_raw_handle(connected.0) })
+        }
+    }
+
+    impl Drop for Listener {
+        fn drop(&mut self) {
+            let _ = unsafe { CloseHandle(self.pending) };
+        }
+    }
+
+    /// Cancels a blocking read or write of another thread on the pipe, or a clone of it.
+    pub fn shutdown(stream: &Stream) {
+        let _ = unsafe { CancelIoEx(HANDLE(stream.as_raw_handle()), None) };
+    }
+
+    pub fn connect(endpoint: &str) -> io::Result<Stream> {
+        const RETRY_COUNT: usize = 20;
+        let mut retry_count = 0;
+        loop {
+            match OpenOptions::new().read(true).write(true).open(endpoint) {
+                Err(err)
+                    if err.raw_os_error() == Some(ERROR_PIPE_BUSY.0 as _)
+                        && retry_count < RETRY_COUNT =>
+                {
+                    retry_count += 1;
+                    std::thread::sleep(Duration::from_millis(50));
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+mod unix_socket {
+    use std::{
+        io,
+        net::Shutdown,
+        os::unix::net::{UnixListener, UnixStream},
+        path::PathBuf,
+    };
+
+    pub type Stream = UnixStream;
+
+    pub fn default_endpoint() -> String {
+        std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir)
+            .join("window_modifier.sock")
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    pub struct Listener {
+        listener: UnixListener,
+        path: PathBuf,
+    }
+
+    impl Listener {
+        /// fails if another process already serves `endpoint`, a stale socket file is replaced
+        pub fn bind(endpoint: &str) -> io::Result<Self> {
+            let path = PathBuf::from(endpoint);
+            if path.exists() {
+                if UnixStream::connect(&path).is_ok() {
+                    return Err(io::Error::from(io::ErrorKind::AddrInUse));
+                }
+                std::fs::remove_file(&path)?;
+            }
+            let listener = UnixListener::bind(&path)?;
+            Ok(Self { listener, path })
+        }
+
+        pub fn accept(&mut self) -> io::Result<Stream> {
+            self.listener.accept().map(|(stream, _)| stream)
+        }
+    }
+
+    impl Drop for Listener {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    /// Ends a blocking read or write of another thread on the socket, or a clone of it.
+    pub fn shutdown(stream: &Stream) {
+        let _ = stream.shutdown(Shutdown::Both);
+    }
+
+    pub fn connect(endpoint: &str) -> io::Result<Stream> {
+        UnixStream::connect(endpoint)
+    }
+}
@@ -0,0 +1,343 @@
+use std::{
+    collections::{BTreeSet, VecDeque},
+    sync::{
+        Mutex, MutexGuard, Once,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use egui::Widget;
+use windows::Win32::Foundation::HWND;
+
+use crate::{
+    error::{Error, Result},
+    i18n, notification,
+    window_modifier::{ModificationKind, WindowInfo, WindowStatus},
+};
+
+/// how often the locked windows are checked
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// a window is not re-applied more often than this
+const MIN_REAPPLY_INTERVAL: Duration = Duration::from_millis(500);
+
+/// re-applying more than `MAX_REAPPLIES` times within `FIGHT_PERIOD` means the app keeps
+/// reverting the lock, it is given up
+const FIGHT_PERIOD: Duration = Duration::from_secs(10);
+const MAX_REAPPLIES: usize = 8;
+
+/// the kinds of modification a lock can keep, borderless fullscreen is geometry and styles
+pub const KINDS: [ModificationKind; 3] = [
+    ModificationKind::Geometry,
+    ModificationKind::TopMost,
+    ModificationKind::Styles,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Decision {
+    Reapply,
+    /// re-applied too recently
+    Wait,
+    GiveUp,
+}
+
+/// Rate limits re-applying a lock and tells when the app keeps reverting it.
+#[derive(Debug, Clone, Default)]
+struct Enforcer {
+    /// when the lock was re-applied within `FIGHT_PERIOD`, oldest first
+    reapplied_at: VecDeque<Instant>,
+}
+
+impl Enforcer {
+    /// Called when the window drifted from the lock.
+    fn decide(&mut self, now: Instant) -> Decision {
+        while self
+            .reapplied_at
+            .front()
+            .is_some_and(|&reapplied_at| now.duration_since(reapplied_at) > FIGHT_PERIOD)
+        {
+            self.reapplied_at.pop_front();
+        }
+        if self
+            .reapplied_at
+            .back()
+            .is_some_and(|&reapplied_at| now.duration_since(reapplied_at) < MIN_REAPPLY_INTERVAL)
+        {
+            return Decision::Wait;
+        }
+        if self.reapplied_at.len() >= MAX_REAPPLIES {
+            return Decision::GiveUp;
+        }
+        self.reapplied_at.push_back(now);
+        Decision::Reapply
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Lock {
+    /// tells a lock from a later one of the same window
+    id: u64,
+    pub pid: u32,
+    /// `HWND` is not `Send`
+    hwnd: usize,
+    pub title: String,
+    program_path: String,
+    /// some of [`KINDS`]
+    pub kinds: BTreeSet<ModificationKind>,
+    /// captured when the window was locked
+    status: WindowStatus,
+    enforcer: Enforcer,
+    /// how often the window was set back
+    pub reapplied: usize,
+}
+
+impl Lock {
+    pub fn window_info(&self) -> WindowInfo {
+        WindowInfo {
+            pid: self.pid,
+            hwnd: HWND(self.hwnd as _),
+            title: self.title.clone(),
+            program_path: self.program_path.clone(),
+        }
+    }
+
+    fn key(&self) -> (u32, usize) {
+        (self.pid, self.hwnd)
+    }
+}
+
+static LOCKS: Mutex<Vec<Lock>> = Mutex::new(Vec::new());
+
+static NEXT_LOCK_ID: AtomicU64 = AtomicU64::new(0);
+
+static WORKER: Once = Once::new();
+
+fn locks_storage() -> MutexGuard<'static, Vec<Lock>> {
+    LOCKS.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Keeps the `kinds` of `window_info` as they are now, replacing its previous lock.
+pub fn lock(window_info: &WindowInfo, kinds: &BTreeSet<ModificationKind>) -> Result<()> {
+    // the geometry of a minimized window is off-screen
+    if window_info.is_minimized() {
+        return Err(Error::Unsupported("lock.minimized"));
+    }
+    let status = window_info.current_status()?;
+    let kinds: BTreeSet<_> = kinds
+        .iter()
+        .copied()
+        .filter(|kind| KINDS.contains(kind))
+        .collect();
+    tracing::info!(
+        hwnd = format!("{:#x}", window_info.hwnd.0 as usize),
+        title = window_info.title,
+        ?kinds,
+        "window locked"
+    );
+    let mut locks = locks_storage();
+    let (pid, hwnd) = window_info.key();
+    locks.retain(|lock| lock.key() != (pid, hwnd));
+    locks.push(Lock {
+        id: NEXT_LOCK_ID.fetch_add(1, Ordering::Relaxed),
+        pid,
+        hwnd,
+        title: window_info.title.clone(),
+        program_path: window_info.program_path.clone(),
+        kinds,
+        status,
+        enforcer: Enforcer::default(),
+        reapplied: 0,
+    });
+    WORKER.call_once(|| {
+        std::thread::Builder::new()
+            .name("lock".to_string())
+            .spawn(|| {
+                loop {
+                    std::thread::sleep(POLL_INTERVAL);
+                    enforce_locks(Instant::now());
+                }
+            })
+            .map_err(|err| tracing::error!(%err, "failed to start the lock thread"))
+            .ok();
+    });
+    Ok(())
+}
+
+pub fn unlock(key: (u32, usize)) {
+    locks_storage().retain(|lock| lock.key() != key);
+}
+
+/// Stops keeping the `kinds` of the window, e.g. when they are restored.
+pub fn release(key: (u32, usize), kinds: &BTreeSet<ModificationKind>) {
+    let released: BTreeSet<_> = match kinds.contains(&ModificationKind::BorderlessFullscreen) {
+        true => kinds
+            .iter()
+            .copied()
+            .chain([ModificationKind::Geometry, ModificationKind::Styles])
+            .collect(),
+        false => kinds.clone(),
+    };
+    let mut locks = locks_storage();
+    locks
+        .iter_mut()
+        .filter(|lock| lock.key() == key)
+        .for_each(|lock| lock.kinds.retain(|kind| !released.contains(kind)));
+    locks.retain(|lock| !lock.kinds.is_empty());
+}
+
+/// Changes what the lock of the window keeps of `kind`, if it keeps it.
+pub fn update_status(
+    key: (u32, usize),
+    kind: ModificationKind,
+    update: impl FnOnce(&mut WindowStatus),
+) {
+    locks_storage()
+        .iter_mut()
+        .find(|lock| lock.key() == key && lock.kinds.contains(&kind))
+        .map(|lock| update(&mut lock.status));
+}
+
+pub fn is_locked(key: (u32, usize)) -> bool {
+    locks_storage().iter().any(|lock| lock.key() == key)
+}
+
+pub fn locks() -> Vec<Lock> {
+    locks_storage().clone()
+}
+
+pub fn count() -> usize {
+    locks_storage().len()
+}
+
+/// Sets the locked windows that drifted back, drops the closed ones and the ones the app keeps
+/// reverting.
+fn enforce_locks(now: Instant) {
+    // a hung window blocks the Win32 calls, the locks are not held meanwhile so the ui can still
+    // lock and unlock windows
+    let mut locks = locks();
+    let mut given_up = Vec::new();
+    let kept: Vec<_> = locks
+        .iter_mut()
+        .map(|lock| {
+            let window_info = lock.window_info();
+            if !window_info.is_valid() {
+                tracing::info!(title = lock.title, "locked window closed");
+                return false;
+            }
+            if window_info.is_minimized() {
+                return true;
+            }
+            let Ok(drifted_kinds) = window_info.drifted_kinds(&lock.status, &lock.kinds) else {
+                return true;
+            };
+            if drifted_kinds.is_empty() {
+                return true;
+            }
+            match lock.enforcer.decide(now) {
+                Decision::Reapply => {
+                    // failures are logged, the next poll tries again
+                    let _ = window_info.reapply_status(&lock.status, &drifted_kinds);
+                    lock.reapplied += 1;
+                    true
+                }
+                Decision::Wait => true,
+                Decision::GiveUp => {
+                    tracing::warn!(
+                        title = lock.title,
+                        reapplied = lock.reapplied,
+                        "the window keeps reverting the lock, giving up"
+                    );
+                    given_up.push(lock.title.clone());
+                    false
+                }
+            }
+        })
+        .collect();
+    // the locks made meanwhile were not enforced yet, the removed ones stay removed
+    locks_storage().retain_mut(|stored| {
+        let Some((lock, &kept)) = locks
+            .iter()
+            .zip(&kept)
+            .find(|(lock, _)| lock.id == stored.id)
+        else {
+            return true;
+        };
+        stored.enforcer = lock.enforcer.clone();
+        stored.reapplied = lock.reapplied;
+        kept
+    });
+    given_up.into_iter().for_each(|title| {
+        notification::warning(i18n::tr_format("lock.gave_up", &[("title", &title)]))
+    });
+}
+
+/// The active locks with a button to unlock each of them.
+pub fn show_locks_ui(ui: &mut egui::Ui) {
+    const FONT_SIZE: f32 = crate::app::App::FONT_SIZE;
+    let text = |text: &str| egui::RichText::new(text).size(FONT_SIZE);
+    let locks = locks();
+    if locks.is_empty() {
+        egui::Label::new(text(i18n::tr("lock.none"))).ui(ui);
+        return;
+    }
+    egui::Grid::new("locks").striped(true).show(ui, |ui| {
+        locks.iter().for_each(|lock| {
+            egui::Label::new(text(&lock.title)).ui(ui);
+            let kinds: Vec<_> = lock.kinds.iter().map(|kind| kind.name()).collect();
+            egui::Label::new(text(&kinds.join(", "))).ui(ui);
+            egui::Label::new(text(&i18n::tr_format(
+                "lock.reapplied",
+                &[("count", &lock.reapplied)],
+            )))
+            .ui(ui);
+            egui::Button::new(text(i18n::tr("lock.unlock")))
+                .ui(ui)
+                .clicked()
+                .then(|| unlock(lock.key()));
+            ui.end_row();
+        });
+    });
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_enforcer_rate_limit() {
+        let mut enforcer = Enforcer::default();
+        let now = Instant::now();
+        assert_eq!(enforcer.decide(now), Decision::Reapply);
+        assert_eq!(
+            enforcer.decide(now + Duration::from_millis(100)),
+            Decision::Wait
+        );
+        assert_eq!(
+            enforcer.decide(now + MIN_REAPPLY_INTERVAL),
+            Decision::Reapply
+        );
+    }
+
+    #[test]
+    fn test_enforcer_gives_up_on_fights() {
+        let mut enforcer = Enforcer::default();
+        let now = Instant::now();
+        // reverted every second, the lock is given up after `MAX_REAPPLIES`
+        let decisions: Vec<_> = (0..MAX_REAPPLIES as u64 + 1)
+            .map(|second| enforcer.decide(now + Duration::from_secs(second)))
+            .collect();
+        assert!(
+            decisions[..MAX_REAPPLIES]
+                .iter()
+                .all(|&decision| decision == Decision::Reapply)
+        );
+        assert_eq!(decisions[MAX_REAPPLIES], Decision::GiveUp);
+
+        // occasional reverts are not a fight
+        let mut enforcer = Enforcer::default();
+        assert!((0..MAX_REAPPLIES as u64 * 3).all(|index| {
+            enforcer.decide(now + FIGHT_PERIOD / 2 * index as u32) == Decision::Reapply
+        }));
+    }
+}
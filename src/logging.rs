@@ -0,0 +1,362 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use egui::Widget;
+use tracing::{
+    Event, Level, Metadata, Subscriber,
+    field::{Field, Visit},
+    span,
+};
+use windows::Win32::System::SystemInformation::GetLocalTime;
+
+use crate::{i18n, utils};
+
+const LOG_FILE_NAME: &str = "window_modifier.log";
+
+/// a log file is rotated once it exceeds this size
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// the current log file and the rotated ones
+const MAX_FILE_COUNT: usize = 5;
+
+/// records kept in memory for the log viewer
+const MAX_RECENT_RECORDS: usize = 2000;
+
+#[derive(Debug, Clone)]
+pub struct LogRecord {
+    /// [`local_time`]
+    pub time: String,
+    pub level: Level,
+    pub target: String,
+    /// the message followed by the fields as `name=value`, line breaks are escaped so every
+    /// record is one line
+    pub message: String,
+}
+
+impl std::fmt::Display for LogRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:5} {}: {}",
+            self.time, self.level, self.target, self.message
+        )
+    }
+}
+
+/// `logs` in the app data dir, created if missing.
+pub fn log_dir() -> PathBuf {
+    let dir = utils::app_data_dir().join("logs");
+    let _ = std::fs::create_dir_all(&dir);
+    dir
+}
+
+/// `window_modifier.log`, then `window_modifier.1.log` for the previous file and so on
+fn rotated_path(dir: &Path, index: usize) -> PathBuf {
+    match index {
+        0 => dir.join(LOG_FILE_NAME),
+        index => dir.join(format!("window_modifier.{}.log", index)),
+    }
+}
+
+/// Appends to a log file, rotating it when it grows too large.
+struct RotatingFile {
+    dir: PathBuf,
+    max_file_size: u64,
+    max_file_count: usize,
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn new(dir: PathBuf, max_file_size: u64, max_file_count: usize) -> Self {
+        let path = rotated_path(&dir, 0);
+        let file = File::options().create(true).append(true).open(path).ok();
+        let size = file
+            .as_ref()
+            .and_then(|file| file.metadata().ok())
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+        Self {
+            dir,
+            max_file_size,
+            max_file_count,
+            file,
+            size,
+        }
+    }
+
+    fn rotate(&mut self) {
+        self.file = None;
+        let _ = std::fs::remove_file(rotated_path(&self.dir, self.max_file_count - 1));
+        (0..self.max_file_count - 1).rev().for_each(|index| {
+            let _ = std::fs::rename(
+                rotated_path(&self.dir, index),
+                rotated_path(&self.dir, index + 1),
+            );
+        });
+        self.file = File::create(rotated_path(&self.dir, 0)).ok();
+        self.size = 0;
+    }
+
+    fn write_line(&mut self, line: &str) {
+        if self.size > 0 && self.size + line.len() as u64 + 1 > self.max_file_size {
+            self.rotate();
+        }
+        self.file.as_mut().map(|file| {
+            writeln!(file, "{}", line)
+                .map(|_| self.size += line.len() as u64 + 1)
+                .unwrap_or_default()
+        });
+    }
+}
+
+/// Collects the message and the fields of an event.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message = value.to_string(),
+            name => self.fields += &format!(" {}={}", name, value),
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        match field.name() {
+            "message" => self.message = format!("{:?}", value),
+            name => self.fields += &format!(" {}={:?}", name, value),
+        }
+    }
+}
+
+impl MessageVisitor {
+    fn into_message(self) -> String {
+        format!("{}{}", self.message, self.fields)
+            .replace('\r', "\\r")
+            .replace('\n', "\\n")
+    }
+}
+
+/// like `2025-01-31 08:00:00.000`
+pub fn local_time() -> String {
+    let time = unsafe { GetLocalTime() };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+        time.wYear,
+        time.wMonth,
+        time.wDay,
+        time.wHour,
+        time.wMinute,
+        time.wSecond,
+        time.wMilliseconds
+    )
+}
+
+static RECENT_RECORDS: Mutex<VecDeque<LogRecord>> = Mutex::new(VecDeque::new());
+
+/// Writes events to the log file and keeps the recent ones for the log viewer, spans are not
+/// recorded.
+struct Logger {
+    max_level: Level,
+    file: Mutex<RotatingFile>,
+    next_span_id: AtomicU64,
+}
+
+impl Subscriber for Logger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        *metadata.level() <= self.max_level
+    }
+
+    fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(self.next_span_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let record = LogRecord {
+            time: local_time(),
+            level: *event.metadata().level(),
+            target: event.metadata().target().to_string(),
+            message: visitor.into_message(),
+        };
+        // the panic hook logs too, panicking again would abort before the crash report is written
+        self.file
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .write_line(&record.to_string());
+        let mut recent_records = RECENT_RECORDS.lock().unwrap_or_else(|err| err.into_inner());
+        if recent_records.len() == MAX_RECENT_RECORDS {
+            recent_records.pop_front();
+        }
+        recent_records.push_back(record);
+    }
+
+    fn enter(&self, _span: &span::Id) {}
+
+    fn exit(&self, _span: &span::Id) {}
+}
+
+/// Installs the logger, debug events are only logged by debug builds.
+pub fn init() {
+    let max_level = if cfg!(debug_assertions) {
+        Level::DEBUG
+    } else {
+        Level::INFO
+    };
+    let logger = Logger {
+        max_level,
+        file: Mutex::new(RotatingFile::new(log_dir(), MAX_FILE_SIZE, MAX_FILE_COUNT)),
+        next_span_id: AtomicU64::new(1),
+    };
+    let _ = tracing::subscriber::set_global_default(logger);
+    tracing::info!(version = env!("CARGO_PKG_VERSION"), "started");
+}
+
+/// the recent records, oldest first
+pub fn recent_records() -> Vec<LogRecord> {
+    recent_records_where(|_| true)
+}
+
+/// the recent records matching `predicate`, oldest first, only those are cloned
+pub fn recent_records_where(predicate: impl Fn(&LogRecord) -> bool) -> Vec<LogRecord> {
+    // also called by the panic hook, which must not panic again
+    RECENT_RECORDS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .iter()
+        .filter(|record| predicate(record))
+        .cloned()
+        .collect()
+}
+
+/// `"0x80070005"`, how Win32 errors are logged
+pub fn error_code(err: &windows::core::Error) -> String {
+    format!("{:#010x}", err.code().0)
+}
+
+/// The log viewer panel, showing the records kept in memory.
+pub struct LogViewer {
+    /// records less severe than this are hidden
+    min_level: Level,
+    filter: String,
+}
+
+impl LogViewer {
+    pub fn new() -> Self {
+        Self {
+            min_level: Level::INFO,
+            filter: String::new(),
+        }
+    }
+
+    pub fn show_ui(&mut self, ui: &mut egui::Ui) {
+        const FONT_SIZE: f32 = crate::app::App::FONT_SIZE;
+        let text = |text: &str| egui::RichText::new(text).size(FONT_SIZE);
+        ui.horizontal(|ui| {
+            egui::Label::new(text(i18n::tr("log.level"))).ui(ui);
+            egui::ComboBox::from_id_salt("log_level")
+                .selected_text(text(self.min_level.as_str()))
+                .show_ui(ui, |ui| {
+                    [Level::ERROR, Level::WARN, Level::INFO, Level::DEBUG]
+                        .into_iter()
+                        .for_each(|level| {
+                            ui.selectable_value(&mut self.min_level, level, text(level.as_str()));
+                        });
+                });
+            egui::Label::new(text(i18n::tr("log.filter"))).ui(ui);
+            egui::TextEdit::singleline(&mut self.filter)
+                .desired_width(200.0)
+                .ui(ui);
+            egui::Button::new(text(i18n::tr("log.open_folder")))
+                .ui(ui)
+                .clicked()
+                .then(|| {
+                    std::process::Command::new("explorer")
+                        .arg(log_dir())
+                        .spawn()
+                });
+        });
+        ui.separator();
+        let filter = self.filter.to_lowercase();
+        let records = recent_records_where(|record| {
+            record.level <= self.min_level
+                && (filter.is_empty() || record.message.to_lowercase().contains(&filter))
+        });
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        egui::ScrollArea::both()
+            .auto_shrink(false)
+            .stick_to_bottom(true)
+            .show_rows(ui, row_height, records.len(), |ui, row_range| {
+                records[row_range].iter().for_each(|record| {
+                    let color = match record.level {
+                        Level::ERROR => ui.visuals().error_fg_color,
+                        Level::WARN => ui.visuals().warn_fg_color,
+                        _ => ui.visuals().text_color(),
+                    };
+                    egui::Label::new(
+                        egui::RichText::new(record.to_string())
+                            .monospace()
+                            .color(color),
+                    )
+                    .extend()
+                    .selectable(true)
+                    .ui(ui);
+                });
+            });
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_rotating_file() {
+        let dir = std::env::temp_dir().join(format!(
+            "window_modifier_test_rotating_file_{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut file = RotatingFile::new(dir.clone(), 10, 3);
+        ["first", "second", "third", "fourth"]
+            .into_iter()
+            .for_each(|line| file.write_line(line));
+        drop(file);
+        let read = |index| std::fs::read_to_string(rotated_path(&dir, index)).ok();
+        assert_eq!(read(0).as_deref(), Some("fourth\n"));
+        assert_eq!(read(1).as_deref(), Some("third\n"));
+        assert_eq!(read(2).as_deref(), Some("second\n"));
+        assert_eq!(read(3), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_message_is_one_line() {
+        let visitor = MessageVisitor {
+            message: "first\nsecond".to_string(),
+            fields: " error=third\r\nfourth".to_string(),
+        };
+        assert_eq!(
+            visitor.into_message(),
+            "first\\nsecond error=third\\r\\nfourth"
+        );
+    }
+}
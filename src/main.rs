@@ -6,26 +6,61 @@ use app::MainApp;
 
 mod app;
 
+mod alignment;
+mod arrangement;
+mod batch;
+mod crash_report;
+mod enumeration;
+mod error;
+mod expression;
+mod font;
+mod hotkey;
+mod i18n;
+mod icon;
+mod ipc;
+mod lock;
+mod logging;
 mod message_dialog;
+mod monitor;
+mod notification;
+mod restore_on_exit;
+mod script;
+mod settings;
+mod tray;
 mod utils;
+mod verification;
+mod window_list;
 mod window_modifier;
+mod window_picker;
+mod window_tree;
 
+/// Runs `f`, a panic is shown in an error dialog offering to open its crash report, then the
+/// modified windows are restored as configured in the settings.
 fn graceful_run<R>(
     f: impl FnOnce() -> R + std::panic::UnwindSafe,
 ) -> Result<R, Box<dyn Any + Send + 'static>> {
-    std::panic::catch_unwind(f).map_err(|err| {
-        let message = if let Some(str) = err.downcast_ref::<&str>() {
-            str.to_string()
-        } else if let Some(string) = err.downcast_ref::<String>() {
-            string.clone()
-        } else {
-            format!("{:?}", err)
-        };
-        message_dialog::error(&message).show();
-        err
+    std::panic::catch_unwind(f).inspect_err(|err| {
+        // the crash dialog waits for the user, the windows shouldn't stay modified meanwhile
+        restore_on_exit::restore_after_panic();
+        crash_report::show_crash_dialog(err.as_ref());
     })
 }
 
-fn main() {
+fn main() -> std::process::ExitCode {
+    logging::init();
+    crash_report::install_panic_hook();
+    restore_on_exit::install_console_ctrl_handler();
+    let mut args = std::env::args_os().skip(1);
+    match (args.next(), args.next()) {
+        (Some(flag), Some(path)) if flag == "--script" => return script::run_cli(path.into()),
+        (Some(flag), Some(method)) if flag == "--ipc" => {
+            let params = args
+                .next()
+                .map(|params| params.to_string_lossy().into_owned());
+            return ipc::client::run_cli(method.to_string_lossy().into_owned(), params);
+        }
+        _ => (),
+    }
     let _ = graceful_run(|| MainApp::new().run());
+    std::process::ExitCode::SUCCESS
 }
@@ -0,0 +1,285 @@
+use windows::{
+    Win32::{
+        Foundation::{HWND, LPARAM, RECT},
+        Graphics::Gdi::{
+            EnumDisplayMonitors, GetMonitorInfoW, HDC, HMONITOR, MONITOR_DEFAULTTONEAREST,
+            MONITORINFO, MONITORINFOEXW, MonitorFromWindow,
+        },
+        UI::{
+            HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI},
+            WindowsAndMessaging::MONITORINFOF_PRIMARY,
+        },
+    },
+    core::BOOL,
+};
+
+/// `USER_DEFAULT_SCREEN_DPI`, the DPI of 100% scaling
+const DEFAULT_DPI: u32 = 96;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorInfo {
+    /// device name like `\\.\DISPLAY1`
+    pub name: String,
+    /// `[x, y, width, height]` in virtual screen coordinates
+    pub rect: [i32; 4],
+    /// like `rect`, without the taskbar and docked app bars
+    pub work_area: [i32; 4],
+    pub primary: bool,
+    /// effective DPI, 96 is 100% scaling
+    pub dpi: u32,
+}
+
+impl MonitorInfo {
+    fn from_hmonitor(hmonitor: HMONITOR) -> Option<Self> {
+        let mut monitor_info = MONITORINFOEXW {
+            monitorInfo: MONITORINFO {
+                cbSize: size_of::<MONITORINFOEXW>() as _,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        if !unsafe { GetMonitorInfoW(hmonitor, &mut monitor_info as *mut MONITORINFOEXW as _) }
+            .as_bool()
+        {
+            return None;
+        }
+        let to_array = |rect: RECT| {
+            [
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+            ]
+        };
+        let name_len = monitor_info
+            .szDevice
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(monitor_info.szDevice.len());
+        let (mut dpi, mut dpi_y) = (0, 0);
+        if unsafe { GetDpiForMonitor(hmonitor, MDT_EFFECTIVE_DPI, &mut dpi, &mut dpi_y) }.is_err() {
+            dpi = DEFAULT_DPI;
+        }
+        Some(Self {
+            name: String::from_utf16_lossy(&monitor_info.szDevice[..name_len]),
+            rect: to_array(monitor_info.monitorInfo.rcMonitor),
+            work_area: to_array(monitor_info.monitorInfo.rcWork),
+            primary: monitor_info.monitorInfo.dwFlags & MONITORINFOF_PRIMARY != 0,
+            dpi,
+        })
+    }
+
+    /// the scale factor of the monitor, 1.0 is 100%
+    pub fn scale_factor(&self) -> f32 {
+        self.dpi as f32 / DEFAULT_DPI as f32
+    }
+}
+
+/// all monitors, the primary monitor first
+pub fn enumerate_monitors() -> Vec<MonitorInfo> {
+    unsafe extern "system" fn enum_monitor_proc(
+        hmonitor: HMONITOR,
+        _hdc: HDC,
+        _rect: *mut RECT,
+        lparam: LPARAM,
+    ) -> BOOL {
+        let monitor_list = unsafe { &mut *(lparam.0 as *mut Vec<MonitorInfo>) };
+        MonitorInfo::from_hmonitor(hmonitor).map(|monitor_info| monitor_list.push(monitor_info));
+        BOOL(1)
+    }
+    let mut monitor_list: Vec<MonitorInfo> = Vec::new();
+    let _ = unsafe {
+        EnumDisplayMonitors(
+            None,
+            None,
+            Some(enum_monitor_proc),
+            LPARAM(&mut monitor_list as *mut Vec<MonitorInfo> as _),
+        )
+    };
+    monitor_list.sort_by_key(|monitor_info| !monitor_info.primary);
+    monitor_list
+}
+
+/// the monitor that has the largest intersection with the window
+pub fn monitor_of_window(hwnd: HWND) -> Option<MonitorInfo> {
+    let hmonitor = unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) };
+    MonitorInfo::from_hmonitor(hmonitor)
+}
+
+/// a window showing less of itself on every monitor is off-screen
+const MIN_VISIBLE_FRACTION: f32 = 0.1;
+
+/// the height of a title bar in points, the part of a window kept in a work area
+const TITLE_BAR_HEIGHT: i32 = 32;
+
+/// the width of the title bar kept in a work area in points, enough to grab it
+const MIN_GRAB_WIDTH: i32 = 100;
+
+/// `[x, y, width, height]` of the overlap of two rects, `None` if they don't overlap
+fn intersection(a: [i32; 4], b: [i32; 4]) -> Option<[i32; 4]> {
+    let left = a[0].max(b[0]);
+    let top = a[1].max(b[1]);
+    let right = (a[0] + a[2]).min(b[0] + b[2]);
+    let bottom = (a[1] + a[3]).min(b[1] + b[3]);
+    (left < right && top < bottom).then_some([left, top, right - left, bottom - top])
+}
+
+fn area(rect: [i32; 4]) -> i64 {
+    rect[2] as i64 * rect[3] as i64
+}
+
+/// The share of `rect` shown by the monitor showing most of it, 0.0 to 1.0.
+pub fn visible_fraction(rect: [i32; 4], monitors: &[MonitorInfo]) -> f32 {
+    if area(rect) <= 0 {
+        return 0.0;
+    }
+    monitors
+        .iter()
+        .filter_map(|monitor_info| intersection(rect, monitor_info.rect))
+        .map(area)
+        .max()
+        .map(|visible_area| visible_area as f32 / area(rect) as f32)
+        .unwrap_or(0.0)
+}
+
+/// The monitor whose work area overlaps `rect` the most, or else the one closest to it.
+fn nearest_monitor(rect: [i32; 4], monitors: &[MonitorInfo]) -> Option<&MonitorInfo> {
+    let center = |[x, y, width, height]: [i32; 4]| {
+        [x as i64 + width as i64 / 2, y as i64 + height as i64 / 2]
+    };
+    let [center_x, center_y] = center(rect);
+    monitors.iter().min_by_key(|monitor_info| {
+        let overlap = intersection(rect, monitor_info.work_area)
+            .map(area)
+            .unwrap_or(0);
+        let [x, y] = center(monitor_info.work_area);
+        (-overlap, (x - center_x).pow(2) + (y - center_y).pow(2))
+    })
+}
+
+/// Where to move a window at `rect: [x, y, width, height]` that is off-screen, into the nearest
+/// work area, `None` if enough of it is visible.
+pub fn rescue_position(rect: [i32; 4], monitors: &[MonitorInfo]) -> Option<[i32; 2]> {
+    if visible_fraction(rect, monitors) >= MIN_VISIBLE_FRACTION {
+        return None;
+    }
+    let [x, y, width, height] = rect;
+    let [area_x, area_y, area_width, area_height] = nearest_monitor(rect, monitors)?.work_area;
+    // a window larger than the work area is aligned to its top left corner
+    Some([
+        x.min(area_x + area_width - width).max(area_x),
+        y.min(area_y + area_height - height).max(area_y),
+    ])
+}
+
+/// `position` of a window of `size`, moved if needed so that enough of its title bar to grab it
+/// stays in a work area.
+pub fn keep_title_bar_reachable(
+    position: [i32; 2],
+    size: [i32; 2],
+    monitors: &[MonitorInfo],
+) -> [i32; 2] {
+    let [x, y] = position;
+    let [width, _] = size;
+    let scaled = |points: i32, monitor_info: &MonitorInfo| {
+        (points as f32 * monitor_info.scale_factor()).round() as i32
+    };
+    let reachable = |monitor_info: &MonitorInfo| {
+        let title_bar_height = scaled(TITLE_BAR_HEIGHT, monitor_info);
+        let grab_width = scaled(MIN_GRAB_WIDTH, monitor_info).min(width);
+        let [_, area_y, _, area_height] = monitor_info.work_area;
+        y >= area_y
+            && y + title_bar_height <= area_y + area_height
+            && intersection([x, y, width, title_bar_height], monitor_info.work_area)
+                .is_some_and(|[_, _, visible_width, _]| visible_width >= grab_width)
+    };
+    if monitors.iter().any(reachable) {
+        return position;
+    }
+    let Some(monitor_info) = nearest_monitor([x, y, width, TITLE_BAR_HEIGHT], monitors) else {
+        return position;
+    };
+    let title_bar_height = scaled(TITLE_BAR_HEIGHT, monitor_info);
+    let grab_width = scaled(MIN_GRAB_WIDTH, monitor_info).min(width);
+    let [area_x, area_y, area_width, area_height] = monitor_info.work_area;
+    [
+        x.min(area_x + area_width - grab_width)
+            .max(area_x - width + grab_width),
+        y.min(area_y + area_height - title_bar_height).max(area_y),
+    ]
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    /// a 1920x1080 primary monitor at 100% and a 2560x1440 one at 150% to its right, both with
+    /// a 40 pixel taskbar at the bottom
+    #[allow(dead_code)]
+    fn monitors() -> Vec<MonitorInfo> {
+        let monitor = |rect: [i32; 4], primary, dpi| MonitorInfo {
+            name: String::new(),
+            rect,
+            work_area: [rect[0], rect[1], rect[2], rect[3] - 40],
+            primary,
+            dpi,
+        };
+        vec![
+            monitor([0, 0, 1920, 1080], true, 96),
+            monitor([1920, 0, 2560, 1440], false, 144),
+        ]
+    }
+
+    #[test]
+    fn test_visible_fraction() {
+        let monitors = monitors();
+        assert_eq!(visible_fraction([100, 100, 800, 600], &monitors), 1.0);
+        assert_eq!(visible_fraction([1520, 0, 800, 600], &monitors), 0.5);
+        assert_eq!(visible_fraction([-5000, 0, 800, 600], &monitors), 0.0);
+    }
+
+    #[test]
+    fn test_rescue_position() {
+        let monitors = monitors();
+        assert_eq!(rescue_position([100, 100, 800, 600], &monitors), None);
+        // left of the primary monitor, e.g. after unplugging a monitor there
+        assert_eq!(
+            rescue_position([-2000, 300, 800, 600], &monitors),
+            Some([0, 300])
+        );
+        // below the second monitor
+        assert_eq!(
+            rescue_position([3000, 5000, 800, 600], &monitors),
+            Some([3000, 800])
+        );
+        // larger than the work area
+        assert_eq!(
+            rescue_position([-8000, -8000, 4000, 3000], &monitors),
+            Some([0, 0])
+        );
+    }
+
+    #[test]
+    fn test_keep_title_bar_reachable() {
+        let monitors = monitors();
+        // mostly off-screen but the title bar can be grabbed
+        assert_eq!(
+            keep_title_bar_reachable([-700, 100], [800, 600], &monitors),
+            [-700, 100]
+        );
+        assert_eq!(
+            keep_title_bar_reachable([-750, 100], [800, 600], &monitors),
+            [-700, 100]
+        );
+        // above the screen
+        assert_eq!(
+            keep_title_bar_reachable([100, -300], [800, 600], &monitors),
+            [100, 0]
+        );
+        // below the taskbar of the 150% monitor, its title bar is 48 pixels high
+        assert_eq!(
+            keep_title_bar_reachable([2000, 1400], [800, 600], &monitors),
+            [2000, 1352]
+        );
+    }
+}
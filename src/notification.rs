@@ -0,0 +1,411 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use egui::Widget;
+
+use crate::{error::Error, i18n, logging};
+
+/// older notifications are dropped from the history
+const MAX_HISTORY: usize = 200;
+
+/// toasts shown at the same time, the oldest are dismissed first
+const MAX_TOASTS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Info => i18n::tr("dialog.info"),
+            Self::Warning => i18n::tr("dialog.warning"),
+            Self::Error => i18n::tr("dialog.error"),
+        }
+    }
+
+    /// how long the toast stays if it is not hovered
+    fn duration(self) -> Duration {
+        match self {
+            Self::Info => Duration::from_secs(4),
+            Self::Warning => Duration::from_secs(8),
+            Self::Error => Duration::from_secs(15),
+        }
+    }
+
+    fn color(self, visuals: &egui::Visuals) -> egui::Color32 {
+        match self {
+            Self::Info => visuals.text_color(),
+            Self::Warning => visuals.warn_fg_color,
+            Self::Error => visuals.error_fg_color,
+        }
+    }
+}
+
+/// A button on the notification, e.g. opening a folder it tells about.
+#[derive(Clone)]
+pub struct Action {
+    pub label: String,
+    pub run: Arc<dyn Fn() + Send + Sync>,
+}
+
+impl std::fmt::Debug for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Action")
+            .field("label", &self.label)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    id: u64,
+    pub severity: Severity,
+    /// [`logging::local_time`]
+    pub time: String,
+    pub message: String,
+    /// e.g. the code of an OS error, copied along with the message
+    pub details: Option<String>,
+    pub action: Option<Action>,
+}
+
+impl Notification {
+    /// the message and the details, as copied to the clipboard
+    pub fn to_text(&self) -> String {
+        match &self.details {
+            Some(details) => format!(
+                "{} {}: {}\n{}",
+                self.time,
+                self.severity.name(),
+                self.message,
+                details
+            ),
+            None => format!("{} {}: {}", self.time, self.severity.name(), self.message),
+        }
+    }
+}
+
+/// The toasts on screen and the history of every notification.
+#[derive(Debug, Default)]
+pub struct NotificationCenter {
+    next_id: u64,
+    /// newest last
+    history: VecDeque<Notification>,
+    /// ids of the notifications shown as toasts and when they expire, newest last
+    toasts: Vec<(u64, Instant)>,
+    /// notifications pushed since the history was last viewed
+    unread: usize,
+}
+
+impl NotificationCenter {
+    pub fn push(
+        &mut self,
+        severity: Severity,
+        message: String,
+        details: Option<String>,
+        action: Option<Action>,
+        now: Instant,
+    ) {
+        let id = self.next_id;
+        self.next_id += 1;
+        if self.history.len() == MAX_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(Notification {
+            id,
+            severity,
+            time: logging::local_time(),
+            message,
+            details,
+            action,
+        });
+        if self.toasts.len() == MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+        self.toasts.push((id, now + severity.duration()));
+        self.unread += 1;
+    }
+
+    /// Dismisses the toasts that expired by `now`.
+    pub fn expire(&mut self, now: Instant) {
+        self.toasts.retain(|&(_, expires_at)| expires_at > now);
+    }
+
+    pub fn dismiss(&mut self, id: u64) {
+        self.toasts.retain(|&(toast_id, _)| toast_id != id);
+    }
+
+    /// Keeps a hovered toast on screen for its whole duration again.
+    fn keep(&mut self, id: u64, now: Instant) {
+        let severity = self
+            .history
+            .iter()
+            .find(|notification| notification.id == id)
+            .map(|notification| notification.severity);
+        self.toasts
+            .iter_mut()
+            .filter(|(toast_id, _)| *toast_id == id)
+            .zip(severity)
+            .for_each(|((_, expires_at), severity)| *expires_at = now + severity.duration());
+    }
+
+    /// the notifications shown as toasts, oldest first
+    pub fn toasts(&self) -> Vec<Notification> {
+        self.toasts
+            .iter()
+            .filter_map(|&(id, _)| {
+                self.history
+                    .iter()
+                    .find(|notification| notification.id == id)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// when the next toast expires
+    fn next_expiry(&self) -> Option<Instant> {
+        self.toasts.iter().map(|&(_, expires_at)| expires_at).min()
+    }
+
+    pub fn unread(&self) -> usize {
+        self.unread
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+        self.toasts.clear();
+        self.unread = 0;
+    }
+}
+
+static NOTIFICATION_CENTER: Mutex<NotificationCenter> = Mutex::new(NotificationCenter {
+    next_id: 0,
+    history: VecDeque::new(),
+    toasts: Vec::new(),
+    unread: 0,
+});
+
+/// set by the first `show_toasts`, so notifications pushed from other threads repaint the ui
+static CONTEXT: OnceLock<egui::Context> = OnceLock::new();
+
+fn notification_center() -> std::sync::MutexGuard<'static, NotificationCenter> {
+    NOTIFICATION_CENTER
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+}
+
+/// notifications pushed since the history was last viewed
+pub fn unread() -> usize {
+    notification_center().unread()
+}
+
+/// Shows a toast and adds it to the history, from any thread.
+pub fn push(severity: Severity, message: impl Into<String>, details: Option<String>) {
+    push_with_action(severity, message, details, None);
+}
+
+/// [`push`] with a button that runs `action`, the toast is dismissed when it's clicked
+pub fn push_with_action(
+    severity: Severity,
+    message: impl Into<String>,
+    details: Option<String>,
+    action: Option<Action>,
+) {
+    notification_center().push(severity, message.into(), details, action, Instant::now());
+    CONTEXT.get().map(|ctx| ctx.request_repaint());
+}
+
+pub fn info(message: impl Into<String>) {
+    push(Severity::Info, message, None);
+}
+
+pub fn warning(message: impl Into<String>) {
+    push(Severity::Warning, message, None);
+}
+
+/// an error with the message of `err`, its code in the details
+pub fn error(err: &Error) {
+    push(Severity::Error, err.message(), Some(err.details()));
+}
+
+/// Draws the toasts in the bottom right corner, called every frame.
+pub fn show_toasts(ctx: &egui::Context) {
+    const FONT_SIZE: f32 = crate::app::App::FONT_SIZE;
+    let text = |text: &str| egui::RichText::new(text).size(FONT_SIZE);
+    CONTEXT.get_or_init(|| ctx.clone());
+    let now = Instant::now();
+    let toasts = {
+        let mut notification_center = notification_center();
+        notification_center.expire(now);
+        notification_center
+            .next_expiry()
+            .map(|expires_at| ctx.request_repaint_after(expires_at - now));
+        notification_center.toasts()
+    };
+    if toasts.is_empty() {
+        return;
+    }
+    egui::Area::new(egui::Id::new("toasts"))
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-12.0, -12.0])
+        .order(egui::Order::Foreground)
+        .show(ctx, |ui| {
+            ui.set_max_width(420.0);
+            toasts.iter().for_each(|notification| {
+                let color = notification.severity.color(ui.visuals());
+                let response = egui::Frame::popup(ui.style())
+                    .stroke(egui::Stroke::new(1.0, color))
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            egui::Label::new(
+                                text(notification.severity.name()).color(color).strong(),
+                            )
+                            .ui(ui);
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    egui::Button::new(text("×"))
+                                        .frame(false)
+                                        .ui(ui)
+                                        .on_hover_text(i18n::tr("notification.dismiss"))
+                                        .clicked()
+                                        .then(|| notification_center().dismiss(notification.id));
+                                    egui::Button::new(text(i18n::tr("notification.copy")))
+                                        .small()
+                                        .ui(ui)
+                                        .clicked()
+                                        .then(|| ctx.copy_text(notification.to_text()));
+                                    notification.action.as_ref().map(|action| {
+                                        egui::Button::new(text(&action.label))
+                                            .small()
+                                            .ui(ui)
+                                            .clicked()
+                                            .then(|| {
+                                                (action.run)();
+                                                notification_center().dismiss(notification.id);
+                                            })
+                                    });
+                                },
+                            );
+                        });
+                        egui::Label::new(text(&notification.message)).wrap().ui(ui);
+                    })
+                    .response;
+                response
+                    .contains_pointer()
+                    .then(|| notification_center().keep(notification.id, now));
+                ui.add_space(6.0);
+            });
+        });
+}
+
+/// The history panel, newest first.
+pub fn show_history_ui(ui: &mut egui::Ui) {
+    const FONT_SIZE: f32 = crate::app::App::FONT_SIZE;
+    let text = |text: &str| egui::RichText::new(text).size(FONT_SIZE);
+    let history: Vec<_> = {
+        let mut notification_center = notification_center();
+        notification_center.unread = 0;
+        notification_center.history.iter().rev().cloned().collect()
+    };
+    ui.horizontal(|ui| {
+        egui::Button::new(text(i18n::tr("notification.copy_all")))
+            .ui(ui)
+            .clicked()
+            .then(|| {
+                let text = history
+                    .iter()
+                    .map(Notification::to_text)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                ui.ctx().copy_text(text);
+            });
+        egui::Button::new(text(i18n::tr("notification.clear")))
+            .ui(ui)
+            .clicked()
+            .then(|| notification_center().clear_history());
+    });
+    ui.separator();
+    if history.is_empty() {
+        egui::Label::new(text(i18n::tr("notification.empty"))).ui(ui);
+        return;
+    }
+    egui::ScrollArea::vertical()
+        .auto_shrink(false)
+        .show(ui, |ui| {
+            history.iter().for_each(|notification| {
+                let color = notification.severity.color(ui.visuals());
+                ui.horizontal(|ui| {
+                    egui::Label::new(text(&notification.time).weak()).ui(ui);
+                    egui::Label::new(text(notification.severity.name()).color(color)).ui(ui);
+                    egui::Button::new(text(i18n::tr("notification.copy")))
+                        .small()
+                        .ui(ui)
+                        .clicked()
+                        .then(|| ui.ctx().copy_text(notification.to_text()));
+                    notification.action.as_ref().map(|action| {
+                        egui::Button::new(text(&action.label))
+                            .small()
+                            .ui(ui)
+                            .clicked()
+                            .then(|| (action.run)())
+                    });
+                });
+                egui::Label::new(text(&notification.message))
+                    .selectable(true)
+                    .wrap()
+                    .ui(ui);
+                notification.details.as_ref().map(|details| {
+                    egui::Label::new(egui::RichText::new(details).monospace().weak())
+                        .selectable(true)
+                        .ui(ui)
+                });
+                ui.separator();
+            });
+        });
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_toasts_expire_and_history_stays() {
+        let mut notification_center = NotificationCenter::default();
+        let now = Instant::now();
+        notification_center.push(Severity::Info, "info".to_string(), None, None, now);
+        notification_center.push(
+            Severity::Error,
+            "error".to_string(),
+            Some("0x80070005".to_string()),
+            None,
+            now,
+        );
+        assert_eq!(notification_center.toasts().len(), 2);
+        notification_center.expire(now + Duration::from_secs(5));
+        let toasts = notification_center.toasts();
+        assert_eq!(toasts.len(), 1);
+        assert_eq!(toasts[0].message, "error");
+        notification_center.dismiss(toasts[0].id);
+        assert!(notification_center.toasts().is_empty());
+        assert_eq!(notification_center.history.len(), 2);
+        assert_eq!(notification_center.unread(), 2);
+    }
+
+    #[test]
+    fn test_max_toasts() {
+        let mut notification_center = NotificationCenter::default();
+        let now = Instant::now();
+        (0..MAX_TOASTS + 2).for_each(|index| {
+            notification_center.push(Severity::Warning, index.to_string(), None, None, now)
+        });
+        let toasts = notification_center.toasts();
+        assert_eq!(toasts.len(), MAX_TOASTS);
+        assert_eq!(toasts[0].message, "2");
+    }
+}
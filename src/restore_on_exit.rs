@@ -0,0 +1,109 @@
+use std::collections::BTreeSet;
+
+use windows::Win32::{
+    Foundation::STATUS_CONTROL_C_EXIT,
+    System::Console::{CTRL_BREAK_EVENT, CTRL_C_EVENT, CTRL_CLOSE_EVENT, SetConsoleCtrlHandler},
+};
+
+use crate::{
+    i18n, message_dialog,
+    settings::{self, ExitAction},
+    tray,
+    window_modifier::{self, ModificationKind},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    /// closing the main window or quitting from the tray, asked in the app
+    Quit,
+    /// a panic caught by `graceful_run`
+    Panic,
+    /// Ctrl+C, Ctrl+Break or closing the console
+    Interrupt,
+}
+
+/// The modified windows with kinds set to `Ask`, as `title (kinds)`.
+fn asked_windows(asked_kinds: &BTreeSet<ModificationKind>) -> Vec<String> {
+    window_modifier::modified_windows()
+        .iter()
+        .filter_map(|modified_window| {
+            let names: Vec<_> = modified_window
+                .kinds
+                .intersection(asked_kinds)
+                .map(|kind| kind.name())
+                .collect();
+            (!names.is_empty()).then(|| format!("{} ({})", modified_window.title, names.join(", ")))
+        })
+        .collect()
+}
+
+fn kinds_with(exit_action: ExitAction) -> BTreeSet<ModificationKind> {
+    let restore_on_exit = settings::settings().restore_on_exit;
+    ModificationKind::ALL
+        .into_iter()
+        .filter(|&kind| restore_on_exit.action(kind) == exit_action)
+        .collect()
+}
+
+/// The question whether to restore the modifications whose kinds are set to `Ask`, `None` if no
+/// window has any of them.
+pub fn question() -> Option<String> {
+    let asked_windows = asked_windows(&kinds_with(ExitAction::Ask));
+    (!asked_windows.is_empty()).then(|| {
+        i18n::tr_format(
+            "exit.restore_question",
+            &[("windows", &asked_windows.join("\n"))],
+        )
+    })
+}
+
+/// Restores the modified windows as configured by `Settings::restore_on_exit`, the kinds set to
+/// `Ask` if `restore_asked`.
+pub fn restore_on_exit(reason: ExitReason, restore_asked: bool) {
+    let mut kinds = kinds_with(ExitAction::Restore);
+    if restore_asked {
+        kinds.extend(kinds_with(ExitAction::Ask));
+    }
+    if kinds.is_empty() || window_modifier::modified_windows().is_empty() {
+        return;
+    }
+    tracing::info!(?reason, ?kinds, "restoring the modified windows on exit");
+    let Some(message) =
+        tray::restore_errors_message(window_modifier::restore_modified_windows(&kinds))
+    else {
+        return;
+    };
+    tracing::warn!(message, "restoring on exit failed");
+    // the app is exiting, a toast would not be seen; an interrupted process is killed soon
+    if reason != ExitReason::Interrupt {
+        message_dialog::error(&message).show();
+    }
+}
+
+/// Asks with a native dialog, the user interface is gone after a panic.
+pub fn restore_after_panic() {
+    let restore_asked = question().is_some_and(|question| {
+        message_dialog::question(&question)
+            .set_buttons(rfd::MessageButtons::YesNo)
+            .show()
+            == rfd::MessageDialogResult::Yes
+    });
+    restore_on_exit(ExitReason::Panic, restore_asked);
+}
+
+/// Restores the windows on Ctrl+C, Ctrl+Break and closing the console before exiting.
+pub fn install_console_ctrl_handler() {
+    unsafe extern "system" fn handler(ctrl_type: u32) -> windows::core::BOOL {
+        match ctrl_type {
+            CTRL_C_EVENT | CTRL_BREAK_EVENT | CTRL_CLOSE_EVENT => {
+                // nobody can answer before Windows kills the process, the windows are restored
+                restore_on_exit(ExitReason::Interrupt, true);
+                std::process::exit(STATUS_CONTROL_C_EXIT.0);
+            }
+            // the next handler, the default one exits
+            _ => false.into(),
+        }
+    }
+    // fails only without a console, then there is nothing to interrupt
+    let _ = unsafe { SetConsoleCtrlHandler(Some(handler), true) };
+}
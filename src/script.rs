@@ -0,0 +1,640 @@
+use std::{
+    fmt::Display,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, Sender, channel},
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use egui::Widget;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, Position};
+use windows::Win32::{
+    System::Console::{ATTACH_PARENT_PROCESS, AttachConsole},
+    UI::WindowsAndMessaging::GetForegroundWindow,
+};
+
+use crate::{
+    error::Error,
+    i18n,
+    monitor::{self, MonitorInfo},
+    window_modifier::{WindowInfo, find_windows, window_class_name},
+};
+
+/// Script operations between two checks of the cancellation flag are counted by Rhai, the limit
+/// stops runaway loops; sleeping does not count.
+const MAX_OPERATIONS: u64 = 100_000_000;
+
+/// sleeps are split into slices so that a cancelled script stops quickly
+const SLEEP_SLICE: Duration = Duration::from_millis(50);
+
+/// shown in the script panel
+const API_REFERENCE: &str = "\
+windows() -> [WindowInfo]
+find_windows(text) -> [WindowInfo]   // title or program path contains text, case-insensitive
+foreground_window() -> WindowInfo | ()
+wait_for_window(text, timeout_ms) -> WindowInfo | ()
+monitors() -> [#{name, x, y, width, height, work_x, work_y, work_width, work_height, primary, dpi, scale}]
+primary_monitor() -> #{...}
+sleep(ms)
+
+WindowInfo:
+  .pid  .hwnd  .title  .program_path  .class_name
+  .is_valid()  .size()  .inner_size()  .position()  .monitor()
+  .is_top_most()  .is_borderless_fullscreen()  .is_child()
+  .resize(w, h)  .resize_inner(w, h)  .move_to(x, y)
+  .set_top_most(bool)  .set_resizable(bool)  .set_maximizable(bool)  .set_minimizable(bool)
+  .set_borderless_fullscreen()  .restore_from_borderless_fullscreen()  .restore()
+
+Failed WindowInfo methods throw #{code, message}, e.g.
+  try { window.resize(800, 600) } catch (err) { if err.code == \"elevated_target\" { ... } }
+codes: window_gone elevated_target access_denied refused invalid_monitor unsupported
+       invalid_argument os_error";
+
+const EXAMPLE_SCRIPT: &str = r#"let monitor = primary_monitor();
+for window in find_windows("notepad") {
+    if monitor.width >= 3840 {
+        window.resize(2560, 1440);
+        window.move_to(monitor.x + (monitor.width - 2560) / 2, monitor.y + (monitor.height - 1440) / 2);
+    } else {
+        window.set_borderless_fullscreen();
+    }
+    print(`${window.title}: ${window.size()}`);
+}
+"#;
+
+#[derive(Debug, Clone)]
+pub struct ScriptError {
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl ScriptError {
+    fn new(mut err: EvalAltResult) -> Self {
+        // the position is reported separately, the message of inner errors keeps theirs
+        let position = err.take_position();
+        Self {
+            line: position.line(),
+            column: position.position(),
+            message: window_error_message(&err).unwrap_or_else(|| err.to_string()),
+        }
+    }
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(
+                f,
+                "{}",
+                i18n::tr_format(
+                    "script.error_at",
+                    &[
+                        ("line", &line),
+                        ("column", &column),
+                        ("message", &self.message)
+                    ]
+                )
+            ),
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+fn runtime_error(message: impl Display) -> Box<EvalAltResult> {
+    message.to_string().into()
+}
+
+/// `#{code, message}`, so scripts can catch specific failures by their [`Error::code`]
+fn window_error(err: Error) -> Box<EvalAltResult> {
+    let mut map = Map::new();
+    map.insert("code".into(), err.code().into());
+    map.insert("message".into(), err.message().into());
+    EvalAltResult::ErrorRuntime(map.into(), Position::NONE).into()
+}
+
+/// `message (code)` of an uncaught error thrown by [`window_error`]
+fn window_error_message(err: &EvalAltResult) -> Option<String> {
+    let EvalAltResult::ErrorRuntime(value, _) = err else {
+        return None;
+    };
+    let map = value.clone().try_cast::<Map>()?;
+    let field = |name: &str| map.get(name).map(Dynamic::to_string);
+    Some(format!("{} ({})", field("message")?, field("code")?))
+}
+
+fn sleep(cancelled: &AtomicBool, duration: Duration) -> Result<(), Box<EvalAltResult>> {
+    let deadline = Instant::now() + duration;
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(runtime_error(i18n::tr("script.cancelled")));
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(());
+        }
+        std::thread::sleep(SLEEP_SLICE.min(deadline - now));
+    }
+}
+
+fn to_array(window_info_list: Vec<WindowInfo>) -> Array {
+    window_info_list.into_iter().map(Dynamic::from).collect()
+}
+
+fn pair_to_array([a, b]: [i32; 2]) -> Array {
+    vec![Dynamic::from(a as i64), Dynamic::from(b as i64)]
+}
+
+fn monitor_to_map(monitor_info: MonitorInfo) -> Map {
+    let [x, y, width, height] = monitor_info.rect;
+    let [work_x, work_y, work_width, work_height] = monitor_info.work_area;
+    let scale = monitor_info.scale_factor() as f64;
+    let mut map = Map::new();
+    map.insert("name".into(), monitor_info.name.into());
+    [
+        ("x", x),
+        ("y", y),
+        ("width", width),
+        ("height", height),
+        ("work_x", work_x),
+        ("work_y", work_y),
+        ("work_width", work_width),
+        ("work_height", work_height),
+        ("dpi", monitor_info.dpi as i32),
+    ]
+    .into_iter()
+    .for_each(|(key, value)| {
+        map.insert(key.into(), (value as i64).into());
+    });
+    map.insert("primary".into(), monitor_info.primary.into());
+    map.insert("scale".into(), scale.into());
+    map
+}
+
+fn register_window_info(engine: &mut Engine) {
+    type Result<T> = std::result::Result<T, Box<EvalAltResult>>;
+    engine
+        .register_type_with_name::<WindowInfo>("WindowInfo")
+        .register_get("pid", |window_info: &mut WindowInfo| window_info.pid as i64)
+        .register_get("hwnd", |window_info: &mut WindowInfo| {
+            window_info.hwnd.0 as usize as i64
+        })
+        .register_get("title", |window_info: &mut WindowInfo| {
+            window_info.title.clone()
+        })
+        .register_get("program_path", |window_info: &mut WindowInfo| {
+            window_info.program_path.clone()
+        })
+        .register_get("class_name", |window_info: &mut WindowInfo| {
+            window_class_name(window_info.hwnd)
+        })
+        .register_fn("to_string", |window_info: &mut WindowInfo| {
+            format!(
+                "WindowInfo(pid: {}, hwnd: {}, title: {:?})",
+                window_info.pid, window_info.hwnd.0 as usize, window_info.title
+            )
+        })
+        .register_fn("is_valid", |window_info: &mut WindowInfo| {
+            window_info.is_valid()
+        })
+        .register_fn("is_child", |window_info: &mut WindowInfo| {
+            window_info.is_child()
+        })
+        .register_fn("is_top_most", |window_info: &mut WindowInfo| {
+            window_info.is_top_most()
+        })
+        .register_fn(
+            "is_borderless_fullscreen",
+            |window_info: &mut WindowInfo| window_info.is_borderless_fullscreen(),
+        )
+        .register_fn("size", |window_info: &mut WindowInfo| -> Result<Array> {
+            window_info
+                .get_size()
+                .map(pair_to_array)
+                .map_err(window_error)
+        })
+        .register_fn(
+            "inner_size",
+            |window_info: &mut WindowInfo| -> Result<Array> {
+                window_info
+                    .get_inner_size()
+                    .map(pair_to_array)
+                    .map_err(window_error)
+            },
+        )
+        .register_fn(
+            "position",
+            |window_info: &mut WindowInfo| -> Result<Array> {
+                window_info
+                    .get_position()
+                    .map(pair_to_array)
+                    .map_err(window_error)
+            },
+        )
+        .register_fn("monitor", |window_info: &mut WindowInfo| -> Result<Map> {
+            monitor::monitor_of_window(window_info.hwnd)
+                .map(monitor_to_map)
+                .ok_or_else(|| window_error(Error::InvalidMonitor))
+        })
+        .register_fn(
+            "resize",
+            |window_info: &mut WindowInfo, width: i64, height: i64| -> Result<()> {
+                window_info
+                    .resize([width as _, height as _])
+                    .map_err(window_error)
+            },
+        )
+        .register_fn(
+            "resize_inner",
+            |window_info: &mut WindowInfo, width: i64, height: i64| -> Result<()> {
+                window_info
+                    .resize_inner([width as _, height as _])
+                    .map_err(window_error)
+            },
+        )
+        .register_fn(
+            "move_to",
+            |window_info: &mut WindowInfo, x: i64, y: i64| -> Result<()> {
+                window_info.move_to([x as _, y as _]).map_err(window_error)
+            },
+        )
+        .register_fn(
+            "set_top_most",
+            |window_info: &mut WindowInfo, top_most: bool| -> Result<()> {
+                window_info.set_top_most(top_most).map_err(window_error)
+            },
+        )
+        .register_fn(
+            "set_resizable",
+            |window_info: &mut WindowInfo, resizable: bool| -> Result<()> {
+                window_info.set_resizable(resizable).map_err(window_error)
+            },
+        )
+        .register_fn(
+            "set_maximizable",
+            |window_info: &mut WindowInfo, maximizable: bool| -> Result<()> {
+                window_info
+                    .set_maximizable(maximizable)
+                    .map_err(window_error)
+            },
+        )
+        .register_fn(
+            "set_minimizable",
+            |window_info: &mut WindowInfo, minimizable: bool| -> Result<()> {
+                window_info
+                    .set_minimizable(minimizable)
+                    .map_err(window_error)
+            },
+        )
+        .register_fn(
+            "set_borderless_fullscreen",
+            |window_info: &mut WindowInfo| -> Result<()> {
+                window_info
+                    .set_borderless_fullscreen()
+                    .map_err(window_error)
+            },
+        )
+        .register_fn(
+            "restore_from_borderless_fullscreen",
+            |window_info: &mut WindowInfo| -> Result<()> {
+                window_info
+                    .restore_from_borderless_fullscreen()
+                    .map_err(window_error)
+            },
+        )
+        .register_fn("restore", |window_info: &mut WindowInfo| -> Result<()> {
+            window_info.restore_original_status().map_err(window_error)
+        });
+}
+
+/// An engine without file or module access, with limits against runaway scripts.
+fn create_engine(cancelled: Arc<AtomicBool>, on_print: impl Fn(String) + 'static) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new())
+        .disable_symbol("eval")
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(64)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(1 << 20)
+        .set_max_array_size(1 << 16)
+        .set_max_map_size(1 << 16);
+    let on_print = std::rc::Rc::new(on_print);
+    let on_debug = on_print.clone();
+    engine.on_print(move |text| on_print(text.to_string()));
+    engine.on_debug(move |text, _, position| {
+        on_debug(match position.line() {
+            Some(line) => format!("[{}] {}", line, text),
+            None => text.to_string(),
+        })
+    });
+    let progress_cancelled = cancelled.clone();
+    engine.on_progress(move |_| {
+        progress_cancelled
+            .load(Ordering::Relaxed)
+            .then(|| i18n::tr("script.cancelled").into())
+    });
+    register_window_info(&mut engine);
+    let sleep_cancelled = cancelled.clone();
+    let wait_cancelled = cancelled;
+    engine
+        .register_fn("windows", || to_array(find_windows("")))
+        .register_fn("find_windows", |text: &str| to_array(find_windows(text)))
+        .register_fn("foreground_window", || {
+            WindowInfo::from_hwnd(unsafe { GetForegroundWindow() })
+                .map(Dynamic::from)
+                .unwrap_or(Dynamic::UNIT)
+        })
+        .register_fn(
+            "wait_for_window",
+            move |text: &str, timeout_ms: i64| -> Result<Dynamic, Box<EvalAltResult>> {
+                let deadline = Instant::now() + Duration::from_millis(timeout_ms.max(0) as _);
+                loop {
+                    if let Some(window_info) = find_windows(text).into_iter().next() {
+                        return Ok(Dynamic::from(window_info));
+                    }
+                    if Instant::now() >= deadline {
+                        return Ok(Dynamic::UNIT);
+                    }
+                    sleep(&wait_cancelled, Duration::from_millis(200))?;
+                }
+            },
+        )
+        .register_fn("monitors", || -> Array {
+            monitor::enumerate_monitors()
+                .into_iter()
+                .map(|monitor_info| monitor_to_map(monitor_info).into())
+                .collect()
+        })
+        .register_fn("primary_monitor", || -> Result<Map, Box<EvalAltResult>> {
+            monitor::enumerate_monitors()
+                .into_iter()
+                .find(|monitor_info| monitor_info.primary)
+                .map(monitor_to_map)
+                .ok_or_else(|| runtime_error(i18n::tr("script.no_monitor")))
+        })
+        .register_fn("sleep", move |ms: i64| {
+            sleep(&sleep_cancelled, Duration::from_millis(ms.max(0) as _))
+        });
+    engine
+}
+
+/// Runs the script on the current thread, `on_print` receives the output of `print` and `debug`.
+pub fn run_script(
+    source: &str,
+    cancelled: Arc<AtomicBool>,
+    on_print: impl Fn(String) + 'static,
+) -> Result<(), ScriptError> {
+    let engine = create_engine(cancelled, on_print);
+    let ast = engine
+        .compile(source)
+        .map_err(|err| ScriptError::new(*Box::<EvalAltResult>::from(err)))?;
+    engine.run_ast(&ast).map_err(|err| ScriptError::new(*err))
+}
+
+/// `window_modifier --script <path>`, prints to the console that started the program.
+pub fn run_cli(path: PathBuf) -> std::process::ExitCode {
+    // release builds use the windows subsystem and have no console of their own
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+    let source = match std::fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("{}: {}", path.display(), err);
+            return std::process::ExitCode::FAILURE;
+        }
+    };
+    match run_script(&source, Default::default(), |text| println!("{}", text)) {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}: {}", path.display(), err);
+            std::process::ExitCode::FAILURE
+        }
+    }
+}
+
+enum ScriptEvent {
+    Print(String),
+    Finished(Result<(), ScriptError>),
+}
+
+struct RunningScript {
+    cancelled: Arc<AtomicBool>,
+    event_receiver: Receiver<ScriptEvent>,
+    _join_handle: JoinHandle<()>,
+}
+
+/// Editor and output of the script panel, scripts run on their own thread.
+pub struct ScriptPanel {
+    ctx: egui::Context,
+    source: String,
+    path: Option<PathBuf>,
+    output: Vec<String>,
+    error: Option<ScriptError>,
+    running: Option<RunningScript>,
+}
+
+impl ScriptPanel {
+    pub fn new(ctx: egui::Context) -> Self {
+        Self {
+            ctx,
+            source: EXAMPLE_SCRIPT.to_string(),
+            path: None,
+            output: Vec::new(),
+            error: None,
+            running: None,
+        }
+    }
+
+    fn run(&mut self) {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (event_sender, event_receiver) = channel();
+        let source = self.source.clone();
+        let thread_cancelled = cancelled.clone();
+        let ctx = self.ctx.clone();
+        let join_handle = std::thread::Builder::new()
+            .name("script".to_string())
+            .spawn(move || {
+                let print_sender: Sender<ScriptEvent> = event_sender.clone();
+                let print_ctx = ctx.clone();
+                let result = run_script(&source, thread_cancelled, move |text| {
+                    let _ = print_sender.send(ScriptEvent::Print(text));
+                    print_ctx.request_repaint();
+                });
+                let _ = event_sender.send(ScriptEvent::Finished(result));
+                ctx.request_repaint();
+            })
+            .unwrap();
+        self.output.clear();
+        self.error = None;
+        self.running = Some(RunningScript {
+            cancelled,
+            event_receiver,
+            _join_handle: join_handle,
+        });
+    }
+
+    fn poll_events(&mut self) {
+        let Some(running) = self.running.as_ref() else {
+            return;
+        };
+        let mut finished = false;
+        while let Ok(event) = running.event_receiver.try_recv() {
+            match event {
+                ScriptEvent::Print(text) => self.output.push(text),
+                ScriptEvent::Finished(result) => {
+                    self.error = result.err();
+                    finished = true;
+                }
+            }
+        }
+        finished.then(|| self.running = None);
+    }
+
+    /// returns the error message if a file could not be opened or saved
+    pub fn show_ui(&mut self, ui: &mut egui::Ui) -> Option<String> {
+        const FONT_SIZE: f32 = crate::app::App::FONT_SIZE;
+        let text = |text: &str| egui::RichText::new(text).size(FONT_SIZE);
+        self.poll_events();
+        let mut file_error = None;
+        ui.horizontal(|ui| {
+            match &self.running {
+                Some(running) => {
+                    egui::Button::new(text(i18n::tr("script.stop")))
+                        .ui(ui)
+                        .clicked()
+                        .then(|| running.cancelled.store(true, Ordering::Relaxed));
+                    ui.spinner();
+                }
+                None => {
+                    egui::Button::new(text(i18n::tr("script.run")))
+                        .ui(ui)
+                        .clicked()
+                        .then(|| self.run());
+                }
+            }
+            egui::Button::new(text(i18n::tr("script.open")))
+                .ui(ui)
+                .clicked()
+                .then(|| {
+                    rfd::FileDialog::new()
+                        .add_filter("Rhai", &["rhai"])
+                        .pick_file()
+                        .map(|path| match std::fs::read_to_string(&path) {
+                            Ok(source) => {
+                                self.source = source;
+                                self.path = Some(path);
+                            }
+                            Err(err) => file_error = Some(err.to_string()),
+                        })
+                });
+            egui::Button::new(text(i18n::tr("script.save")))
+                .ui(ui)
+                .clicked()
+                .then(|| {
+                    self.path
+                        .clone()
+                        .or_else(|| {
+                            rfd::FileDialog::new()
+                                .add_filter("Rhai", &["rhai"])
+                                .save_file()
+                        })
+                        .map(|path| match std::fs::write(&path, &self.source) {
+                            Ok(()) => self.path = Some(path),
+                            Err(err) => file_error = Some(err.to_string()),
+                        })
+                });
+            self.path.as_ref().map(|path| {
+                egui::Label::new(text(&path.display().to_string())).ui(ui);
+            });
+        });
+        ui.collapsing(text(i18n::tr("script.api_reference")), |ui| {
+            egui::Label::new(egui::RichText::new(API_REFERENCE).monospace()).ui(ui);
+        });
+        egui::ScrollArea::vertical()
+            .id_salt("script_source")
+            .max_height(ui.available_height() * 0.6)
+            .show(ui, |ui| {
+                egui::TextEdit::multiline(&mut self.source)
+                    .code_editor()
+                    .desired_width(f32::INFINITY)
+                    .desired_rows(16)
+                    .ui(ui);
+            });
+        ui.separator();
+        egui::Label::new(text(i18n::tr("script.output"))).ui(ui);
+        egui::ScrollArea::vertical()
+            .id_salt("script_output")
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                self.output.iter().for_each(|line| {
+                    egui::Label::new(egui::RichText::new(line).monospace())
+                        .selectable(true)
+                        .ui(ui);
+                });
+                self.error.as_ref().map(|err| {
+                    egui::Label::new(
+                        egui::RichText::new(err.to_string())
+                            .monospace()
+                            .color(ui.visuals().error_fg_color),
+                    )
+                    .selectable(true)
+                    .ui(ui);
+                });
+            });
+        file_error
+    }
+}
+
+impl Drop for ScriptPanel {
+    fn drop(&mut self) {
+        self.running
+            .as_ref()
+            .map(|running| running.cancelled.store(true, Ordering::Relaxed));
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_error_line_numbers() {
+        let err = run_script("let x = 1;\nlet y = ;", Default::default(), |_| ()).unwrap_err();
+        assert_eq!(err.line, Some(2));
+        let err = run_script(
+            "let x = 1;\n\nthrow \"failed\";",
+            Default::default(),
+            |_| (),
+        )
+        .unwrap_err();
+        assert_eq!(err.line, Some(3));
+    }
+
+    #[test]
+    fn test_sandbox() {
+        assert!(run_script("import \"module\" as m;", Default::default(), |_| ()).is_err());
+        assert!(run_script("eval(\"1\")", Default::default(), |_| ()).is_err());
+        let cancelled = Arc::new(AtomicBool::new(true));
+        assert!(run_script("loop {}", cancelled, |_| ()).is_err());
+    }
+
+    #[test]
+    fn test_window_error() {
+        let mut engine = Engine::new();
+        engine.register_fn("fail", || -> Result<(), Box<EvalAltResult>> {
+            Err(window_error(Error::ElevatedTarget))
+        });
+        let code = engine
+            .eval::<String>(
+                "let code = \"\"; try { fail(); } catch (err) { code = err.code; } code",
+            )
+            .unwrap();
+        assert_eq!(code, "elevated_target");
+        let err = ScriptError::new(*window_error(Error::WindowGone(None)));
+        assert!(err.message.ends_with("(window_gone)"));
+    }
+}
@@ -0,0 +1,497 @@
+use std::sync::{LazyLock, Mutex, MutexGuard};
+
+use egui::{ThemePreference, Widget};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    font::{self, FontFace},
+    i18n::{self, Language},
+    notification, utils,
+    window_modifier::ModificationKind,
+};
+
+const SETTINGS_FILE_NAME: &str = "settings.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Theme {
+    Dark,
+    Light,
+    /// follows the theme of Windows
+    System,
+}
+
+impl Theme {
+    pub const ALL: [Self; 3] = [Self::Dark, Self::Light, Self::System];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Dark => i18n::tr("settings.theme.dark"),
+            Self::Light => i18n::tr("settings.theme.light"),
+            Self::System => i18n::tr("settings.theme.system"),
+        }
+    }
+
+    pub fn preference(self) -> ThemePreference {
+        match self {
+            Self::Dark => ThemePreference::Dark,
+            Self::Light => ThemePreference::Light,
+            Self::System => ThemePreference::System,
+        }
+    }
+}
+
+/// What happens to a kind of modification when WindowModifier exits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExitAction {
+    Keep,
+    Ask,
+    Restore,
+}
+
+impl ExitAction {
+    pub const ALL: [Self; 3] = [Self::Keep, Self::Ask, Self::Restore];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Keep => i18n::tr("settings.exit_action.keep"),
+            Self::Ask => i18n::tr("settings.exit_action.ask"),
+            Self::Restore => i18n::tr("settings.exit_action.restore"),
+        }
+    }
+}
+
+/// The exit action of each kind of modification, used on quitting, after a crash and on Ctrl+C
+/// in the console.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RestoreOnExit {
+    pub geometry: ExitAction,
+    pub top_most: ExitAction,
+    pub styles: ExitAction,
+    pub borderless_fullscreen: ExitAction,
+}
+
+impl Default for RestoreOnExit {
+    fn default() -> Self {
+        Self {
+            geometry: ExitAction::Ask,
+            top_most: ExitAction::Ask,
+            styles: ExitAction::Ask,
+            borderless_fullscreen: ExitAction::Ask,
+        }
+    }
+}
+
+impl RestoreOnExit {
+    pub fn action(mut self, kind: ModificationKind) -> ExitAction {
+        *self.action_mut(kind)
+    }
+
+    fn action_mut(&mut self, kind: ModificationKind) -> &mut ExitAction {
+        match kind {
+            ModificationKind::Geometry => &mut self.geometry,
+            ModificationKind::TopMost => &mut self.top_most,
+            ModificationKind::Styles => &mut self.styles,
+            ModificationKind::BorderlessFullscreen => &mut self.borderless_fullscreen,
+        }
+    }
+}
+
+/// The main window when it was last moved or resized, in points.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    /// the outer position
+    pub position: [f32; 2],
+    pub inner_size: [f32; 2],
+    pub maximized: bool,
+}
+
+/// Saved in `settings.json`, missing fields of older files take their defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub theme: Theme,
+    /// zoom factor of the user interface, 1.0 is 100%
+    pub ui_scale: f32,
+    /// an installed font drawing the user interface before the default fonts, `None` uses them
+    pub ui_font: Option<FontFace>,
+    /// language code, `None` follows the language of Windows
+    pub language: Option<String>,
+    pub remember_window_geometry: bool,
+    pub window_geometry: Option<WindowGeometry>,
+    pub minimize_to_tray_on_close: bool,
+    pub restore_on_exit: RestoreOnExit,
+    /// moving a window keeps enough of its title bar in a work area to grab it
+    pub keep_title_bar_on_screen: bool,
+    /// modifications wait for the window and report what differs from the request
+    pub verify_after_apply: bool,
+    /// seconds between automatic refreshes of the window list, 0 disables them
+    pub auto_refresh_interval: u32,
+    /// upper bound of the size sliders, the position sliders range from its negative
+    pub slider_max: i32,
+    pub logarithmic_sliders: bool,
+    /// the local JSON-RPC server is started with the app
+    pub ipc_server_enabled: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            theme: Theme::Dark,
+            ui_scale: 1.0,
+            ui_font: None,
+            language: None,
+            remember_window_geometry: true,
+            window_geometry: None,
+            minimize_to_tray_on_close: false,
+            restore_on_exit: RestoreOnExit::default(),
+            keep_title_bar_on_screen: false,
+            verify_after_apply: false,
+            auto_refresh_interval: 0,
+            slider_max: 8192,
+            logarithmic_sliders: true,
+            ipc_server_enabled: false,
+        }
+    }
+}
+
+impl Settings {
+    pub const DEFAULT_WINDOW_SIZE: [f32; 2] = [1280.0, 720.0];
+    pub const MIN_WINDOW_SIZE: [f32; 2] = [600.0, 600.0];
+    const UI_SCALE_RANGE: std::ops::RangeInclusive<f32> = 0.5..=3.0;
+    const SLIDER_MAX_RANGE: std::ops::RangeInclusive<i32> = 1024..=65536;
+
+    /// the defaults if the file is missing, `Err` if it is broken
+    fn load() -> std::io::Result<Self> {
+        Ok(utils::load_json(SETTINGS_FILE_NAME)?
+            .map(Self::sanitized)
+            .unwrap_or_default())
+    }
+
+    fn save(&self) {
+        let _ = serde_json::to_string_pretty(self)
+            .map(|text| std::fs::write(utils::app_data_dir().join(SETTINGS_FILE_NAME), text));
+    }
+
+    /// clamps values a hand-edited file may have out of range
+    fn sanitized(mut self) -> Self {
+        self.ui_scale = self
+            .ui_scale
+            .clamp(*Self::UI_SCALE_RANGE.start(), *Self::UI_SCALE_RANGE.end());
+        self.slider_max = self.slider_max.clamp(
+            *Self::SLIDER_MAX_RANGE.start(),
+            *Self::SLIDER_MAX_RANGE.end(),
+        );
+        self
+    }
+
+    /// the saved language, or the language of Windows
+    pub fn language(&self) -> Language {
+        self.language
+            .as_deref()
+            .and_then(Language::from_code)
+            .unwrap_or_else(Language::system)
+    }
+
+    /// Applies the theme and the scale to the user interface.
+    pub fn apply(&self, ctx: &egui::Context) {
+        ctx.set_theme(self.theme.preference());
+        ctx.set_zoom_factor(self.ui_scale);
+    }
+
+    /// The rows of the settings panel, returns `true` if anything changed.
+    pub fn show_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        const FONT_SIZE: f32 = crate::app::App::FONT_SIZE;
+        let text = |text: &str| egui::RichText::new(text).size(FONT_SIZE);
+        let mut changed = false;
+        egui::Grid::new(ui.next_auto_id())
+            .num_columns(2)
+            .spacing([20.0, 8.0])
+            .striped(true)
+            .show(ui, |ui| {
+                egui::Label::new(text(i18n::tr("settings.theme"))).ui(ui);
+                ui.horizontal(|ui| {
+                    Theme::ALL.into_iter().for_each(|theme| {
+                        changed |= ui
+                            .radio_value(&mut self.theme, theme, text(theme.name()))
+                            .changed();
+                    });
+                });
+                ui.end_row();
+
+                egui::Label::new(text(i18n::tr("settings.ui_scale"))).ui(ui);
+                // applied on release, a scale changing while dragging moves the slider away
+                let response = egui::Slider::new(&mut self.ui_scale, Self::UI_SCALE_RANGE)
+                    .step_by(0.05)
+                    .fixed_decimals(2)
+                    .ui(ui);
+                changed |= response.drag_stopped() || (response.changed() && !response.dragged());
+                ui.end_row();
+
+                egui::Label::new(text(i18n::tr("settings.ui_font"))).ui(ui);
+                changed |= self.ui_font_combo_box(ui);
+                ui.end_row();
+
+                egui::Label::new(text(i18n::tr("app.language"))).ui(ui);
+                let current_language = self.language();
+                egui::ComboBox::from_id_salt("language")
+                    .selected_text(text(current_language.name()))
+                    .show_ui(ui, |ui| {
+                        Language::ALL.into_iter().for_each(|language| {
+                            ui.selectable_label(
+                                language == current_language,
+                                text(language.name()),
+                            )
+                            .clicked()
+                            .then(|| {
+                                self.language = Some(language.code().to_string());
+                                changed = true;
+                            });
+                        });
+                    });
+                ui.end_row();
+
+                egui::Label::new(text(i18n::tr("settings.window"))).ui(ui);
+                ui.vertical(|ui| {
+                    changed |= egui::Checkbox::new(
+                        &mut self.remember_window_geometry,
+                        text(i18n::tr("settings.remember_window_geometry")),
+                    )
+                    .ui(ui)
+                    .changed();
+                    changed |= egui::Checkbox::new(
+                        &mut self.minimize_to_tray_on_close,
+                        text(i18n::tr("app.minimize_to_tray_on_close")),
+                    )
+                    .ui(ui)
+                    .changed();
+                });
+                ui.end_row();
+
+                egui::Label::new(text(i18n::tr("settings.restore_on_exit"))).ui(ui);
+                egui::Grid::new("restore_on_exit")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ModificationKind::ALL.into_iter().for_each(|kind| {
+                            egui::Label::new(text(kind.name())).ui(ui);
+                            ui.horizontal(|ui| {
+                                let action = self.restore_on_exit.action_mut(kind);
+                                ExitAction::ALL.into_iter().for_each(|exit_action| {
+                                    changed |= ui
+                                        .radio_value(action, exit_action, text(exit_action.name()))
+                                        .changed();
+                                });
+                            });
+                            ui.end_row();
+                        });
+                    });
+                ui.end_row();
+
+                egui::Label::new(text(i18n::tr("settings.positions"))).ui(ui);
+                changed |= egui::Checkbox::new(
+                    &mut self.keep_title_bar_on_screen,
+                    text(i18n::tr("settings.keep_title_bar_on_screen")),
+                )
+                .ui(ui)
+                .changed();
+                ui.end_row();
+
+                egui::Label::new(text(i18n::tr("settings.verification"))).ui(ui);
+                changed |= egui::Checkbox::new(
+                    &mut self.verify_after_apply,
+                    text(i18n::tr("settings.verify_after_apply")),
+                )
+                .ui(ui)
+                .on_hover_text(i18n::tr("settings.verify_after_apply_hint"))
+                .changed();
+                ui.end_row();
+
+                egui::Label::new(text(i18n::tr("settings.auto_refresh_interval"))).ui(ui);
+                ui.horizontal(|ui| {
+                    changed |= egui::DragValue::new(&mut self.auto_refresh_interval)
+                        .range(0..=3600)
+                        .suffix(i18n::tr("settings.seconds"))
+                        .ui(ui)
+                        .changed();
+                    egui::Label::new(text(i18n::tr("settings.zero_disables"))).ui(ui);
+                });
+                ui.end_row();
+
+                egui::Label::new(text(i18n::tr("settings.sliders"))).ui(ui);
+                ui.horizontal(|ui| {
+                    egui::Label::new(text(i18n::tr("settings.slider_max"))).ui(ui);
+                    changed |= egui::DragValue::new(&mut self.slider_max)
+                        .range(Self::SLIDER_MAX_RANGE)
+                        .speed(16.0)
+                        .ui(ui)
+                        .changed();
+                    changed |= egui::Checkbox::new(
+                        &mut self.logarithmic_sliders,
+                        text(i18n::tr("settings.logarithmic_sliders")),
+                    )
+                    .ui(ui)
+                    .changed();
+                });
+                ui.end_row();
+            });
+        ui.separator();
+        egui::Button::new(text(i18n::tr("settings.reset")))
+            .ui(ui)
+            .clicked()
+            .then(|| {
+                // the geometry is not a preference, keep where the window is
+                *self = Self {
+                    window_geometry: self.window_geometry,
+                    ..Default::default()
+                };
+                changed = true;
+            });
+        changed
+    }
+}
+
+impl Settings {
+    /// `true` if another font was picked
+    fn ui_font_combo_box(&mut self, ui: &mut egui::Ui) -> bool {
+        const FONT_SIZE: f32 = crate::app::App::FONT_SIZE;
+        let text = |text: &str| egui::RichText::new(text).size(FONT_SIZE);
+        let selected_text = self
+            .ui_font
+            .as_ref()
+            .map(|face| face.family.as_str())
+            .unwrap_or(i18n::tr("settings.ui_font.default"));
+        let mut changed = false;
+        egui::ComboBox::from_id_salt("ui_font")
+            .selected_text(text(selected_text))
+            .height(400.0)
+            .show_ui(ui, |ui| {
+                ui.selectable_label(
+                    self.ui_font.is_none(),
+                    text(i18n::tr("settings.ui_font.default")),
+                )
+                .clicked()
+                .then(|| {
+                    changed = self.ui_font.is_some();
+                    self.ui_font = None;
+                });
+                let Some(installed_fonts) = font::installed_fonts() else {
+                    egui::Label::new(text(i18n::tr("settings.ui_font.loading"))).ui(ui);
+                    ui.ctx()
+                        .request_repaint_after(std::time::Duration::from_millis(100));
+                    return;
+                };
+                installed_fonts.iter().for_each(|face| {
+                    let selected = self.ui_font.as_ref() == Some(face);
+                    ui.selectable_label(selected, text(&face.family))
+                        .clicked()
+                        .then(|| {
+                            changed = !selected;
+                            self.ui_font = Some(face.clone());
+                        });
+                });
+            });
+        changed
+    }
+}
+
+struct StoredSettings {
+    settings: Settings,
+    /// `false` after the settings file could not be read, so the defaults don't overwrite it
+    /// before the user edits a setting
+    save_allowed: bool,
+}
+
+static SETTINGS: LazyLock<Mutex<StoredSettings>> = LazyLock::new(|| {
+    let (settings, save_allowed) = match Settings::load() {
+        Ok(settings) => (settings, true),
+        Err(err) => {
+            tracing::warn!(error = %err, "settings could not be loaded");
+            notification::warning(i18n::tr_format(
+                "settings.load_failed",
+                &[("file", &SETTINGS_FILE_NAME), ("error", &err)],
+            ));
+            (Settings::default(), false)
+        }
+    };
+    Mutex::new(StoredSettings {
+        settings,
+        save_allowed,
+    })
+});
+
+fn stored_settings() -> MutexGuard<'static, StoredSettings> {
+    // also read when restoring the windows after a panic
+    SETTINGS.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// a copy of the current settings
+pub fn settings() -> Settings {
+    stored_settings().settings.clone()
+}
+
+/// Replaces the settings edited by the user and saves them if they changed.
+pub fn set_settings(settings: Settings) {
+    let mut stored = stored_settings();
+    if stored.settings != settings {
+        stored.settings = settings;
+        stored.save_allowed = true;
+        stored.settings.save();
+    }
+}
+
+/// Remembers the geometry of the main window, saved unless a broken settings file is kept.
+pub fn set_window_geometry(window_geometry: Option<WindowGeometry>) {
+    let mut stored = stored_settings();
+    if stored.settings.window_geometry != window_geometry {
+        stored.settings.window_geometry = window_geometry;
+        if stored.save_allowed {
+            stored.settings.save();
+        }
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_missing_fields_take_defaults() {
+        let settings: Settings =
+            serde_json::from_str(r#"{"theme": "Light", "slider_max": 4096}"#).unwrap();
+        assert_eq!(
+            settings,
+            Settings {
+                theme: Theme::Light,
+                slider_max: 4096,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_restore_on_exit() {
+        let settings: Settings =
+            serde_json::from_str(r#"{"restore_on_exit": {"styles": "Restore"}}"#).unwrap();
+        let restore_on_exit = settings.restore_on_exit;
+        assert_eq!(
+            restore_on_exit.action(ModificationKind::Styles),
+            ExitAction::Restore
+        );
+        assert_eq!(
+            restore_on_exit.action(ModificationKind::BorderlessFullscreen),
+            ExitAction::Ask
+        );
+    }
+
+    #[test]
+    fn test_sanitized() {
+        let settings = Settings {
+            ui_scale: 10.0,
+            slider_max: 0,
+            ..Default::default()
+        }
+        .sanitized();
+        assert_eq!(settings.ui_scale, 3.0);
+        assert_eq!(settings.slider_max, 1024);
+    }
+}
@@ -0,0 +1,303 @@
+use std::{
+    cell::RefCell,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use tray_icon::{
+    Icon, MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent,
+    menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu},
+};
+use windows::Win32::{
+    Foundation::HWND,
+    UI::WindowsAndMessaging::{
+        GetForegroundWindow, IsIconic, SW_HIDE, SW_RESTORE, SW_SHOW, SetForegroundWindow,
+        ShowWindow,
+    },
+};
+
+use crate::{
+    error::Error,
+    hotkey::HotkeyAction,
+    i18n, notification,
+    window_list::program_name,
+    window_modifier::{self, WindowInfo, window_class_name, window_pid},
+};
+
+const MENU_ID_OPEN: &str = "open";
+const MENU_ID_TOGGLE_BORDERLESS_FULLSCREEN: &str = "toggle_borderless_fullscreen";
+const MENU_ID_RESTORE_ALL: &str = "restore_all";
+const MENU_ID_QUIT: &str = "quit";
+/// followed by `{pid}:{hwnd}`
+const MENU_ID_RESTORE_PREFIX: &str = "restore:";
+
+const RECENT_WINDOWS_MAX_COUNT: usize = 10;
+
+/// windows of the shell that become the foreground window when the tray icon is clicked
+const SHELL_CLASS_NAMES: [&str; 4] = [
+    "Shell_TrayWnd",
+    "Shell_SecondaryTrayWnd",
+    "NotifyIconOverflowWindow",
+    "TopLevelWindowForOverflowXamlIsland",
+];
+
+static MAIN_WINDOW: AtomicUsize = AtomicUsize::new(0);
+/// The foreground window right before the tray icon was clicked.
+static LAST_FOREGROUND_WINDOW: AtomicUsize = AtomicUsize::new(0);
+/// set by the quit item, the app closes its main window on the next frame
+static QUIT_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    /// The tray icon lives on the main thread, its events are dispatched there while eframe
+    /// pumps messages, even if the main window is hidden.
+    static TRAY: RefCell<Option<Tray>> = const { RefCell::new(None) };
+}
+
+struct Tray {
+    _tray_icon: TrayIcon,
+    ctx: egui::Context,
+    open_item: MenuItem,
+    toggle_borderless_fullscreen_item: MenuItem,
+    recent_windows_submenu: Submenu,
+    quit_item: MenuItem,
+}
+
+impl Tray {
+    fn update_texts(&self) {
+        self.open_item.set_text(i18n::tr("tray.open"));
+        self.toggle_borderless_fullscreen_item
+            .set_text(i18n::tr("tray.toggle_borderless_fullscreen"));
+        self.recent_windows_submenu
+            .set_text(i18n::tr("tray.recent_windows"));
+        self.quit_item.set_text(i18n::tr("tray.quit"));
+    }
+}
+
+/// Creates the tray icon, must be called on the main thread.
+pub fn install(main_window: HWND, ctx: egui::Context) -> Result<(), Box<dyn std::error::Error>> {
+    MAIN_WINDOW.store(main_window.0 as usize, Ordering::Relaxed);
+    let open_item = MenuItem::with_id(MENU_ID_OPEN, i18n::tr("tray.open"), true, None);
+    let toggle_borderless_fullscreen_item = MenuItem::with_id(
+        MENU_ID_TOGGLE_BORDERLESS_FULLSCREEN,
+        i18n::tr("tray.toggle_borderless_fullscreen"),
+        true,
+        None,
+    );
+    let recent_windows_submenu = Submenu::new(i18n::tr("tray.recent_windows"), true);
+    let quit_item = MenuItem::with_id(MENU_ID_QUIT, i18n::tr("tray.quit"), true, None);
+    let menu = Menu::new();
+    menu.append_items(&[
+        &open_item,
+        &PredefinedMenuItem::separator(),
+        &toggle_borderless_fullscreen_item,
+        &recent_windows_submenu,
+        &PredefinedMenuItem::separator(),
+        &quit_item,
+    ])?;
+    let tray_icon = TrayIconBuilder::new()
+        .with_tooltip("Window Modifier")
+        .with_icon(icon())
+        .with_menu(Box::new(menu))
+        .with_menu_on_left_click(false)
+        .build()?;
+    TRAY.with_borrow_mut(|tray| {
+        *tray = Some(Tray {
+            _tray_icon: tray_icon,
+            ctx,
+            open_item,
+            toggle_borderless_fullscreen_item,
+            recent_windows_submenu,
+            quit_item,
+        })
+    });
+    TrayIconEvent::set_event_handler(Some(handle_tray_icon_event));
+    MenuEvent::set_event_handler(Some(handle_menu_event));
+    Ok(())
+}
+
+/// Applies the current language to the menu, the recently modified windows are rebuilt anyway
+/// before the menu shows up.
+pub fn update_texts() {
+    TRAY.with_borrow(|tray| tray.as_ref().map(Tray::update_texts));
+}
+
+pub fn hide_main_window() {
+    let main_window = HWND(MAIN_WINDOW.load(Ordering::Relaxed) as _);
+    if main_window.is_invalid() {
+        return;
+    }
+    let _ = unsafe { ShowWindow(main_window, SW_HIDE) };
+}
+
+pub fn show_main_window() {
+    let main_window = HWND(MAIN_WINDOW.load(Ordering::Relaxed) as _);
+    if main_window.is_invalid() {
+        return;
+    }
+    let _ = unsafe { ShowWindow(main_window, SW_SHOW) };
+    if unsafe { IsIconic(main_window) }.as_bool() {
+        let _ = unsafe { ShowWindow(main_window, SW_RESTORE) };
+    }
+    let _ = unsafe { SetForegroundWindow(main_window) };
+}
+
+fn handle_tray_icon_event(event: TrayIconEvent) {
+    match event {
+        TrayIconEvent::Enter { .. } | TrayIconEvent::Move { .. } => remember_foreground_window(),
+        TrayIconEvent::Click {
+            button: MouseButton::Right,
+            button_state: MouseButtonState::Down,
+            ..
+        } => {
+            // sent right before the context menu shows up
+            remember_foreground_window();
+            update_recent_windows_submenu();
+        }
+        TrayIconEvent::DoubleClick {
+            button: MouseButton::Left,
+            ..
+        } => show_main_window(),
+        _ => (),
+    }
+}
+
+fn handle_menu_event(event: MenuEvent) {
+    let id = event.id.as_ref();
+    match id {
+        MENU_ID_OPEN => show_main_window(),
+        MENU_ID_TOGGLE_BORDERLESS_FULLSCREEN => {
+            let last_foreground_window = HWND(LAST_FOREGROUND_WINDOW.load(Ordering::Relaxed) as _);
+            WindowInfo::from_hwnd(last_foreground_window)
+                .ok_or(Error::WindowGone(None))
+                .and_then(|window_info| {
+                    HotkeyAction::ToggleBorderlessFullscreen.apply(&window_info)
+                })
+                .unwrap_or_else(|err| notification::error(&err));
+        }
+        MENU_ID_RESTORE_ALL => {
+            restore_errors_message(window_modifier::restore_all_modified_windows())
+                .map(notification::warning);
+        }
+        MENU_ID_QUIT => quit(),
+        _ => {
+            let Some(modified_window) = id.strip_prefix(MENU_ID_RESTORE_PREFIX).and_then(|key| {
+                window_modifier::modified_windows()
+                    .into_iter()
+                    .find(|modified_window| modified_window_menu_id(modified_window) == key)
+            }) else {
+                return;
+            };
+            modified_window
+                .window_info()
+                .restore_original_status()
+                .unwrap_or_else(|err| notification::error(&err));
+        }
+    }
+}
+
+fn remember_foreground_window() {
+    let foreground_window = unsafe { GetForegroundWindow() };
+    if foreground_window.is_invalid()
+        || window_pid(foreground_window) == std::process::id()
+        || SHELL_CLASS_NAMES.contains(&window_class_name(foreground_window).as_str())
+    {
+        return;
+    }
+    LAST_FOREGROUND_WINDOW.store(foreground_window.0 as usize, Ordering::Relaxed);
+}
+
+fn modified_window_menu_id(modified_window: &window_modifier::ModifiedWindow) -> String {
+    let window_info = modified_window.window_info();
+    format!("{}:{}", window_info.pid, window_info.hwnd.0 as usize)
+}
+
+fn update_recent_windows_submenu() {
+    // cloned so that the thread local is not borrowed while the menu is rebuilt
+    let Some(submenu) = TRAY.with_borrow(|tray| {
+        tray.as_ref()
+            .map(|tray| tray.recent_windows_submenu.clone())
+    }) else {
+        return;
+    };
+    (0..submenu.items().len()).for_each(|_| {
+        submenu.remove_at(0);
+    });
+    let modified_windows = window_modifier::modified_windows();
+    if modified_windows.is_empty() {
+        let _ = submenu.append(&MenuItem::new(i18n::tr("common.none"), false, None));
+        return;
+    }
+    modified_windows
+        .iter()
+        .take(RECENT_WINDOWS_MAX_COUNT)
+        .for_each(|modified_window| {
+            let text = format!(
+                "{} ({})",
+                modified_window.title,
+                program_name(&modified_window.program_path)
+            );
+            let id = format!(
+                "{}{}",
+                MENU_ID_RESTORE_PREFIX,
+                modified_window_menu_id(modified_window)
+            );
+            let _ = submenu.append(&MenuItem::with_id(id, text, true, None));
+        });
+    let _ = submenu.append_items(&[
+        &PredefinedMenuItem::separator(),
+        &MenuItem::with_id(
+            MENU_ID_RESTORE_ALL,
+            i18n::tr("tray.restore_all"),
+            true,
+            None,
+        ),
+    ]);
+}
+
+/// the message listing the windows that failed to be restored, `None` if none failed
+pub fn restore_errors_message(errors: Vec<(WindowInfo, Error)>) -> Option<String> {
+    if errors.is_empty() {
+        return None;
+    }
+    let message = errors
+        .iter()
+        .map(|(window_info, err)| format!("{}: {}", window_info.title, err))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(i18n::tr_format(
+        "tray.restore_failed",
+        &[("windows", &message)],
+    ))
+}
+
+/// Asks the app to close its main window, so quitting runs through eframe's shutdown and the
+/// restoring of the modified windows. The window is shown, it can't update while hidden.
+fn quit() {
+    QUIT_REQUESTED.store(true, Ordering::Relaxed);
+    show_main_window();
+    TRAY.with_borrow(|tray| tray.as_ref().map(|tray| tray.ctx.request_repaint()));
+}
+
+/// whether quit was chosen from the menu since the last call
+pub fn take_quit_request() -> bool {
+    QUIT_REQUESTED.swap(false, Ordering::Relaxed)
+}
+
+/// a white window frame with a title bar on a blue rounded square
+fn icon() -> Icon {
+    const SIZE: u32 = 32;
+    let rgba = (0..SIZE * SIZE)
+        .flat_map(|index| {
+            let (x, y) = ((index % SIZE) as i32, (index / SIZE) as i32);
+            let corner_distance = |c: i32| (3 - c).max(c - (SIZE as i32 - 4)).max(0);
+            let in_square = corner_distance(x).pow(2) + corner_distance(y).pow(2) <= 9;
+            let in_frame = (6..=25).contains(&x) && (7..=24).contains(&y);
+            let in_client = (8..=23).contains(&x) && (12..=22).contains(&y);
+            match (in_square, in_frame && !in_client) {
+                (false, _) => [0, 0, 0, 0],
+                (true, true) => [255, 255, 255, 255],
+                (true, false) => [38, 110, 200, 255],
+            }
+        })
+        .collect();
+    Icon::from_rgba(rgba, SIZE, SIZE).unwrap()
+}
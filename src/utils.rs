@@ -8,3 +8,36 @@ where
     }
     a
 }
+
+/// `%APPDATA%\WindowModifier`, created if missing.
+pub fn app_data_dir() -> std::path::PathBuf {
+    let dir = std::env::var_os("APPDATA")
+        .map(std::path::PathBuf::from)
+        .unwrap_or_default()
+        .join("WindowModifier");
+    let _ = std::fs::create_dir_all(&dir);
+    dir
+}
+
+/// Reads the JSON file `file_name` of the app data directory, `None` if it doesn't exist. A file
+/// that can't be read is copied to `<file_name>.bak`, so the caller's defaults can't overwrite
+/// the only copy of it.
+pub fn load_json<T: serde::de::DeserializeOwned>(file_name: &str) -> std::io::Result<Option<T>> {
+    let path = app_data_dir().join(file_name);
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    serde_json::from_str(&text).map(Some).map_err(|err| {
+        let _ = std::fs::copy(&path, backup_path(&path));
+        err.into()
+    })
+}
+
+/// `<path>.bak`
+pub fn backup_path(path: &std::path::Path) -> std::path::PathBuf {
+    let mut backup_path = path.as_os_str().to_owned();
+    backup_path.push(".bak");
+    backup_path.into()
+}
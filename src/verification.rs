@@ -0,0 +1,234 @@
+use windows::Win32::{
+    Foundation::{CloseHandle, HANDLE},
+    Security::{
+        GetSidSubAuthority, GetSidSubAuthorityCount, GetTokenInformation, TOKEN_MANDATORY_LABEL,
+        TOKEN_QUERY, TokenIntegrityLevel,
+    },
+    System::Threading::{
+        GetCurrentProcess, OpenProcess, OpenProcessToken, PROCESS_QUERY_LIMITED_INFORMATION,
+    },
+    UI::WindowsAndMessaging::{SET_WINDOW_POS_FLAGS, SWP_ASYNCWINDOWPOS},
+};
+
+use crate::{
+    error::{Error, Result},
+    i18n, settings,
+    window_modifier::WindowInfo,
+};
+
+/// `true` if modifications wait for the window and check the result,
+/// [`settings::Settings::verify_after_apply`]
+pub fn enabled() -> bool {
+    settings::settings().verify_after_apply
+}
+
+/// `SWP_ASYNCWINDOWPOS`, unless the result is verified, which needs the change to be done
+pub fn async_flag() -> SET_WINDOW_POS_FLAGS {
+    match enabled() {
+        true => SET_WINDOW_POS_FLAGS(0),
+        false => SWP_ASYNCWINDOWPOS,
+    }
+}
+
+/// The mandatory integrity level of the token of `process`, like `0x2000` for medium and
+/// `0x3000` for high (elevated).
+fn token_integrity_level(process: HANDLE) -> Option<u32> {
+    let mut token = HANDLE::default();
+    unsafe { OpenProcessToken(process, TOKEN_QUERY, &mut token) }.ok()?;
+    let mut size = 0;
+    // fails with the size needed
+    let _ = unsafe { GetTokenInformation(token, TokenIntegrityLevel, None, 0, &mut size) };
+    let mut buffer = vec![0u8; size as usize];
+    let result = unsafe {
+        GetTokenInformation(
+            token,
+            TokenIntegrityLevel,
+            Some(buffer.as_mut_ptr().cast()),
+            size,
+            &mut size,
+        )
+    };
+    let _ = unsafe { CloseHandle(token) };
+    result.ok()?;
+    let label = unsafe {
+        buffer
+            .as_ptr()
+            .cast::<TOKEN_MANDATORY_LABEL>()
+            .read_unaligned()
+    };
+    let sid = label.Label.Sid;
+    let count = unsafe { *GetSidSubAuthorityCount(sid) };
+    Some(unsafe { *GetSidSubAuthority(sid, u32::from(count).checked_sub(1)?) })
+}
+
+fn integrity_level(pid: u32) -> Option<u32> {
+    let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) }.ok()?;
+    let integrity_level = token_integrity_level(process);
+    let _ = unsafe { CloseHandle(process) };
+    integrity_level
+}
+
+/// `true` if the process of the window runs at a higher integrity level than this one, e.g. as
+/// administrator, so User Interface Privilege Isolation blocks changing its windows.
+pub fn is_blocked_by_uipi(window_info: &WindowInfo) -> bool {
+    let own = token_integrity_level(unsafe { GetCurrentProcess() });
+    match (integrity_level(window_info.pid), own) {
+        (Some(target), Some(own)) => target > own,
+        _ => false,
+    }
+}
+
+/// Explains a failed modification: access denied by UIPI is reported as such.
+pub fn explain(window_info: &WindowInfo, err: Error) -> Error {
+    match err {
+        Error::AccessDenied(_) if is_blocked_by_uipi(window_info) => Error::ElevatedTarget,
+        err => err,
+    }
+}
+
+/// Why a window ended up with another size than requested, as told by `WM_GETMINMAXINFO`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SizeLimit {
+    Minimum,
+    Maximum,
+}
+
+impl SizeLimit {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Minimum => i18n::tr("verification.minimum_size"),
+            Self::Maximum => i18n::tr("verification.maximum_size"),
+        }
+    }
+}
+
+/// Larger than requested means the window has a minimum size, smaller a maximum size.
+fn size_limit(requested: [i32; 2], actual: [i32; 2]) -> Option<SizeLimit> {
+    let larger = requested
+        .iter()
+        .zip(actual)
+        .any(|(&requested, actual)| actual > requested);
+    let smaller = requested
+        .iter()
+        .zip(actual)
+        .any(|(&requested, actual)| actual < requested);
+    match (larger, smaller) {
+        (true, false) => Some(SizeLimit::Minimum),
+        (false, true) => Some(SizeLimit::Maximum),
+        _ => None,
+    }
+}
+
+/// like `requested 800x600, got 1024x768 (minimum size)`
+fn mismatch_message(property: &str, requested: &str, actual: &str, reason: Option<&str>) -> String {
+    let message = i18n::tr_format(
+        "verification.mismatch",
+        &[
+            ("property", &property),
+            ("requested", &requested),
+            ("actual", &actual),
+        ],
+    );
+    match reason {
+        Some(reason) => format!("{} ({})", message, reason),
+        None => message,
+    }
+}
+
+fn mismatch(
+    window_info: &WindowInfo,
+    property: &str,
+    requested: &str,
+    actual: &str,
+    reason: Option<&str>,
+) -> Error {
+    // an asynchronous change that was silently dropped
+    if is_blocked_by_uipi(window_info) {
+        return Error::ElevatedTarget;
+    }
+    let message = mismatch_message(property, requested, actual, reason);
+    tracing::warn!(title = window_info.title, message, "verification failed");
+    Error::Refused { message }
+}
+
+/// `property_key` names the size, like `app.window_size`
+pub fn verify_size(
+    window_info: &WindowInfo,
+    property_key: &'static str,
+    requested: [i32; 2],
+    actual: [i32; 2],
+) -> Result<()> {
+    if requested == actual {
+        return Ok(());
+    }
+    let format = |[width, height]: [i32; 2]| format!("{}x{}", width, height);
+    Err(mismatch(
+        window_info,
+        i18n::tr(property_key),
+        &format(requested),
+        &format(actual),
+        size_limit(requested, actual).map(SizeLimit::name),
+    ))
+}
+
+pub fn verify_position(
+    window_info: &WindowInfo,
+    requested: [i32; 2],
+    actual: [i32; 2],
+) -> Result<()> {
+    if requested == actual {
+        return Ok(());
+    }
+    let format = |[x, y]: [i32; 2]| format!("({}, {})", x, y);
+    Err(mismatch(
+        window_info,
+        i18n::tr("app.window_position"),
+        &format(requested),
+        &format(actual),
+        None,
+    ))
+}
+
+/// `property_key` names the switch, like `app.top_most`
+pub fn verify_switch(
+    window_info: &WindowInfo,
+    property_key: &'static str,
+    requested: bool,
+    actual: bool,
+) -> Result<()> {
+    if requested == actual {
+        return Ok(());
+    }
+    let format = |enabled: bool| match enabled {
+        true => i18n::tr("common.enabled"),
+        false => i18n::tr("common.disabled"),
+    };
+    Err(mismatch(
+        window_info,
+        i18n::tr(property_key),
+        format(requested),
+        format(actual),
+        None,
+    ))
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_size_limit() {
+        assert_eq!(
+            size_limit([800, 600], [1024, 768]),
+            Some(SizeLimit::Minimum)
+        );
+        assert_eq!(size_limit([800, 600], [800, 768]), Some(SizeLimit::Minimum));
+        assert_eq!(
+            size_limit([4000, 3000], [1920, 1080]),
+            Some(SizeLimit::Maximum)
+        );
+        // clamped both ways, or not at all
+        assert_eq!(size_limit([100, 3000], [400, 1080]), None);
+        assert_eq!(size_limit([800, 600], [800, 600]), None);
+    }
+}
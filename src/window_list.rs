@@ -0,0 +1,811 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    ops::Range,
+};
+
+use egui::Widget;
+use windows::Win32::UI::WindowsAndMessaging::{GA_ROOT, GetAncestor};
+
+use crate::{
+    i18n, icon, lock, utils,
+    window_modifier::{self, WindowInfo},
+    window_tree::enumerate_top_level_windows,
+};
+
+const PINNED_PROCESSES_FILE_NAME: &str = "pinned_processes.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortMode {
+    Title,
+    Path,
+    Pid,
+    /// top-most window first
+    ZOrder,
+    RecentlyModified,
+}
+
+impl SortMode {
+    pub const ALL: [Self; 5] = [
+        Self::Title,
+        Self::Path,
+        Self::Pid,
+        Self::ZOrder,
+        Self::RecentlyModified,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Title => i18n::tr("window_list.sort.title"),
+            Self::Path => i18n::tr("window_list.sort.path"),
+            Self::Pid => i18n::tr("window_list.sort.pid"),
+            Self::ZOrder => i18n::tr("window_list.sort.z_order"),
+            Self::RecentlyModified => i18n::tr("window_list.sort.recently_modified"),
+        }
+    }
+}
+
+/// How much of every window the list shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowMode {
+    /// one line with the title
+    Compact,
+    /// a card with the pid, the handle, the title and the path
+    Detailed,
+}
+
+impl RowMode {
+    pub const ALL: [Self; 2] = [Self::Compact, Self::Detailed];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Compact => i18n::tr("window_list.view.compact"),
+            Self::Detailed => i18n::tr("window_list.view.detailed"),
+        }
+    }
+}
+
+/// A line of the list, only the visible ones are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Row {
+    /// the header of the windows of one process, `index` of its first window
+    Process {
+        index: usize,
+        count: usize,
+        collapsed: bool,
+    },
+    /// `position` in the shown order, `index` into the window info list
+    Window {
+        position: usize,
+        index: usize,
+        grouped: bool,
+    },
+}
+
+/// Rows of the same kind have the same height, measured when they are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum RowKind {
+    Process,
+    Compact,
+    Detailed,
+}
+
+impl Row {
+    /// the window of the row, the first one of the process for a header
+    fn index(self) -> usize {
+        match self {
+            Self::Process { index, .. } | Self::Window { index, .. } => index,
+        }
+    }
+
+    fn kind(self, row_mode: RowMode) -> RowKind {
+        match (self, row_mode) {
+            (Self::Process { .. }, _) => RowKind::Process,
+            (Self::Window { .. }, RowMode::Compact) => RowKind::Compact,
+            (Self::Window { .. }, RowMode::Detailed) => RowKind::Detailed,
+        }
+    }
+}
+
+/// the file name of the executable, which identifies a pinned process across restarts
+pub fn program_name(program_path: &str) -> &str {
+    program_path
+        .rsplit(['\\', '/'])
+        .next()
+        .unwrap_or(program_path)
+}
+
+/// Order in which the window list is shown, indices into the window info list so that the list
+/// itself and the index of the current window stay untouched.
+#[derive(Debug)]
+pub struct WindowListView {
+    sort_mode: SortMode,
+    group_by_process: bool,
+    /// lowercase program names
+    pinned_programs: BTreeSet<String>,
+    order: Vec<usize>,
+    /// `(pid, hwnd)` of the window info list `order` was sorted for
+    sorted_keys: Vec<(u32, usize)>,
+    /// `(pid, hwnd)` of the checked windows
+    selection: BTreeSet<(u32, usize)>,
+    /// position in `order` of the last clicked checkbox, where a shift-click range starts
+    selection_anchor: Option<usize>,
+    selection_query: String,
+    row_mode: RowMode,
+    /// pids of the process groups whose windows are hidden
+    collapsed: BTreeSet<u32>,
+    row_heights: HashMap<RowKind, f32>,
+    /// set when the list changed, the current window is scrolled into view on the next frame
+    scroll_to_current: bool,
+}
+
+impl WindowListView {
+    pub fn new() -> Self {
+        Self {
+            sort_mode: SortMode::Pid,
+            group_by_process: true,
+            pinned_programs: load_pinned_programs(),
+            order: Vec::new(),
+            sorted_keys: Vec::new(),
+            selection: BTreeSet::new(),
+            selection_anchor: None,
+            selection_query: String::new(),
+            row_mode: RowMode::Detailed,
+            collapsed: BTreeSet::new(),
+            row_heights: HashMap::new(),
+            scroll_to_current: true,
+        }
+    }
+
+    fn is_pinned(&self, window_info: &WindowInfo) -> bool {
+        self.pinned_programs
+            .contains(&program_name(&window_info.program_path).to_lowercase())
+    }
+
+    /// Sorts again, call it after the window info list changed.
+    pub fn update(&mut self, window_info_list: &[WindowInfo]) {
+        let rank_of = |hwnd_list: Vec<usize>| -> HashMap<usize, usize> {
+            hwnd_list
+                .into_iter()
+                .enumerate()
+                .map(|(rank, hwnd)| (hwnd, rank))
+                .collect()
+        };
+        let ranks = match self.sort_mode {
+            SortMode::ZOrder => rank_of(
+                enumerate_top_level_windows()
+                    .into_iter()
+                    .map(|hwnd| hwnd.0 as usize)
+                    .collect(),
+            ),
+            SortMode::RecentlyModified => rank_of(
+                window_modifier::modified_windows()
+                    .iter()
+                    .map(|modified_window| modified_window.window_info().hwnd.0 as usize)
+                    .collect(),
+            ),
+            _ => HashMap::new(),
+        };
+        let rank = |window_info: &WindowInfo| {
+            // child windows are ranked by their top-level window
+            let root = unsafe { GetAncestor(window_info.hwnd, GA_ROOT) };
+            ranks
+                .get(&(window_info.hwnd.0 as usize))
+                .or_else(|| ranks.get(&(root.0 as usize)))
+                .copied()
+                .unwrap_or(usize::MAX)
+        };
+        self.order = sorted_order(
+            window_info_list,
+            self.sort_mode,
+            self.group_by_process,
+            |window_info| self.is_pinned(window_info),
+            rank,
+        );
+        self.sorted_keys = window_info_list.iter().map(WindowInfo::key).collect();
+        let keys: BTreeSet<_> = self.sorted_keys.iter().copied().collect();
+        self.selection.retain(|key| keys.contains(key));
+        self.selection_anchor = None;
+        self.scroll_to_current = true;
+    }
+
+    /// the checked windows in the order they are shown
+    pub fn selected_windows(&self, window_info_list: &[WindowInfo]) -> Vec<WindowInfo> {
+        self.order
+            .iter()
+            .map(|&index| &window_info_list[index])
+            .filter(|window_info| self.selection.contains(&window_info.key()))
+            .cloned()
+            .collect()
+    }
+
+    /// Checks or unchecks the window at `position` in the shown order, with a shift-click also
+    /// every window from the last clicked one.
+    fn click_checkbox(
+        &mut self,
+        window_info_list: &[WindowInfo],
+        position: usize,
+        checked: bool,
+        shift: bool,
+    ) {
+        let range = match self.selection_anchor {
+            Some(anchor) if shift => anchor.min(position)..=anchor.max(position),
+            _ => position..=position,
+        };
+        self.order[range].iter().for_each(|&index| {
+            let key = window_info_list[index].key();
+            match checked {
+                true => self.selection.insert(key),
+                false => self.selection.remove(&key),
+            };
+        });
+        self.selection_anchor = Some(position);
+    }
+
+    /// The row selecting windows by a query, selecting all or none.
+    pub fn show_selection_ui(&mut self, ui: &mut egui::Ui, window_info_list: &[WindowInfo]) {
+        const FONT_SIZE: f32 = crate::app::App::FONT_SIZE;
+        let text = |text: &str| egui::RichText::new(text).size(FONT_SIZE);
+        ui.horizontal(|ui| {
+            let response = egui::TextEdit::singleline(&mut self.selection_query)
+                .hint_text(i18n::tr("window_list.selection_query_hint"))
+                .desired_width(160.0)
+                .ui(ui);
+            let submitted =
+                response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+            (egui::Button::new(text(i18n::tr("window_list.select_matching")))
+                .ui(ui)
+                .clicked()
+                || submitted)
+                .then(|| {
+                    self.selection
+                        .extend(matching_keys(window_info_list, &self.selection_query))
+                });
+            egui::Button::new(text(i18n::tr("window_list.select_all")))
+                .ui(ui)
+                .clicked()
+                .then(|| {
+                    self.selection
+                        .extend(window_info_list.iter().map(WindowInfo::key))
+                });
+            ui.add_enabled(
+                !self.selection.is_empty(),
+                egui::Button::new(text(i18n::tr("window_list.clear_selection"))),
+            )
+            .clicked()
+            .then(|| self.selection.clear());
+            egui::Label::new(text(&i18n::tr_format(
+                "window_list.selected_count",
+                &[("count", &self.selection.len())],
+            )))
+            .ui(ui);
+        });
+    }
+
+    /// A checkbox followed by the window, returns the new state of the checkbox if it was
+    /// clicked, and whether the select button was clicked.
+    fn show_item_ui(&self, ui: &mut egui::Ui, window_info: &WindowInfo) -> (Option<bool>, bool) {
+        let mut checked = self.selection.contains(&window_info.key());
+        let mut selected = false;
+        let clicked = ui
+            .horizontal(|ui| {
+                let clicked = egui::Checkbox::without_text(&mut checked).ui(ui).clicked();
+                selected = window_info.show_selectable_ui(ui);
+                lock::is_locked(window_info.key()).then(|| {
+                    egui::Label::new("🔒")
+                        .ui(ui)
+                        .on_hover_text(i18n::tr("lock.locked"))
+                });
+                clicked
+            })
+            .inner;
+        (clicked.then_some(checked), selected)
+    }
+
+    pub fn show_options_ui(&mut self, ui: &mut egui::Ui, window_info_list: &[WindowInfo]) {
+        const FONT_SIZE: f32 = crate::app::App::FONT_SIZE;
+        let text = |text: &str| egui::RichText::new(text).size(FONT_SIZE);
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            egui::Label::new(text(i18n::tr("window_list.sort_by"))).ui(ui);
+            egui::ComboBox::from_id_salt("window_list_sort_mode")
+                .selected_text(text(self.sort_mode.name()))
+                .show_ui(ui, |ui| {
+                    SortMode::ALL.into_iter().for_each(|sort_mode| {
+                        changed |= ui
+                            .selectable_value(
+                                &mut self.sort_mode,
+                                sort_mode,
+                                text(sort_mode.name()),
+                            )
+                            .clicked();
+                    });
+                });
+            changed |= egui::Checkbox::new(
+                &mut self.group_by_process,
+                text(i18n::tr("window_list.group_by_process")),
+            )
+            .ui(ui)
+            .changed();
+            egui::Label::new(text(i18n::tr("window_list.view"))).ui(ui);
+            egui::ComboBox::from_id_salt("window_list_row_mode")
+                .selected_text(text(self.row_mode.name()))
+                .show_ui(ui, |ui| {
+                    RowMode::ALL.into_iter().for_each(|row_mode| {
+                        changed |= ui
+                            .selectable_value(&mut self.row_mode, row_mode, text(row_mode.name()))
+                            .clicked();
+                    });
+                });
+        });
+        changed.then(|| self.update(window_info_list));
+    }
+
+    /// One line with the title, clicking it selects the window. Returns the new state of the
+    /// checkbox if it was clicked, and whether the window was selected.
+    fn show_compact_item_ui(
+        &self,
+        ui: &mut egui::Ui,
+        window_info: &WindowInfo,
+        current: bool,
+    ) -> (Option<bool>, bool) {
+        let mut checked = self.selection.contains(&window_info.key());
+        let mut selected = false;
+        let clicked = ui
+            .horizontal(|ui| {
+                let clicked = egui::Checkbox::without_text(&mut checked).ui(ui).clicked();
+                icon::show_icon(ui, window_info);
+                selected = ui
+                    .selectable_label(
+                        current,
+                        egui::RichText::new(&window_info.title).size(crate::app::App::FONT_SIZE),
+                    )
+                    .on_hover_text(format!(
+                        "PID: {}\n{}",
+                        window_info.pid, window_info.program_path
+                    ))
+                    .clicked();
+                lock::is_locked(window_info.key()).then(|| {
+                    egui::Label::new("🔒")
+                        .ui(ui)
+                        .on_hover_text(i18n::tr("lock.locked"))
+                });
+                clicked
+            })
+            .inner;
+        (clicked.then_some(checked), selected)
+    }
+
+    /// The header of a process group, returns the lowercase program name if its pin was
+    /// clicked, and whether it was clicked to collapse or expand the group.
+    fn show_process_ui(
+        &self,
+        ui: &mut egui::Ui,
+        first: &WindowInfo,
+        count: usize,
+        collapsed: bool,
+    ) -> (Option<String>, bool) {
+        let name = program_name(&first.program_path);
+        let pinned = self.is_pinned(first);
+        let mut toggled_pin = None;
+        let mut toggled = false;
+        ui.horizontal(|ui| {
+            egui::Button::new(egui::RichText::new(if pinned { "★" } else { "☆" }))
+                .frame(false)
+                .ui(ui)
+                .on_hover_text(if pinned {
+                    i18n::tr("window_list.unpin")
+                } else {
+                    i18n::tr("window_list.pin")
+                })
+                .clicked()
+                .then(|| toggled_pin = Some(name.to_lowercase()));
+            icon::show_icon(ui, first);
+            toggled = egui::Button::new(
+                egui::RichText::new(format!(
+                    "{} {}",
+                    if collapsed { "⏵" } else { "⏷" },
+                    i18n::tr_format(
+                        "window_list.process_header",
+                        &[("name", &name), ("pid", &first.pid), ("count", &count)],
+                    )
+                ))
+                .size(crate::app::App::FONT_SIZE),
+            )
+            .frame(false)
+            .ui(ui)
+            .clicked();
+        });
+        (toggled_pin, toggled)
+    }
+
+    /// the height of the rows of `kind`, estimated until one is drawn
+    fn row_height(&self, ui: &egui::Ui, kind: RowKind) -> f32 {
+        const FONT_SIZE: f32 = crate::app::App::FONT_SIZE;
+        self.row_heights.get(&kind).copied().unwrap_or_else(|| {
+            let line_height = ui.spacing().interact_size.y.max(icon::ICON_SIZE);
+            match kind {
+                RowKind::Process | RowKind::Compact => line_height,
+                // five lines in a frame
+                RowKind::Detailed => 5.0 * (FONT_SIZE + ui.spacing().item_spacing.y) + 12.0,
+            }
+        })
+    }
+
+    /// Draws only the rows in view, `current` is the key of the current window which is
+    /// scrolled into view after the list changed. Returns the index of the window selected.
+    pub fn show_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        window_info_list: &[WindowInfo],
+        current: Option<(u32, usize)>,
+    ) -> Option<usize> {
+        const BOTTOM_MARGIN: f32 = 10.0;
+        // a window replaced by another one keeps the length of the list
+        if !window_info_list
+            .iter()
+            .map(WindowInfo::key)
+            .eq(self.sorted_keys.iter().copied())
+        {
+            self.update(window_info_list);
+        }
+        let rows = rows(
+            &self.order,
+            window_info_list,
+            self.group_by_process,
+            &self.collapsed,
+        );
+        let heights: Vec<f32> = rows
+            .iter()
+            .map(|row| self.row_height(ui, row.kind(self.row_mode)))
+            .collect();
+        let tops = row_tops(&heights, ui.spacing().item_spacing.y);
+        let scroll_to = current
+            .filter(|_| std::mem::take(&mut self.scroll_to_current))
+            .and_then(|key| {
+                rows.iter().position(|row| {
+                    matches!(*row, Row::Window { index, .. } if window_info_list[index].key() == key)
+                })
+            });
+        let mut selected = None;
+        let mut toggled_pin = None;
+        let mut toggled_group = None;
+        // `(position, checked)` of a clicked checkbox
+        let mut checkbox_click = None;
+        let mut measured_heights = Vec::new();
+        egui::ScrollArea::both().show_viewport(ui, |ui, viewport| {
+            let height = tops
+                .last()
+                .zip(heights.last())
+                .map_or(0.0, |(top, height)| top + height);
+            ui.set_min_height(height + BOTTOM_MARGIN);
+            let origin = ui.max_rect().min;
+            let width = ui.available_width();
+            let row_rect = |row: usize| {
+                egui::Rect::from_min_size(
+                    origin + egui::vec2(0.0, tops[row]),
+                    egui::vec2(width, heights[row]),
+                )
+            };
+            scroll_to.map(|row| ui.scroll_to_rect(row_rect(row), None));
+            visible_rows(&tops, viewport.y_range()).for_each(|row| {
+                let builder = egui::UiBuilder::new().max_rect(row_rect(row)).id_salt((
+                    matches!(rows[row], Row::Process { .. }),
+                    window_info_list[rows[row].index()].key(),
+                ));
+                let response = ui.scope_builder(builder, |ui| match rows[row] {
+                    Row::Process {
+                        index,
+                        count,
+                        collapsed,
+                    } => {
+                        let first = &window_info_list[index];
+                        let (pin, toggled) = self.show_process_ui(ui, first, count, collapsed);
+                        pin.map(|name| toggled_pin = Some(name));
+                        toggled.then(|| toggled_group = Some(first.pid));
+                    }
+                    Row::Window {
+                        position,
+                        index,
+                        grouped,
+                    } => {
+                        let window_info = &window_info_list[index];
+                        let (checked, clicked) = ui
+                            .horizontal(|ui| {
+                                grouped.then(|| ui.add_space(ui.spacing().indent));
+                                match self.row_mode {
+                                    RowMode::Compact => self.show_compact_item_ui(
+                                        ui,
+                                        window_info,
+                                        current == Some(window_info.key()),
+                                    ),
+                                    RowMode::Detailed => self.show_item_ui(ui, window_info),
+                                }
+                            })
+                            .inner;
+                        checked.map(|checked| checkbox_click = Some((position, checked)));
+                        clicked.then(|| selected = Some(index));
+                    }
+                });
+                measured_heights.push((
+                    rows[row].kind(self.row_mode),
+                    response.response.rect.height(),
+                ));
+            });
+        });
+        measured_heights.into_iter().for_each(|(kind, height)| {
+            if (self.row_height(ui, kind) - height).abs() > 0.5 {
+                self.row_heights.insert(kind, height);
+                ui.ctx().request_repaint();
+            }
+        });
+        if let Some((position, checked)) = checkbox_click {
+            let shift = ui.input(|input| input.modifiers.shift);
+            self.click_checkbox(window_info_list, position, checked, shift);
+        }
+        if let Some(pid) = toggled_group
+            && !self.collapsed.remove(&pid)
+        {
+            self.collapsed.insert(pid);
+        }
+        if let Some(name) = toggled_pin {
+            if !self.pinned_programs.remove(&name) {
+                self.pinned_programs.insert(name);
+            }
+            save_pinned_programs(&self.pinned_programs);
+            self.update(window_info_list);
+        }
+        selected
+    }
+}
+
+/// The rows of the windows in `order`, each process group after a header if `group_by_process`
+/// is set, without the windows of the `collapsed` groups.
+fn rows(
+    order: &[usize],
+    window_info_list: &[WindowInfo],
+    group_by_process: bool,
+    collapsed: &BTreeSet<u32>,
+) -> Vec<Row> {
+    let entries: Vec<(usize, usize)> = order.iter().copied().enumerate().collect();
+    if !group_by_process {
+        return entries
+            .into_iter()
+            .map(|(position, index)| Row::Window {
+                position,
+                index,
+                grouped: false,
+            })
+            .collect();
+    }
+    entries
+        .chunk_by(|&(_, a), &(_, b)| window_info_list[a].pid == window_info_list[b].pid)
+        .flat_map(|group| {
+            let index = group[0].1;
+            let collapsed = collapsed.contains(&window_info_list[index].pid);
+            let header = Row::Process {
+                index,
+                count: group.len(),
+                collapsed,
+            };
+            let windows = group
+                .iter()
+                .filter(move |_| !collapsed)
+                .map(|&(position, index)| Row::Window {
+                    position,
+                    index,
+                    grouped: true,
+                });
+            std::iter::once(header).chain(windows)
+        })
+        .collect()
+}
+
+/// the top of every row in the content, rows are `spacing` apart
+fn row_tops(heights: &[f32], spacing: f32) -> Vec<f32> {
+    heights
+        .iter()
+        .scan(0.0, |top, &height| {
+            let row_top = *top;
+            *top += height + spacing;
+            Some(row_top)
+        })
+        .collect()
+}
+
+/// The rows overlapping the `viewport` of the content, including the one starting above it.
+fn visible_rows(tops: &[f32], viewport: egui::Rangef) -> Range<usize> {
+    let start = tops
+        .partition_point(|&top| top <= viewport.min)
+        .saturating_sub(1);
+    let end = tops.partition_point(|&top| top < viewport.max);
+    start..end.max(start)
+}
+
+/// Pinned windows first, then by `sort_mode`; windows of the same process are kept together
+/// at the position of their first window if `group_by_process` is set.
+fn sorted_order(
+    window_info_list: &[WindowInfo],
+    sort_mode: SortMode,
+    group_by_process: bool,
+    is_pinned: impl Fn(&WindowInfo) -> bool,
+    rank: impl Fn(&WindowInfo) -> usize,
+) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..window_info_list.len()).collect();
+    let sort_key = |window_info: &WindowInfo| match sort_mode {
+        SortMode::Title => (0, window_info.title.to_lowercase()),
+        SortMode::Path => (0, window_info.program_path.to_lowercase()),
+        SortMode::Pid => (0, String::new()),
+        SortMode::ZOrder | SortMode::RecentlyModified => (rank(window_info), String::new()),
+    };
+    order.sort_by_cached_key(|&index| {
+        let window_info = &window_info_list[index];
+        let (rank, text) = sort_key(window_info);
+        (!is_pinned(window_info), rank, text, window_info.pid)
+    });
+    if group_by_process {
+        let mut group_position: HashMap<u32, usize> = HashMap::new();
+        order.iter().enumerate().for_each(|(position, &index)| {
+            group_position
+                .entry(window_info_list[index].pid)
+                .or_insert(position);
+        });
+        // stable, so windows keep their order inside a group
+        order.sort_by_key(|&index| group_position[&window_info_list[index].pid]);
+    }
+    order
+}
+
+/// `(pid, hwnd)` of the windows whose title or program path contains `query`, none for an
+/// empty query
+fn matching_keys(window_info_list: &[WindowInfo], query: &str) -> Vec<(u32, usize)> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    window_info_list
+        .iter()
+        .filter(|window_info| window_info.matches(query))
+        .map(WindowInfo::key)
+        .collect()
+}
+
+fn load_pinned_programs() -> BTreeSet<String> {
+    std::fs::read_to_string(utils::app_data_dir().join(PINNED_PROCESSES_FILE_NAME))
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn save_pinned_programs(pinned_programs: &BTreeSet<String>) {
+    let _ = serde_json::to_string_pretty(pinned_programs)
+        .map(|text| std::fs::write(utils::app_data_dir().join(PINNED_PROCESSES_FILE_NAME), text));
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[allow(dead_code)]
+    fn window_info(pid: u32, hwnd: usize, title: &str, program_path: &str) -> WindowInfo {
+        WindowInfo {
+            pid,
+            hwnd: windows::Win32::Foundation::HWND(hwnd as _),
+            title: title.to_string(),
+            program_path: program_path.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_matching_keys() {
+        let window_info_list = [
+            window_info(1, 10, "Dashboard - Grafana", r"C:\chrome.exe"),
+            window_info(2, 20, "notes.txt", r"C:\notepad.exe"),
+            window_info(3, 30, "dashboard", r"C:\app.exe"),
+        ];
+        assert_eq!(
+            matching_keys(&window_info_list, " DASHBOARD "),
+            [(1, 10), (3, 30)]
+        );
+        assert_eq!(matching_keys(&window_info_list, "notepad"), [(2, 20)]);
+        assert!(matching_keys(&window_info_list, "").is_empty());
+    }
+
+    #[test]
+    fn test_sorted_order() {
+        let window_info_list = [
+            window_info(1, 10, "b", r"C:\a.exe"),
+            window_info(2, 20, "a", r"C:\b.exe"),
+            window_info(1, 11, "c", r"C:\a.exe"),
+            window_info(3, 30, "d", r"C:\pinned.exe"),
+        ];
+        let is_pinned =
+            |window_info: &WindowInfo| program_name(&window_info.program_path) == "pinned.exe";
+        let rank = |window_info: &WindowInfo| window_info.hwnd.0 as usize;
+        assert_eq!(
+            sorted_order(&window_info_list, SortMode::Title, false, is_pinned, rank),
+            [3, 1, 0, 2]
+        );
+        assert_eq!(
+            sorted_order(&window_info_list, SortMode::Title, true, is_pinned, rank),
+            [3, 1, 0, 2]
+        );
+        assert_eq!(
+            sorted_order(&window_info_list, SortMode::Pid, true, |_| false, rank),
+            [0, 2, 1, 3]
+        );
+        let reversed_rank = |window_info: &WindowInfo| 100 - window_info.hwnd.0 as usize;
+        assert_eq!(
+            sorted_order(
+                &window_info_list,
+                SortMode::ZOrder,
+                true,
+                |_| false,
+                reversed_rank
+            ),
+            [3, 1, 2, 0]
+        );
+    }
+
+    #[test]
+    fn test_rows() {
+        let window_info_list = [
+            window_info(1, 10, "a", r"C:\a.exe"),
+            window_info(1, 11, "b", r"C:\a.exe"),
+            window_info(2, 20, "c", r"C:\b.exe"),
+        ];
+        let window = |position, index| Row::Window {
+            position,
+            index,
+            grouped: true,
+        };
+        assert_eq!(
+            rows(&[0, 1, 2], &window_info_list, true, &BTreeSet::new()),
+            [
+                Row::Process {
+                    index: 0,
+                    count: 2,
+                    collapsed: false
+                },
+                window(0, 0),
+                window(1, 1),
+                Row::Process {
+                    index: 2,
+                    count: 1,
+                    collapsed: false
+                },
+                window(2, 2),
+            ]
+        );
+        assert_eq!(
+            rows(&[0, 1, 2], &window_info_list, true, &BTreeSet::from([1])),
+            [
+                Row::Process {
+                    index: 0,
+                    count: 2,
+                    collapsed: true
+                },
+                Row::Process {
+                    index: 2,
+                    count: 1,
+                    collapsed: false
+                },
+                window(2, 2),
+            ]
+        );
+        assert_eq!(
+            rows(&[2, 0, 1], &window_info_list, false, &BTreeSet::new()).len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_visible_rows() {
+        let tops = row_tops(&[20.0, 20.0, 100.0, 20.0], 10.0);
+        assert_eq!(tops, [0.0, 30.0, 60.0, 170.0]);
+        assert_eq!(visible_rows(&tops, egui::Rangef::new(0.0, 50.0)), 0..2);
+        // the card starting above the viewport is partly visible
+        assert_eq!(visible_rows(&tops, egui::Rangef::new(100.0, 150.0)), 2..3);
+        assert_eq!(visible_rows(&tops, egui::Rangef::new(40.0, 500.0)), 1..4);
+        assert_eq!(visible_rows(&[], egui::Rangef::new(0.0, 100.0)), 0..0);
+    }
+}
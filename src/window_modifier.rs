@@ -1,58 +1,255 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    sync::{Mutex, MutexGuard},
+};
+
 use egui::Widget;
-use windows::{
-    Win32::{
-        Foundation::{CloseHandle, HWND, LPARAM},
-        Graphics::Gdi::{
-            GetMonitorInfoW, MONITOR_DEFAULTTONEAREST, MONITORINFO, MonitorFromWindow,
-        },
-        System::{
-            ProcessStatus::GetModuleFileNameExW,
-            Threading::{OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_VM_READ},
-        },
-        UI::WindowsAndMessaging::{
-            AdjustWindowRectEx, EnumChildWindows, GWL_EXSTYLE, GWL_STYLE, GetClientRect, GetMenu,
-            GetWindowLongPtrW, GetWindowRect, GetWindowTextW, GetWindowThreadProcessId,
-            HWND_NOTOPMOST, HWND_TOPMOST, IsWindow, IsWindowVisible, SWP_ASYNCWINDOWPOS,
-            SWP_NOMOVE, SWP_NOSIZE, SWP_NOZORDER, SetWindowLongPtrW, SetWindowPos, WINDOW_EX_STYLE,
-            WINDOW_STYLE, WS_EX_APPWINDOW, WS_EX_WINDOWEDGE, WS_MAXIMIZEBOX, WS_MINIMIZEBOX,
-            WS_OVERLAPPEDWINDOW, WS_POPUP, WS_THICKFRAME,
-        },
+use windows::Win32::{
+    Foundation::{HWND, POINT, SetLastError, WIN32_ERROR},
+    Graphics::Dwm::{DWMWA_EXTENDED_FRAME_BOUNDS, DwmGetWindowAttribute},
+    Graphics::Gdi::{
+        GetMonitorInfoW, MONITOR_DEFAULTTONEAREST, MONITORINFO, MonitorFromWindow, ScreenToClient,
+    },
+    UI::WindowsAndMessaging::{
+        AdjustWindowRectEx, GWL_EXSTYLE, GWL_STYLE, GetClassNameW, GetClientRect, GetMenu,
+        GetParent, GetWindowLongPtrW, GetWindowRect, GetWindowTextW, GetWindowThreadProcessId,
+        HWND_NOTOPMOST, HWND_TOPMOST, IsIconic, IsWindow, SWP_FRAMECHANGED, SWP_NOMOVE, SWP_NOSIZE,
+        SWP_NOZORDER, SetWindowLongPtrW, SetWindowPos, WINDOW_EX_STYLE, WINDOW_LONG_PTR_INDEX,
+        WINDOW_STYLE, WS_CHILD, WS_DISABLED, WS_EX_APPWINDOW, WS_EX_TOPMOST, WS_EX_WINDOWEDGE,
+        WS_MAXIMIZE, WS_MAXIMIZEBOX, WS_MINIMIZE, WS_MINIMIZEBOX, WS_OVERLAPPEDWINDOW, WS_POPUP,
+        WS_THICKFRAME, WS_VISIBLE,
     },
-    core::BOOL,
+};
+
+use crate::{
+    enumeration::{self, EnumerationEvent, EnumerationWorker},
+    error::{Error, Result},
+    i18n, icon, lock, logging, monitor, settings, verification,
 };
 
 #[derive(Debug)]
 pub struct WindowModifier {
     window_info_list: Vec<WindowInfo>,
+    /// `(pid, hwnd)` of the windows in the list
+    keys: HashSet<(u32, usize)>,
+    worker: EnumerationWorker,
 }
 
 impl WindowModifier {
-    pub fn new() -> Self {
-        let window_info_list = Vec::with_capacity(1024);
-        Self { window_info_list }
+    pub fn new(ctx: egui::Context) -> Self {
+        Self {
+            window_info_list: Vec::with_capacity(1024),
+            keys: HashSet::with_capacity(1024),
+            worker: EnumerationWorker::spawn(ctx),
+        }
     }
 
+    /// Re-enumerates the windows on this thread, for when the list is used right away.
     pub fn update_window_info_list(&mut self) {
-        enumerate_windows(&mut self.window_info_list);
+        self.remove_closed_windows();
+        let mut windows = Vec::new();
+        enumeration::enumerate(&self.keys, |batch| windows.extend(batch));
+        self.add_windows(windows);
+    }
+
+    /// Re-enumerates the windows on the worker thread, `poll_window_info_list` adds the new ones
+    /// as they are found.
+    pub fn request_window_info_list(&mut self) {
+        self.remove_closed_windows();
+        self.worker.request(self.keys.clone());
+    }
+
+    /// Adds the windows the worker found since the last call, returns `true` if the list
+    /// changed.
+    pub fn poll_window_info_list(&mut self) -> bool {
+        let mut windows = Vec::new();
+        self.worker.events().for_each(|event| match event {
+            EnumerationEvent::Windows(batch) => windows.extend(batch),
+            EnumerationEvent::Finished { count, elapsed } => tracing::debug!(
+                count = self.window_info_list.len(),
+                new = count,
+                ?elapsed,
+                "window list refreshed"
+            ),
+        });
+        let changed = !windows.is_empty();
+        self.add_windows(windows);
+        changed
+    }
+
+    /// Drops the closed windows from the list and from the remembered modifications.
+    fn remove_closed_windows(&mut self) {
+        self.window_info_list
+            .retain(|window_info| window_info.is_valid());
+        self.keys = self.window_info_list.iter().map(WindowInfo::key).collect();
+        original_status_storage().retain(|modified_window| {
+            is_valid_window(modified_window.pid, HWND(modified_window.hwnd as _))
+        });
+        borderless_fullscreen_storage()
+            .retain(|&(pid, hwnd), _| is_valid_window(pid, HWND(hwnd as _)));
+    }
+
+    fn add_windows(&mut self, windows: Vec<enumeration::EnumeratedWindow>) {
+        windows.into_iter().for_each(|window| {
+            if self.keys.insert((window.pid, window.hwnd)) {
+                self.window_info_list.push(window.into_window_info());
+            }
+        });
+        self.window_info_list.sort_by_key(|x: &WindowInfo| x.pid);
     }
 
     pub fn window_info_list(&self) -> &[WindowInfo] {
         &self.window_info_list
     }
 
-    pub fn window_info_list_mut(&mut self) -> &mut [WindowInfo] {
-        &mut self.window_info_list
+    /// Adds a window that enumeration skipped (e.g. a child window), returns its index.
+    pub fn insert_window_info(&mut self, window_info: WindowInfo) -> usize {
+        let (pid, hwnd) = (window_info.pid, window_info.hwnd);
+        let position = |window_info_list: &[WindowInfo]| {
+            window_info_list
+                .iter()
+                .position(|window_info| window_info.pid == pid && window_info.hwnd == hwnd)
+        };
+        if let Some(index) = position(&self.window_info_list) {
+            return index;
+        }
+        self.keys.insert(window_info.key());
+        self.window_info_list.push(window_info);
+        self.window_info_list.sort_by_key(|x: &WindowInfo| x.pid);
+        position(&self.window_info_list).unwrap()
+    }
+}
+
+/// Original status of the windows in borderless fullscreen, keyed by `(pid, hwnd)`.
+///
+/// Kept outside of `WindowInfo` so that every `WindowInfo` of the same window, e.g. the one
+/// built for the foreground window by a global hotkey, sees the same state.
+static BORDERLESS_FULLSCREEN_STORAGE: Mutex<BTreeMap<(u32, usize), WindowStatus>> =
+    Mutex::new(BTreeMap::new());
+
+/// Status of the modified windows before their first modification, the most recently modified
+/// last.
+static ORIGINAL_STATUS_STORAGE: Mutex<Vec<ModifiedWindow>> = Mutex::new(Vec::new());
+
+/// The windows are restored after a panic, which may have poisoned the storages.
+fn borderless_fullscreen_storage() -> MutexGuard<'static, BTreeMap<(u32, usize), WindowStatus>> {
+    BORDERLESS_FULLSCREEN_STORAGE
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+}
+
+fn original_status_storage() -> MutexGuard<'static, Vec<ModifiedWindow>> {
+    ORIGINAL_STATUS_STORAGE
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+}
+
+/// What a modification changed, restored separately on exit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ModificationKind {
+    /// size and position
+    Geometry,
+    TopMost,
+    /// maximizable, minimizable and resizable
+    Styles,
+    BorderlessFullscreen,
+}
+
+impl ModificationKind {
+    pub const ALL: [Self; 4] = [
+        Self::Geometry,
+        Self::TopMost,
+        Self::Styles,
+        Self::BorderlessFullscreen,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Geometry => i18n::tr("modification.geometry"),
+            Self::TopMost => i18n::tr("modification.top_most"),
+            Self::Styles => i18n::tr("modification.styles"),
+            Self::BorderlessFullscreen => i18n::tr("modification.borderless_fullscreen"),
+        }
     }
 }
 
+/// Flags of the style that the window or the system change on their own.
+const TRANSIENT_STYLES: WINDOW_STYLE =
+    WINDOW_STYLE(WS_VISIBLE.0 | WS_MINIMIZE.0 | WS_MAXIMIZE.0 | WS_DISABLED.0);
+
 #[derive(Debug, Clone)]
-struct WindowStatus {
+pub struct WindowStatus {
     width: i32,
     height: i32,
     x: i32,
     y: i32,
     style: WINDOW_STYLE,
     ex_style: WINDOW_EX_STYLE,
+    top_most: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ModifiedWindow {
+    pub pid: u32,
+    hwnd: usize,
+    pub title: String,
+    pub program_path: String,
+    /// the kinds of modification not restored yet
+    pub kinds: BTreeSet<ModificationKind>,
+    original_status: WindowStatus,
+}
+
+impl ModifiedWindow {
+    pub fn window_info(&self) -> WindowInfo {
+        WindowInfo {
+            pid: self.pid,
+            hwnd: HWND(self.hwnd as _),
+            title: self.title.clone(),
+            program_path: self.program_path.clone(),
+        }
+    }
+}
+
+/// the modified windows that still exist, the most recently modified first
+pub fn modified_windows() -> Vec<ModifiedWindow> {
+    original_status_storage()
+        .iter()
+        .rev()
+        .filter(|modified_window| {
+            is_valid_window(modified_window.pid, HWND(modified_window.hwnd as _))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Best-effort restore of every modified window, returns the windows that failed.
+pub fn restore_all_modified_windows() -> Vec<(WindowInfo, Error)> {
+    modified_windows()
+        .into_iter()
+        .filter_map(|modified_window| {
+            let window_info = modified_window.window_info();
+            window_info
+                .restore_original_status()
+                .err()
+                .map(|err| (window_info, err))
+        })
+        .collect()
+}
+
+/// Best-effort restore of the `kinds` of modification of every modified window, returns the
+/// windows that failed.
+pub fn restore_modified_windows(kinds: &BTreeSet<ModificationKind>) -> Vec<(WindowInfo, Error)> {
+    modified_windows()
+        .into_iter()
+        .filter(|modified_window| !modified_window.kinds.is_disjoint(kinds))
+        .filter_map(|modified_window| {
+            let window_info = modified_window.window_info();
+            window_info
+                .restore_modifications(kinds)
+                .err()
+                .map(|err| (window_info, err))
+        })
+        .collect()
 }
 
 #[derive(Debug, Clone)]
@@ -61,209 +258,631 @@ pub struct WindowInfo {
     pub hwnd: HWND,
     pub title: String,
     pub program_path: String,
-    borderless_fullscreen_storage: Option<Box<WindowStatus>>,
 }
 
 impl WindowInfo {
     pub fn is_valid(&self) -> bool {
-        let is_window = unsafe { IsWindow(Some(self.hwnd)) }.as_bool();
-        if !is_window {
-            return false;
+        is_valid_window(self.pid, self.hwnd)
+    }
+
+    /// `true` if the title or the program path contains `text`, case-insensitive
+    pub fn matches(&self, text: &str) -> bool {
+        let text = text.to_lowercase();
+        self.title.to_lowercase().contains(&text)
+            || self.program_path.to_lowercase().contains(&text)
+    }
+
+    /// `(pid, hwnd)`, identifies the window across refreshes of the window list
+    pub fn key(&self) -> (u32, usize) {
+        (self.pid, self.hwnd.0 as usize)
+    }
+
+    /// Builds the info of an arbitrary window, including untitled and child windows that
+    /// `enumerate_windows` skips.
+    pub fn from_hwnd(hwnd: HWND) -> Option<Self> {
+        if !unsafe { IsWindow(Some(hwnd)) }.as_bool() {
+            return None;
         }
-        let pid = {
-            let mut pid = 0;
-            unsafe { GetWindowThreadProcessId(self.hwnd, Some(&mut pid)) };
-            pid
-        };
-        pid == self.pid
+        let pid = window_pid(hwnd);
+        if pid == 0 {
+            return None;
+        }
+        Some(Self {
+            pid,
+            hwnd,
+            title: window_title(hwnd),
+            program_path: enumeration::program_path(pid).unwrap_or_default(),
+        })
     }
 
-    pub fn get_window_rect(&self) -> windows::core::Result<windows::Win32::Foundation::RECT> {
+    /// whether the window is minimized to the taskbar
+    pub fn is_minimized(&self) -> bool {
+        unsafe { IsIconic(self.hwnd) }.as_bool()
+    }
+
+    /// Child windows are positioned in the client coordinates of their parent.
+    pub fn is_child(&self) -> bool {
+        let style = WINDOW_STYLE(unsafe { GetWindowLongPtrW(self.hwnd, GWL_STYLE) } as _);
+        style.contains(WS_CHILD)
+    }
+
+    pub fn get_window_rect(&self) -> Result<windows::Win32::Foundation::RECT> {
         let mut rect = Default::default();
         unsafe { GetWindowRect(self.hwnd, &mut rect) }?;
         Ok(rect)
     }
 
+    /// `[x, y, width, height]` of the visible frame in screen coordinates, without the invisible
+    /// resize borders DWM adds to the window rect. The window rect if DWM has no frame for it.
+    pub fn get_frame_rect(&self) -> Result<[i32; 4]> {
+        let mut rect = windows::Win32::Foundation::RECT::default();
+        let result = unsafe {
+            DwmGetWindowAttribute(
+                self.hwnd,
+                DWMWA_EXTENDED_FRAME_BOUNDS,
+                (&raw mut rect).cast(),
+                size_of_val(&rect) as _,
+            )
+        };
+        match result {
+            Ok(()) => Ok(rect_to_array(rect)),
+            // e.g. child windows
+            Err(_) => self.get_window_rect().map(rect_to_array),
+        }
+    }
+
     /// returns `[width, height]`
-    pub fn get_size(&self) -> windows::core::Result<[i32; 2]> {
+    pub fn get_size(&self) -> Result<[i32; 2]> {
         let rect = self.get_window_rect()?;
         Ok([rect.right - rect.left, rect.bottom - rect.top])
     }
 
     /// returns `[width, height]`
-    pub fn get_inner_size(&self) -> windows::core::Result<[i32; 2]> {
+    pub fn get_inner_size(&self) -> Result<[i32; 2]> {
         let mut rect = Default::default();
         unsafe { GetClientRect(self.hwnd, &mut rect) }?;
         Ok([rect.right - rect.left, rect.bottom - rect.top])
     }
 
-    /// returns `[x, y]`
-    pub fn get_position(&self) -> windows::core::Result<[i32; 2]> {
+    /// returns `[x, y]`, relative to the parent's client area for child windows
+    pub fn get_position(&self) -> Result<[i32; 2]> {
         let rect = self.get_window_rect()?;
-        Ok([rect.left, rect.top])
+        let mut point = POINT {
+            x: rect.left,
+            y: rect.top,
+        };
+        if self.is_child() {
+            let parent = unsafe { GetParent(self.hwnd) }?;
+            unsafe { ScreenToClient(parent, &mut point) }.ok()?;
+        }
+        Ok([point.x, point.y])
     }
 
-    /// `size: [width, height]`
-    pub fn resize(&self, size: [i32; 2]) -> windows::core::Result<()> {
-        let [width, height] = size;
-        const DUMMY: i32 = 0;
-        unsafe {
-            SetWindowPos(
-                self.hwnd,
-                None,
-                DUMMY,
-                DUMMY,
-                width,
-                height,
-                SWP_ASYNCWINDOWPOS | SWP_NOZORDER | SWP_NOMOVE,
-            )
+    /// Runs a modification and logs it with the window rect before and after it, and the error
+    /// code if it failed. The rect after an asynchronous `SetWindowPos` may not be updated yet.
+    fn logged(
+        &self,
+        operation: std::fmt::Arguments,
+        modify: impl FnOnce() -> Result<()>,
+    ) -> Result<()> {
+        let rect = || self.get_window_rect().ok().map(rect_to_array);
+        let before = rect();
+        let result = modify();
+        let after = rect();
+        let hwnd = format!("{:#x}", self.hwnd.0 as usize);
+        match &result {
+            Ok(()) => tracing::info!(
+                hwnd,
+                pid = self.pid,
+                title = self.title,
+                ?before,
+                ?after,
+                "{}",
+                operation
+            ),
+            Err(err) => tracing::warn!(
+                hwnd,
+                pid = self.pid,
+                title = self.title,
+                ?before,
+                ?after,
+                code = err.code(),
+                os_error = err.os_error().map(logging::error_code),
+                error = err.message(),
+                "{} failed",
+                operation
+            ),
         }
+        result
     }
 
     /// `size: [width, height]`
-    pub fn resize_inner(&self, size: [i32; 2]) -> windows::core::Result<()> {
+    pub fn resize(&self, size: [i32; 2]) -> Result<()> {
+        self.logged(format_args!("resize to {:?}", size), || {
+            self.remember_original_status(ModificationKind::Geometry);
+            self.set_size(size).inspect(|()| {
+                self.update_lock(ModificationKind::Geometry, |status| {
+                    [status.width, status.height] = size
+                })
+            })
+        })
+    }
+
+    /// [`Self::resize`] without recording a geometry modification, for modifications that
+    /// restore the geometry themselves
+    fn set_size(&self, size: [i32; 2]) -> Result<()> {
         let [width, height] = size;
         const DUMMY: i32 = 0;
-        let style = WINDOW_STYLE(unsafe { GetWindowLongPtrW(self.hwnd, GWL_STYLE) } as _);
-        let ex_style = WINDOW_EX_STYLE(unsafe { GetWindowLongPtrW(self.hwnd, GWL_EXSTYLE) } as _);
-        let has_menu = !unsafe { GetMenu(self.hwnd) }.is_invalid();
-        let mut rect = windows::Win32::Foundation::RECT {
-            left: 0,
-            top: 0,
-            right: width,
-            bottom: height,
-        };
-        unsafe { AdjustWindowRectEx(&mut rect, style, has_menu, ex_style) }?;
-        let width = rect.right - rect.left;
-        let height = rect.bottom - rect.top;
-        unsafe {
-            SetWindowPos(
-                self.hwnd,
-                None,
-                DUMMY,
-                DUMMY,
-                width,
-                height,
-                SWP_ASYNCWINDOWPOS | SWP_NOZORDER | SWP_NOMOVE,
+        self.verified(
+            || unsafe {
+                SetWindowPos(
+                    self.hwnd,
+                    None,
+                    DUMMY,
+                    DUMMY,
+                    width,
+                    height,
+                    verification::async_flag() | SWP_NOZORDER | SWP_NOMOVE,
+                )
+            },
+            || verification::verify_size(self, "app.window_size", size, self.get_size()?),
+        )
+    }
+
+    /// `size: [width, height]`
+    pub fn resize_inner(&self, size: [i32; 2]) -> Result<()> {
+        self.logged(format_args!("resize inner to {:?}", size), || {
+            self.remember_original_status(ModificationKind::Geometry);
+            let [width, height] = size;
+            const DUMMY: i32 = 0;
+            let style = WINDOW_STYLE(unsafe { GetWindowLongPtrW(self.hwnd, GWL_STYLE) } as _);
+            let ex_style =
+                WINDOW_EX_STYLE(unsafe { GetWindowLongPtrW(self.hwnd, GWL_EXSTYLE) } as _);
+            let has_menu = !unsafe { GetMenu(self.hwnd) }.is_invalid();
+            let mut rect = windows::Win32::Foundation::RECT {
+                left: 0,
+                top: 0,
+                right: width,
+                bottom: height,
+            };
+            unsafe { AdjustWindowRectEx(&mut rect, style, has_menu, ex_style) }?;
+            let width = rect.right - rect.left;
+            let height = rect.bottom - rect.top;
+            self.verified(
+                || unsafe {
+                    SetWindowPos(
+                        self.hwnd,
+                        None,
+                        DUMMY,
+                        DUMMY,
+                        width,
+                        height,
+                        verification::async_flag() | SWP_NOZORDER | SWP_NOMOVE,
+                    )
+                },
+                || {
+                    let inner_size = self.get_inner_size()?;
+                    verification::verify_size(self, "app.window_inner_size", size, inner_size)
+                },
             )
+            .inspect(|()| {
+                self.update_lock(ModificationKind::Geometry, |status| {
+                    [status.width, status.height] = [width, height]
+                })
+            })
+        })
+    }
+
+    /// `position`, moved to keep the title bar reachable if enabled in the settings
+    fn clamped_position(&self, position: [i32; 2]) -> [i32; 2] {
+        if !settings::settings().keep_title_bar_on_screen || self.is_child() {
+            return position;
         }
+        self.get_size()
+            .map(|size| {
+                monitor::keep_title_bar_reachable(position, size, &monitor::enumerate_monitors())
+            })
+            .unwrap_or(position)
     }
 
     /// `position: [x, y]`
-    pub fn move_to(&self, position: [i32; 2]) -> windows::core::Result<()> {
-        let [x, y] = position;
+    pub fn move_to(&self, position: [i32; 2]) -> Result<()> {
+        self.logged(format_args!("move to {:?}", position), || {
+            self.remember_original_status(ModificationKind::Geometry);
+            self.set_position(position).map(|[x, y]| {
+                self.update_lock(ModificationKind::Geometry, |status| {
+                    [status.x, status.y] = [x, y]
+                })
+            })
+        })
+    }
+
+    /// [`Self::move_to`] without recording a geometry modification, returns the position the
+    /// window was moved to
+    fn set_position(&self, position: [i32; 2]) -> Result<[i32; 2]> {
+        let [x, y] = self.clamped_position(position);
         const DUMMY: i32 = 0;
-        unsafe {
-            SetWindowPos(
-                self.hwnd,
-                None,
-                x,
-                y,
-                DUMMY,
-                DUMMY,
-                SWP_ASYNCWINDOWPOS | SWP_NOZORDER | SWP_NOSIZE,
-            )
-        }
+        self.verified(
+            || unsafe {
+                SetWindowPos(
+                    self.hwnd,
+                    None,
+                    x,
+                    y,
+                    DUMMY,
+                    DUMMY,
+                    verification::async_flag() | SWP_NOZORDER | SWP_NOSIZE,
+                )
+            },
+            || verification::verify_position(self, [x, y], self.get_position()?),
+        )
+        .map(|()| [x, y])
     }
 
-    pub fn set_top_most(&self, top_most: bool) -> windows::core::Result<()> {
-        unsafe {
-            SetWindowPos(
-                self.hwnd,
-                Some(if top_most {
-                    HWND_TOPMOST
-                } else {
-                    HWND_NOTOPMOST
-                }),
-                0,
-                0,
-                0,
-                0,
-                SWP_NOMOVE | SWP_NOSIZE | SWP_ASYNCWINDOWPOS,
+    pub fn set_top_most(&self, top_most: bool) -> Result<()> {
+        self.logged(format_args!("set top most {}", top_most), || {
+            self.remember_original_status(ModificationKind::TopMost);
+            self.verified(
+                || unsafe {
+                    SetWindowPos(
+                        self.hwnd,
+                        Some(if top_most {
+                            HWND_TOPMOST
+                        } else {
+                            HWND_NOTOPMOST
+                        }),
+                        0,
+                        0,
+                        0,
+                        0,
+                        SWP_NOMOVE | SWP_NOSIZE | verification::async_flag(),
+                    )
+                },
+                || verification::verify_switch(self, "app.top_most", top_most, self.is_top_most()),
             )
-        }
+            .inspect(|()| {
+                self.update_lock(ModificationKind::TopMost, |status| {
+                    status.top_most = top_most
+                })
+            })
+        })
     }
 
-    pub fn set_maximizable(&self, maximizable: bool) {
-        let style = WINDOW_STYLE(unsafe { GetWindowLongPtrW(self.hwnd, GWL_STYLE) } as _);
-        let new_style = if maximizable {
-            style | WS_MAXIMIZEBOX
-        } else {
-            style & !WS_MAXIMIZEBOX
-        };
-        unsafe { SetWindowLongPtrW(self.hwnd, GWL_STYLE, new_style.0 as _) };
+    /// Runs `modify`, then `verify` if [`verification::enabled`]. Access denied by UIPI is
+    /// reported as such.
+    fn verified(
+        &self,
+        modify: impl FnOnce() -> windows::core::Result<()>,
+        verify: impl FnOnce() -> Result<()>,
+    ) -> Result<()> {
+        modify()
+            .map_err(Error::from)
+            .and_then(|()| match verification::enabled() {
+                true => verify(),
+                false => Ok(()),
+            })
+            .map_err(|err| verification::explain(self, err))
     }
 
-    pub fn set_minimizable(&self, maximizable: bool) {
-        let style = WINDOW_STYLE(unsafe { GetWindowLongPtrW(self.hwnd, GWL_STYLE) } as _);
-        let new_style = if maximizable {
-            style | WS_MINIMIZEBOX
+    /// adds `flag` to the style if `enabled`, removes it otherwise, `property_key` names it in
+    /// the verification
+    fn set_style_flag(
+        &self,
+        flag: WINDOW_STYLE,
+        enabled: bool,
+        property_key: &'static str,
+    ) -> Result<()> {
+        self.remember_original_status(ModificationKind::Styles);
+        let style = || WINDOW_STYLE(unsafe { GetWindowLongPtrW(self.hwnd, GWL_STYLE) } as _);
+        let new_style = if enabled {
+            style() | flag
         } else {
-            style & !WS_MINIMIZEBOX
+            style() & !flag
         };
-        unsafe { SetWindowLongPtrW(self.hwnd, GWL_STYLE, new_style.0 as _) };
+        self.verified(
+            || set_window_long(self.hwnd, GWL_STYLE, new_style.0 as _),
+            || verification::verify_switch(self, property_key, enabled, style().contains(flag)),
+        )
+        .inspect(|()| self.update_lock(ModificationKind::Styles, |status| status.style = new_style))
+    }
+
+    pub fn set_maximizable(&self, maximizable: bool) -> Result<()> {
+        self.logged(format_args!("set maximizable {}", maximizable), || {
+            self.set_style_flag(WS_MAXIMIZEBOX, maximizable, "app.maximizable")
+        })
+    }
+
+    pub fn set_minimizable(&self, minimizable: bool) -> Result<()> {
+        self.logged(format_args!("set minimizable {}", minimizable), || {
+            self.set_style_flag(WS_MINIMIZEBOX, minimizable, "app.minimizable")
+        })
+    }
+
+    pub fn set_resizable(&self, resizable: bool) -> Result<()> {
+        self.logged(format_args!("set resizable {}", resizable), || {
+            self.set_style_flag(WS_THICKFRAME, resizable, "app.resizable")
+        })
     }
 
-    pub fn set_resizable(&self, resizable: bool) {
+    pub fn is_borderless_fullscreen(&self) -> bool {
+        borderless_fullscreen_storage().contains_key(&self.key())
+    }
+
+    pub fn is_top_most(&self) -> bool {
+        let ex_style = WINDOW_EX_STYLE(unsafe { GetWindowLongPtrW(self.hwnd, GWL_EXSTYLE) } as _);
+        ex_style.contains(WS_EX_TOPMOST)
+    }
+
+    pub fn current_status(&self) -> Result<WindowStatus> {
         let style = WINDOW_STYLE(unsafe { GetWindowLongPtrW(self.hwnd, GWL_STYLE) } as _);
-        let new_style = if resizable {
-            style | WS_THICKFRAME
-        } else {
-            style & !WS_THICKFRAME
+        let ex_style = WINDOW_EX_STYLE(unsafe { GetWindowLongPtrW(self.hwnd, GWL_EXSTYLE) } as _);
+        let [width, height] = self.get_size()?;
+        let [x, y] = self.get_position()?;
+        Ok(WindowStatus {
+            width,
+            height,
+            x,
+            y,
+            style,
+            ex_style,
+            top_most: ex_style.contains(WS_EX_TOPMOST),
+        })
+    }
+
+    /// Called before every modification, so that `restore_original_status` can undo all of them.
+    fn remember_original_status(&self, kind: ModificationKind) {
+        let mut storage = original_status_storage();
+        let (pid, hwnd) = self.key();
+        if let Some(index) = storage
+            .iter()
+            .position(|modified_window| modified_window.pid == pid && modified_window.hwnd == hwnd)
+        {
+            let mut modified_window = storage.remove(index);
+            modified_window.kinds.insert(kind);
+            storage.push(modified_window);
+            return;
+        }
+        let Ok(original_status) = self.current_status() else {
+            return;
         };
-        unsafe { SetWindowLongPtrW(self.hwnd, GWL_STYLE, new_style.0 as _) };
+        storage.push(ModifiedWindow {
+            pid,
+            hwnd,
+            title: self.title.clone(),
+            program_path: self.program_path.clone(),
+            kinds: BTreeSet::from([kind]),
+            original_status,
+        });
+    }
+
+    /// Drops `kind` from the remembered modifications without restoring it, the window is no
+    /// longer modified once no kind is left.
+    fn forget_modification(&self, kind: ModificationKind) {
+        let (pid, hwnd) = self.key();
+        let mut storage = original_status_storage();
+        storage
+            .iter_mut()
+            .find(|modified_window| modified_window.pid == pid && modified_window.hwnd == hwnd)
+            .map(|modified_window| modified_window.kinds.remove(&kind));
+        storage.retain(|modified_window| !modified_window.kinds.is_empty());
+    }
+
+    /// Moves what a lock of the window keeps of `kind` along with a modification made here, so
+    /// the lock doesn't set it back.
+    fn update_lock(&self, kind: ModificationKind, update: impl FnOnce(&mut WindowStatus)) {
+        lock::update_status(self.key(), kind, update);
+    }
+
+    pub fn is_modified(&self) -> bool {
+        let (pid, hwnd) = self.key();
+        original_status_storage()
+            .iter()
+            .any(|modified_window| modified_window.pid == pid && modified_window.hwnd == hwnd)
+    }
+
+    /// Restores size, position, styles and top-most state from before the first modification.
+    pub fn restore_original_status(&self) -> Result<()> {
+        self.logged(format_args!("restore original status"), || {
+            self.restore_modifications_inner(&BTreeSet::from(ModificationKind::ALL))
+        })
     }
 
-    pub fn set_borderless_fullscreen(&mut self) -> windows::core::Result<()> {
-        if self.borderless_fullscreen_storage.is_some() {
+    /// Restores what the `kinds` of modification changed to the status from before the first
+    /// modification, the window stays modified while other kinds are not restored.
+    pub fn restore_modifications(&self, kinds: &BTreeSet<ModificationKind>) -> Result<()> {
+        self.logged(format_args!("restore {:?}", kinds), || {
+            self.restore_modifications_inner(kinds)
+        })
+    }
+
+    fn restore_modifications_inner(&self, kinds: &BTreeSet<ModificationKind>) -> Result<()> {
+        // a lock would apply the modification again
+        lock::release(self.key(), kinds);
+        let (pid, hwnd) = self.key();
+        let Some((restored_kinds, original_status)) = ({
+            let mut storage = original_status_storage();
+            storage
+                .iter()
+                .position(|modified_window| {
+                    modified_window.pid == pid && modified_window.hwnd == hwnd
+                })
+                .map(|index| {
+                    let modified_window = &mut storage[index];
+                    let restored_kinds: BTreeSet<_> =
+                        modified_window.kinds.intersection(kinds).copied().collect();
+                    modified_window.kinds.retain(|kind| !kinds.contains(kind));
+                    let original_status = modified_window.original_status.clone();
+                    if modified_window.kinds.is_empty() {
+                        storage.remove(index);
+                    }
+                    (restored_kinds, original_status)
+                })
+        }) else {
+            return Ok(());
+        };
+        if restored_kinds.is_empty() {
             return Ok(());
         }
-        let style = WINDOW_STYLE(unsafe { GetWindowLongPtrW(self.hwnd, GWL_STYLE) } as _);
-        let ex_style = WINDOW_EX_STYLE(unsafe { GetWindowLongPtrW(self.hwnd, GWL_EXSTYLE) } as _);
-        let window_rect = self.get_window_rect()?;
-        let width = window_rect.right - window_rect.left;
-        let height = window_rect.bottom - window_rect.top;
-        let x = window_rect.left;
-        let y = window_rect.top;
-        let borderless_fullscreen_storage = Box::new(WindowStatus {
+        if restored_kinds.contains(&ModificationKind::BorderlessFullscreen) {
+            borderless_fullscreen_storage().remove(&self.key());
+        }
+        self.apply_status(&original_status, &restored_kinds)
+    }
+
+    /// Sets what the `kinds` of modification change to `status`.
+    fn apply_status(
+        &self,
+        status: &WindowStatus,
+        kinds: &BTreeSet<ModificationKind>,
+    ) -> Result<()> {
+        let WindowStatus {
             width,
             height,
             x,
             y,
             style,
             ex_style,
-        });
-        self.borderless_fullscreen_storage = Some(borderless_fullscreen_storage);
+            top_most,
+        } = *status;
+        // borderless fullscreen changes the styles and the geometry
+        let borderless_fullscreen = kinds.contains(&ModificationKind::BorderlessFullscreen);
+        if borderless_fullscreen || kinds.contains(&ModificationKind::Styles) {
+            set_window_long(self.hwnd, GWL_STYLE, style.0 as _)?;
+            set_window_long(self.hwnd, GWL_EXSTYLE, ex_style.0 as _)?;
+        }
+        let mut flags = verification::async_flag() | SWP_FRAMECHANGED;
+        if !borderless_fullscreen && !kinds.contains(&ModificationKind::Geometry) {
+            flags |= SWP_NOMOVE | SWP_NOSIZE;
+        }
+        let insert_after = kinds
+            .contains(&ModificationKind::TopMost)
+            .then_some(if top_most {
+                HWND_TOPMOST
+            } else {
+                HWND_NOTOPMOST
+            });
+        if insert_after.is_none() {
+            flags |= SWP_NOZORDER;
+        }
+        unsafe { SetWindowPos(self.hwnd, insert_after, x, y, width, height, flags) }
+            .map_err(Error::from)
+    }
+
+    /// The `kinds` in which the window differs from `status`, ignoring the transient styles.
+    pub fn drifted_kinds(
+        &self,
+        status: &WindowStatus,
+        kinds: &BTreeSet<ModificationKind>,
+    ) -> Result<BTreeSet<ModificationKind>> {
+        let current = self.current_status()?;
+        Ok(kinds
+            .iter()
+            .copied()
+            .filter(|kind| match kind {
+                ModificationKind::Geometry => {
+                    [current.x, current.y, current.width, current.height]
+                        != [status.x, status.y, status.width, status.height]
+                }
+                ModificationKind::TopMost => current.top_most != status.top_most,
+                ModificationKind::Styles => {
+                    current.style & !TRANSIENT_STYLES != status.style & !TRANSIENT_STYLES
+                        || current.ex_style & !WS_EX_TOPMOST != status.ex_style & !WS_EX_TOPMOST
+                }
+                ModificationKind::BorderlessFullscreen => false,
+            })
+            .collect())
+    }
+
+    /// Sets the `kinds` of `status` again without remembering it as a modification, keeping the
+    /// transient styles of the window.
+    pub fn reapply_status(
+        &self,
+        status: &WindowStatus,
+        kinds: &BTreeSet<ModificationKind>,
+    ) -> Result<()> {
+        self.logged(format_args!("reapply {:?}", kinds), || {
+            let current = self.current_status()?;
+            let status = WindowStatus {
+                style: (status.style & !TRANSIENT_STYLES) | (current.style & TRANSIENT_STYLES),
+                ex_style: (status.ex_style & !WS_EX_TOPMOST) | (current.ex_style & WS_EX_TOPMOST),
+                ..status.clone()
+            };
+            self.apply_status(&status, kinds)
+        })
+    }
+
+    pub fn set_borderless_fullscreen(&self) -> Result<()> {
+        self.logged(format_args!("set borderless fullscreen"), || {
+            self.set_borderless_fullscreen_inner()
+        })
+    }
+
+    fn set_borderless_fullscreen_inner(&self) -> Result<()> {
+        if self.is_borderless_fullscreen() {
+            return Ok(());
+        }
+        let status = self.current_status()?;
+        let WindowStatus {
+            style, ex_style, ..
+        } = status;
         let hmonitor = unsafe { MonitorFromWindow(self.hwnd, MONITOR_DEFAULTTONEAREST) };
         let mut monitor_info = MONITORINFO {
             cbSize: std::mem::size_of::<MONITORINFO>() as u32,
             ..Default::default()
         };
-        unsafe { GetMonitorInfoW(hmonitor, &raw mut monitor_info) }.ok()?;
+        if !unsafe { GetMonitorInfoW(hmonitor, &raw mut monitor_info) }.as_bool() {
+            return Err(Error::InvalidMonitor);
+        }
         let monitor_rect = monitor_info.rcMonitor;
         let monitor_width = monitor_rect.right - monitor_rect.left;
         let monitor_height = monitor_rect.bottom - monitor_rect.top;
-        unsafe {
-            SetWindowLongPtrW(
-                self.hwnd,
-                GWL_STYLE,
-                ((style & !WS_OVERLAPPEDWINDOW) | WS_POPUP).0 as _,
-            );
-            SetWindowLongPtrW(
-                self.hwnd,
-                GWL_EXSTYLE,
-                (ex_style & !(WS_EX_APPWINDOW | WS_EX_WINDOWEDGE)).0 as _,
-            );
+        self.remember_original_status(ModificationKind::BorderlessFullscreen);
+        let fullscreen_style = (style & !WS_OVERLAPPEDWINDOW) | WS_POPUP;
+        let fullscreen_ex_style = ex_style & !(WS_EX_APPWINDOW | WS_EX_WINDOWEDGE);
+        let result = set_window_long(self.hwnd, GWL_STYLE, fullscreen_style.0 as _)
+            .and_then(|()| set_window_long(self.hwnd, GWL_EXSTYLE, fullscreen_ex_style.0 as _))
+            .map_err(Error::from)
+            .and_then(|()| self.set_size([monitor_width, monitor_height]))
+            .and_then(|()| self.set_position([0, 0]));
+        match &result {
+            // only a window that went fullscreen is restored from it
+            Ok([x, y]) => {
+                borderless_fullscreen_storage().insert(self.key(), status);
+                self.update_lock(ModificationKind::Geometry, |status| {
+                    [status.x, status.y, status.width, status.height] =
+                        [*x, *y, monitor_width, monitor_height]
+                });
+                self.update_lock(ModificationKind::Styles, |status| {
+                    status.style = fullscreen_style;
+                    status.ex_style = fullscreen_ex_style;
+                });
+            }
+            Err(_) => {
+                let _ = self.apply_status(
+                    &status,
+                    &BTreeSet::from([ModificationKind::Geometry, ModificationKind::Styles]),
+                );
+                self.forget_modification(ModificationKind::BorderlessFullscreen);
+            }
         }
-        self.resize([monitor_width, monitor_height])?;
-        self.move_to([0, 0])
+        result.map(|_| ())
+    }
+
+    pub fn restore_from_borderless_fullscreen(&self) -> Result<()> {
+        self.logged(format_args!("restore from borderless fullscreen"), || {
+            self.restore_from_borderless_fullscreen_inner()
+        })
     }
 
-    pub fn restore_from_borderless_fullscreen(&mut self) -> windows::core::Result<()> {
-        if self.borderless_fullscreen_storage.is_none() {
+    fn restore_from_borderless_fullscreen_inner(&self) -> Result<()> {
+        lock::release(
+            self.key(),
+            &BTreeSet::from([ModificationKind::BorderlessFullscreen]),
+        );
+        let Some(status) = borderless_fullscreen_storage().remove(&self.key()) else {
             return Ok(());
-        }
-        let borderless_fullscreen_storage = self.borderless_fullscreen_storage.take().unwrap();
+        };
+        self.forget_modification(ModificationKind::BorderlessFullscreen);
         let WindowStatus {
             width,
             height,
@@ -271,11 +890,12 @@ impl WindowInfo {
             y,
             style,
             ex_style,
-        } = *borderless_fullscreen_storage;
-        unsafe { SetWindowLongPtrW(self.hwnd, GWL_STYLE, style.0 as _) };
-        unsafe { SetWindowLongPtrW(self.hwnd, GWL_EXSTYLE, ex_style.0 as _) };
-        self.resize([width, height])?;
-        self.move_to([x, y])
+            ..
+        } = status;
+        set_window_long(self.hwnd, GWL_STYLE, style.0 as _)?;
+        set_window_long(self.hwnd, GWL_EXSTYLE, ex_style.0 as _)?;
+        self.set_size([width, height])?;
+        self.set_position([x, y]).map(|_| ())
     }
 }
 
@@ -304,26 +924,36 @@ impl WindowInfo {
                         .ui(ui);
                         ui.end_row();
                         egui::Label::new(
-                            egui::RichText::new(format!("标题: {}", self.title)).size(FONT_SIZE),
+                            egui::RichText::new(i18n::tr_format(
+                                "window_info.title",
+                                &[("title", &self.title)],
+                            ))
+                            .size(FONT_SIZE),
                         )
                         .selectable(true)
                         .ui(ui);
                         ui.end_row();
                         egui::Label::new(
-                            egui::RichText::new(format!("路径: {}", self.program_path))
-                                .size(FONT_SIZE),
+                            egui::RichText::new(i18n::tr_format(
+                                "window_info.path",
+                                &[("path", &self.program_path)],
+                            ))
+                            .size(FONT_SIZE),
                         )
                         .selectable(true)
                         .ui(ui);
                         ui.end_row();
-                        let state_text = if self.borderless_fullscreen_storage.is_some() {
-                            "是"
+                        let state_text = if self.is_borderless_fullscreen() {
+                            i18n::tr("common.yes")
                         } else {
-                            "否"
+                            i18n::tr("common.no")
                         };
                         egui::Label::new(
-                            egui::RichText::new(format!("强制无边框全屏状态: {}", state_text))
-                                .size(FONT_SIZE),
+                            egui::RichText::new(i18n::tr_format(
+                                "window_info.borderless_fullscreen",
+                                &[("state", &state_text)],
+                            ))
+                            .size(FONT_SIZE),
                         )
                         .selectable(true)
                         .ui(ui);
@@ -337,10 +967,13 @@ impl WindowInfo {
         let mut selected = false;
         ui.horizontal(|ui| {
             ui.vertical(|ui| {
-                ui.add_space(60.0);
-                selected = egui::Button::new(egui::RichText::new("选择").size(FONT_SIZE))
-                    .ui(ui)
-                    .clicked();
+                ui.add_space(60.0 - icon::ICON_SIZE);
+                icon::show_icon(ui, self);
+                selected = egui::Button::new(
+                    egui::RichText::new(i18n::tr("common.select")).size(FONT_SIZE),
+                )
+                .ui(ui)
+                .clicked();
             });
             self.show_ui(ui);
         });
@@ -348,71 +981,75 @@ impl WindowInfo {
     }
 }
 
-fn enumerate_windows(window_info_list: &mut Vec<WindowInfo>) {
-    unsafe extern "system" fn enum_child_windows_proc(hwnd: HWND, lparam: LPARAM) -> BOOL {
-        const DEFAULT_RETURN_VALUE: BOOL = BOOL(1);
-        if !unsafe { IsWindowVisible(hwnd) }.as_bool() {
-            return DEFAULT_RETURN_VALUE;
-        }
-        let pid = {
-            let mut pid = 0;
-            unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
-            pid
-        };
-        if pid == 0 {
-            return DEFAULT_RETURN_VALUE;
-        }
-        let window_info_list = unsafe { &mut *(lparam.0 as *mut Vec<WindowInfo>) };
-        if window_info_list
-            .iter()
-            .any(|window_info| window_info.pid == pid && window_info.hwnd == hwnd)
-        {
-            return DEFAULT_RETURN_VALUE;
+/// the visible windows with a title, sorted by pid
+fn enumerate_windows() -> Vec<WindowInfo> {
+    let mut window_info_list = Vec::new();
+    enumeration::enumerate(&HashSet::new(), |windows| {
+        window_info_list.extend(windows.into_iter().map(|window| window.into_window_info()))
+    });
+    window_info_list.sort_by_key(|x: &WindowInfo| x.pid);
+    window_info_list
+}
+
+/// visible windows whose title or program path contains `text`, case-insensitive
+pub fn find_windows(text: &str) -> Vec<WindowInfo> {
+    enumerate_windows()
+        .into_iter()
+        .filter(|window_info| window_info.matches(text))
+        .collect()
+}
+
+fn is_valid_window(pid: u32, hwnd: HWND) -> bool {
+    let is_window = unsafe { IsWindow(Some(hwnd)) }.as_bool();
+    if !is_window {
+        return false;
+    }
+    window_pid(hwnd) == pid
+}
+
+/// `[x, y, width, height]`
+fn rect_to_array(rect: windows::Win32::Foundation::RECT) -> [i32; 4] {
+    [
+        rect.left,
+        rect.top,
+        rect.right - rect.left,
+        rect.bottom - rect.top,
+    ]
+}
+
+/// `SetWindowLongPtrW` returns the previous value, which may be 0, so a failure is only told by
+/// the last error.
+fn set_window_long(
+    hwnd: HWND,
+    index: WINDOW_LONG_PTR_INDEX,
+    value: isize,
+) -> windows::core::Result<()> {
+    unsafe { SetLastError(WIN32_ERROR(0)) };
+    if unsafe { SetWindowLongPtrW(hwnd, index, value) } == 0 {
+        let err = windows::core::Error::from_win32();
+        if err.code().is_err() {
+            return Err(err);
         }
-        let title = {
-            let mut title_buf = [0u16; 1024];
-            let title_length = unsafe { GetWindowTextW(hwnd, &mut title_buf) };
-            if title_length == 0 {
-                return DEFAULT_RETURN_VALUE;
-            }
-            String::from_utf16_lossy(&title_buf[..title_length as usize])
-        };
-        let program_path = {
-            let mut program_path_buf = [0u16; 1024];
-            let Ok(process_handle) = (unsafe {
-                OpenProcess(
-                    PROCESS_QUERY_LIMITED_INFORMATION | PROCESS_VM_READ,
-                    false,
-                    pid,
-                )
-            }) else {
-                return DEFAULT_RETURN_VALUE;
-            };
-            let program_path_length =
-                unsafe { GetModuleFileNameExW(Some(process_handle), None, &mut program_path_buf) };
-            unsafe { CloseHandle(process_handle) }.unwrap();
-            String::from_utf16_lossy(&program_path_buf[..program_path_length as usize])
-        };
-        window_info_list.push(WindowInfo {
-            pid,
-            hwnd,
-            title,
-            program_path,
-            borderless_fullscreen_storage: None,
-        });
-        DEFAULT_RETURN_VALUE
     }
+    Ok(())
+}
 
-    window_info_list.retain(|window_info| window_info.is_valid());
+pub fn window_pid(hwnd: HWND) -> u32 {
+    let mut pid = 0;
+    unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+    pid
+}
 
-    let _ = unsafe {
-        EnumChildWindows(
-            None,
-            Some(enum_child_windows_proc),
-            LPARAM(window_info_list as *mut _ as _),
-        )
-    };
-    window_info_list.sort_by_key(|x: &WindowInfo| x.pid);
+pub fn window_title(hwnd: HWND) -> String {
+    let mut title_buf = [0u16; 1024];
+    let title_length = unsafe { GetWindowTextW(hwnd, &mut title_buf) };
+    String::from_utf16_lossy(&title_buf[..title_length as usize])
+}
+
+pub fn window_class_name(hwnd: HWND) -> String {
+    let mut class_name_buf = [0u16; 256];
+    let class_name_length = unsafe { GetClassNameW(hwnd, &mut class_name_buf) };
+    String::from_utf16_lossy(&class_name_buf[..class_name_length as usize])
 }
 
 mod test {
@@ -421,9 +1058,7 @@ mod test {
 
     #[test]
     fn test_enumerate_windows() {
-        let mut window_info_list = Vec::new();
-        enumerate_windows(&mut window_info_list);
-        window_info_list.iter().for_each(|window_info| {
+        enumerate_windows().iter().for_each(|window_info| {
             println!("{:?}", window_info);
         });
     }
@@ -0,0 +1,184 @@
+use std::{
+    cell::{Cell, RefCell},
+    sync::mpsc::{self, Receiver, Sender},
+    thread::JoinHandle,
+};
+
+use windows::Win32::{
+    Foundation::{E_UNEXPECTED, HWND, LPARAM, LRESULT, POINT, WPARAM},
+    System::{LibraryLoader::GetModuleHandleW, Threading::GetCurrentThreadId},
+    UI::{
+        Input::KeyboardAndMouse::{GetAsyncKeyState, VK_ESCAPE},
+        WindowsAndMessaging::{
+            CallNextHookEx, GA_ROOT, GetAncestor, GetCursorPos, GetMessageW, HC_ACTION, MSG,
+            MSLLHOOKSTRUCT, PostThreadMessageW, SetWindowsHookExW, UnhookWindowsHookEx,
+            WH_MOUSE_LL, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_QUIT, WindowFromPoint,
+        },
+    },
+};
+
+use crate::window_modifier::{WindowInfo, window_pid};
+
+pub enum PickerEvent {
+    None,
+    Picked(WindowInfo),
+    Cancelled,
+}
+
+/// Polls the global cursor and keyboard state, so windows of other processes can be picked
+/// although they receive the mouse input. The picking click itself is swallowed by a low-level
+/// mouse hook, so it doesn't reach the picked window.
+#[derive(Debug)]
+pub struct WindowPicker {
+    hovered: Option<WindowInfo>,
+    hook: MouseHook,
+}
+
+impl WindowPicker {
+    pub fn new() -> windows::core::Result<Self> {
+        Ok(Self {
+            hovered: None,
+            hook: MouseHook::spawn()?,
+        })
+    }
+
+    pub fn hovered(&self) -> Option<&WindowInfo> {
+        self.hovered.as_ref()
+    }
+
+    pub fn update(&mut self) -> PickerEvent {
+        if is_key_down(VK_ESCAPE.0) {
+            return PickerEvent::Cancelled;
+        }
+        self.update_hovered();
+        self.hook
+            .clicked_receiver
+            .try_iter()
+            .last()
+            .and_then(|hwnd| WindowInfo::from_hwnd(HWND(hwnd as _)))
+            .map(PickerEvent::Picked)
+            .unwrap_or(PickerEvent::None)
+    }
+
+    fn update_hovered(&mut self) {
+        let mut point = POINT::default();
+        if unsafe { GetCursorPos(&mut point) }.is_err() {
+            return;
+        }
+        let Some(hwnd) = foreign_window_from_point(point) else {
+            self.hovered = None;
+            return;
+        };
+        if self
+            .hovered
+            .as_ref()
+            .is_some_and(|window_info| window_info.hwnd == hwnd)
+        {
+            return;
+        }
+        self.hovered = WindowInfo::from_hwnd(hwnd);
+    }
+}
+
+fn is_key_down(virtual_key: u16) -> bool {
+    (unsafe { GetAsyncKeyState(virtual_key as i32) } as u16 & 0x8000) != 0
+}
+
+/// The top-level window under `point`, `None` over our own windows, which can't be picked.
+fn foreign_window_from_point(point: POINT) -> Option<HWND> {
+    let hwnd = unsafe { WindowFromPoint(point) };
+    if hwnd.is_invalid() {
+        return None;
+    }
+    let root = unsafe { GetAncestor(hwnd, GA_ROOT) };
+    let hwnd = if root.is_invalid() { hwnd } else { root };
+    (window_pid(hwnd) != std::process::id()).then_some(hwnd)
+}
+
+/// Owns a thread with a `WH_MOUSE_LL` hook while picking. A left click on a window of another
+/// process is swallowed and its top-level window is sent, clicks on our own windows pass.
+#[derive(Debug)]
+struct MouseHook {
+    thread_id: u32,
+    /// `HWND` of the clicked windows, `HWND` is not `Send`
+    clicked_receiver: Receiver<usize>,
+    join_handle: Option<JoinHandle<()>>,
+}
+
+impl MouseHook {
+    fn spawn() -> windows::core::Result<Self> {
+        let (installed_sender, installed_receiver) = mpsc::channel();
+        let (clicked_sender, clicked_receiver) = mpsc::channel();
+        let join_handle = std::thread::Builder::new()
+            .name("window picker".to_string())
+            .spawn(move || mouse_hook_thread(installed_sender, clicked_sender))
+            .unwrap();
+        let thread_id = installed_receiver
+            .recv()
+            .unwrap_or_else(|_| Err(E_UNEXPECTED.into()))?;
+        Ok(Self {
+            thread_id,
+            clicked_receiver,
+            join_handle: Some(join_handle),
+        })
+    }
+}
+
+impl Drop for MouseHook {
+    fn drop(&mut self) {
+        let _ = unsafe { PostThreadMessageW(self.thread_id, WM_QUIT, WPARAM(0), LPARAM(0)) };
+        self.join_handle
+            .take()
+            .map(|join_handle| join_handle.join());
+    }
+}
+
+thread_local! {
+    /// the hook procedure runs on the thread that installed it
+    static CLICKED_SENDER: RefCell<Option<Sender<usize>>> = const { RefCell::new(None) };
+    /// the button up of a swallowed click is swallowed too
+    static SWALLOWING: Cell<bool> = const { Cell::new(false) };
+}
+
+fn mouse_hook_thread(
+    installed_sender: Sender<windows::core::Result<u32>>,
+    clicked_sender: Sender<usize>,
+) {
+    unsafe extern "system" fn mouse_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+        if code == HC_ACTION as i32 {
+            let point = unsafe { &*(lparam.0 as *const MSLLHOOKSTRUCT) }.pt;
+            match wparam.0 as u32 {
+                WM_LBUTTONDOWN => {
+                    if let Some(hwnd) = foreign_window_from_point(point) {
+                        CLICKED_SENDER.with_borrow(|clicked_sender| {
+                            clicked_sender
+                                .as_ref()
+                                .map(|clicked_sender| clicked_sender.send(hwnd.0 as usize))
+                        });
+                        SWALLOWING.set(true);
+                        return LRESULT(1);
+                    }
+                }
+                WM_LBUTTONUP if SWALLOWING.replace(false) => return LRESULT(1),
+                _ => {}
+            }
+        }
+        unsafe { CallNextHookEx(None, code, wparam, lparam) }
+    }
+
+    CLICKED_SENDER.set(Some(clicked_sender));
+    let hook = unsafe { GetModuleHandleW(None) }.and_then(|instance| unsafe {
+        SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_proc), Some(instance.into()), 0)
+    });
+    let hook = match hook {
+        Ok(hook) => hook,
+        Err(err) => {
+            let _ = installed_sender.send(Err(err));
+            return;
+        }
+    };
+    let _ = installed_sender.send(Ok(unsafe { GetCurrentThreadId() }));
+    let mut msg = MSG::default();
+    while unsafe { GetMessageW(&mut msg, None, 0, 0) }.0 > 0 {}
+    let _ = unsafe { UnhookWindowsHookEx(hook) };
+}
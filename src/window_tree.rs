@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+
+use egui::Widget;
+use windows::{
+    Win32::{
+        Foundation::{HWND, LPARAM, RECT},
+        UI::WindowsAndMessaging::{
+            EnumChildWindows, EnumWindows, GA_PARENT, GW_OWNER, GetAncestor, GetWindow,
+            GetWindowRect, IsWindowVisible,
+        },
+    },
+    core::BOOL,
+};
+
+use crate::{
+    i18n,
+    window_modifier::{window_class_name, window_pid, window_title},
+};
+
+#[derive(Debug, Clone)]
+pub struct WindowNode {
+    pub hwnd: HWND,
+    pub pid: u32,
+    pub class_name: String,
+    pub title: String,
+    pub rect: RECT,
+    pub visible: bool,
+    /// windows whose owner is this window
+    pub owned_windows: Vec<WindowNode>,
+    /// real child windows (`WS_CHILD`)
+    pub child_windows: Vec<WindowNode>,
+}
+
+impl WindowNode {
+    fn new(hwnd: HWND) -> Self {
+        let mut rect = Default::default();
+        let _ = unsafe { GetWindowRect(hwnd, &mut rect) };
+        Self {
+            hwnd,
+            pid: window_pid(hwnd),
+            class_name: window_class_name(hwnd),
+            title: window_title(hwnd),
+            rect,
+            visible: unsafe { IsWindowVisible(hwnd) }.as_bool(),
+            owned_windows: Vec::new(),
+            child_windows: Vec::new(),
+        }
+    }
+
+    fn with_child_windows(mut self) -> Self {
+        let descendants = enumerate_descendants(self.hwnd);
+        let mut children_of: HashMap<usize, Vec<HWND>> = HashMap::new();
+        descendants.iter().for_each(|&hwnd| {
+            let parent = unsafe { GetAncestor(hwnd, GA_PARENT) };
+            children_of.entry(parent.0 as usize).or_default().push(hwnd);
+        });
+        fn build(hwnd: HWND, children_of: &HashMap<usize, Vec<HWND>>) -> Vec<WindowNode> {
+            children_of
+                .get(&(hwnd.0 as usize))
+                .map(|children| {
+                    children
+                        .iter()
+                        .map(|&child| {
+                            let mut node = WindowNode::new(child);
+                            node.child_windows = build(child, children_of);
+                            node
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        }
+        self.child_windows = build(self.hwnd, &children_of);
+        self
+    }
+
+    /// returns the HWND of the node whose select button was clicked
+    pub fn show_ui(&self, ui: &mut egui::Ui) -> Option<HWND> {
+        const FONT_SIZE: f32 = crate::app::App::FONT_SIZE;
+        let RECT {
+            left,
+            top,
+            right,
+            bottom,
+        } = self.rect;
+        let header_text = format!(
+            "{} \"{}\" HWND: {} PID: {} [{}, {}, {}x{}]{}",
+            self.class_name,
+            self.title,
+            self.hwnd.0 as usize,
+            self.pid,
+            left,
+            top,
+            right - left,
+            bottom - top,
+            if self.visible {
+                String::new()
+            } else {
+                format!(" {}", i18n::tr("window_tree.invisible"))
+            },
+        );
+        let mut selected = None;
+        ui.horizontal(|ui| {
+            egui::Button::new(egui::RichText::new(i18n::tr("common.select")).size(FONT_SIZE))
+                .ui(ui)
+                .clicked()
+                .then(|| selected = Some(self.hwnd));
+            let has_subtree = !self.owned_windows.is_empty() || !self.child_windows.is_empty();
+            if !has_subtree {
+                egui::Label::new(egui::RichText::new(&header_text).size(FONT_SIZE))
+                    .selectable(true)
+                    .ui(ui);
+                return;
+            }
+            ui.vertical(|ui| {
+                egui::CollapsingHeader::new(egui::RichText::new(&header_text).size(FONT_SIZE))
+                    .id_salt(self.hwnd.0 as usize)
+                    .show(ui, |ui| {
+                        [
+                            (i18n::tr("window_tree.owned_windows"), &self.owned_windows),
+                            (i18n::tr("window_tree.child_windows"), &self.child_windows),
+                        ]
+                        .into_iter()
+                        .filter(|(_, nodes)| !nodes.is_empty())
+                        .for_each(|(label, nodes)| {
+                            egui::Label::new(
+                                egui::RichText::new(format!("{}: {}", label, nodes.len()))
+                                    .size(FONT_SIZE),
+                            )
+                            .ui(ui);
+                            ui.indent(label, |ui| {
+                                nodes.iter().for_each(|node| {
+                                    if let Some(hwnd) = node.show_ui(ui) {
+                                        selected = Some(hwnd);
+                                    }
+                                });
+                            });
+                        });
+                    });
+            });
+        });
+        selected
+    }
+}
+
+/// Top-level windows arranged by owner, each with its real child windows.
+#[derive(Debug, Default)]
+pub struct WindowTree {
+    roots: Vec<WindowNode>,
+}
+
+impl WindowTree {
+    pub fn build(include_invisible: bool) -> Self {
+        let top_level_windows: Vec<HWND> = enumerate_top_level_windows()
+            .into_iter()
+            .filter(|&hwnd| include_invisible || unsafe { IsWindowVisible(hwnd) }.as_bool())
+            .collect();
+        let mut owned_by: HashMap<usize, Vec<HWND>> = HashMap::new();
+        let mut roots = Vec::new();
+        top_level_windows
+            .iter()
+            .for_each(|&hwnd| match unsafe { GetWindow(hwnd, GW_OWNER) } {
+                Ok(owner) if top_level_windows.contains(&owner) => {
+                    owned_by.entry(owner.0 as usize).or_default().push(hwnd)
+                }
+                _ => roots.push(hwnd),
+            });
+        fn build(hwnd: HWND, owned_by: &HashMap<usize, Vec<HWND>>) -> WindowNode {
+            let mut node = WindowNode::new(hwnd).with_child_windows();
+            node.owned_windows = owned_by
+                .get(&(hwnd.0 as usize))
+                .map(|owned| owned.iter().map(|&hwnd| build(hwnd, owned_by)).collect())
+                .unwrap_or_default();
+            node
+        }
+        let roots = roots
+            .into_iter()
+            .map(|hwnd| build(hwnd, &owned_by))
+            .collect();
+        Self { roots }
+    }
+
+    /// returns the HWND of the node whose select button was clicked
+    pub fn show_ui(&self, ui: &mut egui::Ui) -> Option<HWND> {
+        let mut selected = None;
+        self.roots.iter().for_each(|node| {
+            if let Some(hwnd) = node.show_ui(ui) {
+                selected = Some(hwnd);
+            }
+        });
+        selected
+    }
+}
+
+unsafe extern "system" fn push_hwnd_proc(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let hwnd_list = unsafe { &mut *(lparam.0 as *mut Vec<HWND>) };
+    hwnd_list.push(hwnd);
+    BOOL(1)
+}
+
+pub fn enumerate_top_level_windows() -> Vec<HWND> {
+    let mut hwnd_list = Vec::with_capacity(1024);
+    let _ = unsafe {
+        EnumWindows(
+            Some(push_hwnd_proc),
+            LPARAM(&mut hwnd_list as *mut Vec<HWND> as _),
+        )
+    };
+    hwnd_list
+}
+
+/// all descendants of `hwnd`, not only the direct children
+fn enumerate_descendants(hwnd: HWND) -> Vec<HWND> {
+    let mut hwnd_list = Vec::new();
+    let _ = unsafe {
+        EnumChildWindows(
+            Some(hwnd),
+            Some(push_hwnd_proc),
+            LPARAM(&mut hwnd_list as *mut Vec<HWND> as _),
+        )
+    };
+    hwnd_list
+}